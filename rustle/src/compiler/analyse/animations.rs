@@ -0,0 +1,74 @@
+use super::errors::Error;
+use crate::compiler::Fragment;
+
+/// Checks that the elements with an `animate:` directive are
+/// the only child of a keyed `{#each}` block, whitespace aside.
+pub fn check_animations(fragments: &[Fragment]) -> Result<(), Error> {
+    check_placement(fragments, false)
+}
+
+fn check_placement(fragments: &[Fragment], in_keyed_each: bool) -> Result<(), Error> {
+    let children = fragments
+        .iter()
+        .filter(|fragment| !matches!(fragment, Fragment::Text(text) if text.data.trim().is_empty()))
+        .count();
+
+    for fragment in fragments {
+        match fragment {
+            Fragment::Element(element) => {
+                if let Some(animation) = &element.animation {
+                    if !in_keyed_each {
+                        return Err(Error::invalid_animation_placement(animation.span));
+                    }
+                    if children > 1 {
+                        return Err(Error::invalid_animation_sole(animation.span));
+                    }
+                }
+                check_placement(&element.fragments, false)?;
+            }
+            Fragment::IfBlock(block) => {
+                check_placement(&block.fragments, false)?;
+                check_placement(block.else_fragments.as_deref().unwrap_or_default(), false)?;
+            }
+            Fragment::EachBlock(block) => {
+                check_placement(&block.fragments, block.key.is_some())?;
+                check_placement(block.else_fragments.as_deref().unwrap_or_default(), false)?;
+            }
+            Fragment::KeyBlock(block) => check_placement(&block.fragments, false)?,
+            Fragment::AwaitBlock(block) => {
+                check_placement(&block.pending_fragments, false)?;
+                check_placement(block.then_fragments.as_deref().unwrap_or_default(), false)?;
+                check_placement(block.catch_fragments.as_deref().unwrap_or_default(), false)?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::parse::Parser;
+
+    #[test]
+    fn test_check_animations() {
+        let source = "<script>let items = [];</script>
+            {#each items as item (item)}
+                <li animate:flip>{item}</li>
+            {/each}";
+        let ast = Parser::new(source).parse().unwrap();
+        assert!(analyse(&ast).is_ok());
+
+        let cases = [
+            ("<div animate:flip />", "invalid-animation"),
+            ("{#each [] as item}<li animate:flip />{/each}", "invalid-animation"),
+            ("{#each [] as item (item)}<li animate:flip /><li />{/each}", "invalid-animation"),
+        ];
+        for (source, code) in cases {
+            let ast = Parser::new(source).parse().unwrap();
+            assert_eq!(analyse(&ast).err().unwrap().code, code);
+        }
+    }
+}
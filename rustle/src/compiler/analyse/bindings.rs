@@ -0,0 +1,253 @@
+use swc_common::Spanned;
+use swc_ecma_ast::{
+    Decl, ExportDecl, Expr, Module, ModuleDecl, ModuleItem, Pat, Stmt, VarDeclKind,
+};
+
+use super::errors::Error;
+use super::extract_dependencies::extract_dependencies;
+use super::extract_variables::extract_pattern_names;
+use crate::compiler::{Fragment, RustleAttribute};
+
+/// The properties of the window `<svelte:window>` can bind
+const WINDOW_BINDINGS: [&str; 8] = [
+    "innerWidth",
+    "innerHeight",
+    "outerWidth",
+    "outerHeight",
+    "scrollX",
+    "scrollY",
+    "online",
+    "devicePixelRatio",
+];
+
+/// A name declared by an enclosing `{#each}` or `{#await}` block
+#[derive(Clone)]
+struct BlockName {
+    name: String,
+    /// The top-level variables a binding to the name changes,
+    /// the ones the list of an `{#each}` block reads
+    changes: Vec<String>,
+    /// Whether the name itself can be bound, true for the item of an
+    /// `{#each}` block over a variable, assigned back to the list
+    assignable: bool,
+}
+
+/// Returns the top-level variables bound with `bind:` directives,
+/// checking that each of them is declared and can be assigned.
+///
+/// A binding to a name of an enclosing block changes the variables
+/// the block reads its items from.
+pub fn extract_bindings(
+    fragments: &[Fragment],
+    script: &Module,
+    variables: &[String],
+) -> Result<Vec<String>, Error> {
+    let constants = extract_constants(script);
+
+    let mut bindings = Vec::new();
+    collect_bindings(fragments, &[], variables, &mut bindings);
+
+    let mut names = Vec::new();
+    for (element, attr, scope) in bindings {
+        let expr = &attr.value;
+        let binding = attr.name.trim_start_matches("bind:");
+        if element == "svelte:window" && !WINDOW_BINDINGS.contains(&binding) {
+            return Err(Error::invalid_binding_window(binding, expr.span()));
+        }
+        let name = match binding_root(expr) {
+            Some(name) => name,
+            None => return Err(Error::invalid_binding_value(expr.span())),
+        };
+        if let Some(block_name) = scope.iter().rev().find(|block_name| block_name.name == name) {
+            if matches!(expr, Expr::Ident(_)) && !block_name.assignable {
+                return Err(Error::invalid_binding_block(&name, expr.span()));
+            }
+            for name in &block_name.changes {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            continue;
+        }
+        if constants.contains(&name) {
+            return Err(Error::invalid_binding_const(expr.span()));
+        }
+        if !variables.contains(&name) {
+            return Err(Error::binding_undeclared(&name, expr.span()));
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    Ok(names)
+}
+
+/// Returns the variable a bound expression assigns,
+/// `user.address.city` -> `user`
+pub fn binding_root(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Member(member) => binding_root(&member.obj),
+        Expr::Ident(ident) => Some(ident.sym.to_string()),
+        _ => None,
+    }
+}
+
+fn extract_constants(script: &Module) -> Vec<String> {
+    script
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl)))
+            | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+                decl: Decl::Var(var_decl),
+                ..
+            })) if var_decl.kind == VarDeclKind::Const => Some(var_decl),
+            _ => None,
+        })
+        .flat_map(|var_decl| &var_decl.decls)
+        .flat_map(|declarator| extract_pattern_names(&declarator.name))
+        .collect()
+}
+
+/// Collects the `bind:` attributes along with the name of their
+/// element and the names declared by the enclosing blocks
+fn collect_bindings<'a>(
+    fragments: &'a [Fragment],
+    scope: &[BlockName],
+    variables: &[String],
+    bindings: &mut Vec<(&'a str, &'a RustleAttribute, Vec<BlockName>)>,
+) {
+    for fragment in fragments {
+        match fragment {
+            Fragment::Element(element) => {
+                bindings.extend(
+                    element
+                        .attributes
+                        .iter()
+                        .filter(|attr| attr.name.starts_with("bind:"))
+                        .map(|attr| (element.name.as_str(), attr, scope.to_vec())),
+                );
+                collect_bindings(&element.fragments, scope, variables, bindings);
+            }
+            Fragment::IfBlock(block) => {
+                collect_bindings(&block.fragments, scope, variables, bindings);
+                let else_fragments = block.else_fragments.as_deref().unwrap_or_default();
+                collect_bindings(else_fragments, scope, variables, bindings);
+            }
+            Fragment::EachBlock(block) => {
+                // the items belong to the variables the list is read from
+                let mut changes = Vec::new();
+                for name in extract_dependencies(&block.expression) {
+                    match scope.iter().rev().find(|block_name| block_name.name == name) {
+                        Some(block_name) => changes.extend(block_name.changes.clone()),
+                        None if variables.contains(&name) => changes.push(name),
+                        None => (),
+                    }
+                }
+                let list = matches!(block.expression.unwrap_parens(), Expr::Ident(_) | Expr::Member(_));
+
+                let mut block_scope = scope.to_vec();
+                block_scope.extend(extract_pattern_names(&block.context).into_iter().map(|name| {
+                    BlockName {
+                        name,
+                        changes: changes.clone(),
+                        assignable: list && matches!(block.context, Pat::Ident(_)),
+                    }
+                }));
+                block_scope.extend(block.index.iter().map(|name| BlockName {
+                    name: name.clone(),
+                    changes: changes.clone(),
+                    assignable: false,
+                }));
+                collect_bindings(&block.fragments, &block_scope, variables, bindings);
+                let else_fragments = block.else_fragments.as_deref().unwrap_or_default();
+                collect_bindings(else_fragments, scope, variables, bindings);
+            }
+            Fragment::KeyBlock(block) => collect_bindings(&block.fragments, scope, variables, bindings),
+            Fragment::AwaitBlock(block) => {
+                collect_bindings(&block.pending_fragments, scope, variables, bindings);
+                let branches = [(&block.value, &block.then_fragments), (&block.error, &block.catch_fragments)];
+                for (pattern, fragments) in branches {
+                    // the value is not stored in a variable, only the
+                    // block reading it is updated
+                    let mut block_scope = scope.to_vec();
+                    block_scope.extend(pattern.iter().flat_map(extract_pattern_names).map(|name| {
+                        BlockName {
+                            name,
+                            changes: Vec::new(),
+                            assignable: false,
+                        }
+                    }));
+                    let fragments = fragments.as_deref().unwrap_or_default();
+                    collect_bindings(fragments, &block_scope, variables, bindings);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::parse::Parser;
+
+    fn analyse_error(source: &str) -> String {
+        let ast = Parser::new(source).parse().unwrap();
+        analyse(&ast).err().unwrap().message
+    }
+
+    #[test]
+    fn test_extract_bindings() {
+        let source = "<script>let name = ''; let user = {};</script>
+            <input bind:value={name} />
+            {#if name}<input bind:value={user.city} />{/if}";
+        let ast = Parser::new(source).parse().unwrap();
+        let result = analyse(&ast).unwrap();
+
+        assert!(result.will_change.contains("name"));
+        assert!(result.will_change.contains("user"));
+    }
+
+    #[test]
+    fn test_extract_block_bindings() {
+        let source = "<script>const todos = []; const names = []; const lists = [];</script>
+            {#each todos as todo}<input bind:value={todo.text} />{/each}
+            {#each names as name}<input bind:value={name} />{/each}
+            {#each lists as list}{#each list.items as item}<input bind:checked={item.done} />{/each}{/each}
+            {#await load() then user}<input bind:value={user.name} />{/await}";
+        let ast = Parser::new(source).parse().unwrap();
+        let result = analyse(&ast).unwrap();
+
+        assert!(result.will_change.contains("todos"));
+        assert!(result.will_change.contains("names"));
+        assert!(result.will_change.contains("lists"));
+        assert!(!result.will_change.contains("todo"));
+        assert!(!result.will_change.contains("user"));
+    }
+
+    #[test]
+    fn test_invalid_bindings() {
+        assert_eq!(
+            analyse_error("<script>const name = '';</script><input bind:value={name} />"),
+            "Cannot bind to a variable declared with `const`"
+        );
+        assert_eq!(
+            analyse_error("<input bind:value={name} />"),
+            "name is not declared"
+        );
+        assert_eq!(
+            analyse_error("<script>let items = [];</script>{#each items as item, i}<input bind:value={i} />{/each}"),
+            "Cannot bind to i, declared by a block: bind to one of its properties instead"
+        );
+        assert_eq!(
+            analyse_error("{#await load() then user}<input bind:value={user} />{/await}"),
+            "Cannot bind to user, declared by a block: bind to one of its properties instead"
+        );
+        assert_eq!(
+            analyse_error("<script>let size;</script><svelte:window bind:size={size} />"),
+            "'size' is not a valid binding on <svelte:window>"
+        );
+    }
+}
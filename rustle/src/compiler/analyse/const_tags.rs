@@ -0,0 +1,55 @@
+use super::errors::Error;
+use crate::compiler::Fragment;
+
+/// Checks that the `{@const}` tags are the immediate children
+/// of a block or of a component, where they are declared.
+pub fn check_const_tags(fragments: &[Fragment]) -> Result<(), Error> {
+    check_placement(fragments, false)
+}
+
+fn check_placement(fragments: &[Fragment], in_block: bool) -> Result<(), Error> {
+    for fragment in fragments {
+        match fragment {
+            Fragment::ConstTag(tag) if !in_block => {
+                return Err(Error::invalid_const_placement(tag.span))
+            }
+            Fragment::Element(element) => check_placement(&element.fragments, element.is_component)?,
+            Fragment::IfBlock(block) => {
+                check_placement(&block.fragments, true)?;
+                check_placement(block.else_fragments.as_deref().unwrap_or_default(), true)?;
+            }
+            Fragment::EachBlock(block) => {
+                check_placement(&block.fragments, true)?;
+                check_placement(block.else_fragments.as_deref().unwrap_or_default(), true)?;
+            }
+            Fragment::KeyBlock(block) => check_placement(&block.fragments, false)?,
+            Fragment::AwaitBlock(block) => {
+                check_placement(&block.pending_fragments, false)?;
+                check_placement(block.then_fragments.as_deref().unwrap_or_default(), true)?;
+                check_placement(block.catch_fragments.as_deref().unwrap_or_default(), true)?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::parse::Parser;
+
+    #[test]
+    fn test_check_const_tags() {
+        let source = "<script>let items = [];</script>
+            {#each items as item}{@const double = item * 2}{double}{/each}";
+        let ast = Parser::new(source).parse().unwrap();
+        assert!(analyse(&ast).is_ok());
+
+        for source in ["{@const a = 1}", "{#if true}<div>{@const a = 1}</div>{/if}"] {
+            let ast = Parser::new(source).parse().unwrap();
+            assert_eq!(analyse(&ast).err().unwrap().code, "invalid-const-placement");
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use swc_common::Spanned;
+use swc_css::ast::{
+    ComplexSelector, ComplexSelectorChildren, CompoundSelector, PseudoClassSelector,
+    SubclassSelector, TypeSelector,
+};
+use swc_css::visit::{Visit, VisitWith};
+use swc_ecma_ast::{Expr, Lit};
+
+use crate::compiler::utils::hash;
+use crate::compiler::{Fragment, RustleElement, RustleStyle};
+
+/// The scoping of a component `<style>` tag. Every selector
+/// and every element it can match gets the hash `class`.
+#[derive(Debug)]
+pub struct CssScope {
+    /// The per-component class, e.g. `svelte-1r8hm9j`
+    pub class: String,
+    pub selectors: Vec<ScopedSelector>,
+}
+
+/// A compound selector of the stylesheet like `div.active`,
+/// positions are byte offsets in the `<style>` content.
+#[derive(Debug)]
+pub enum ScopedSelector {
+    /// Scoped by inserting the class at `end`
+    Local {
+        start: usize,
+        end: usize,
+        tag: Option<String>,
+        id: Option<String>,
+        classes: Vec<String>,
+        attributes: Vec<String>,
+        /// The unscoped parts, like `:global(.a)` in `p:global(.a)`
+        globals: Vec<GlobalSelector>,
+    },
+    /// Only made of `:global(...)` parts, left unscoped
+    Global(Vec<GlobalSelector>),
+}
+
+/// A `:global(...)` pseudo class, left unscoped by
+/// removing the wrapper around the `inner` selector
+#[derive(Debug)]
+pub struct GlobalSelector {
+    pub start: usize,
+    pub inner: (usize, usize),
+    pub end: usize,
+}
+
+impl CssScope {
+    pub fn new(style: &RustleStyle) -> CssScope {
+        let mut visitor = SelectorVisitor {
+            selectors: Vec::new(),
+        };
+        style.stylesheet.visit_with(&mut visitor);
+
+        CssScope {
+            class: format!("svelte-{}", hash(&style.content)),
+            selectors: visitor.selectors,
+        }
+    }
+
+    /// Checks if any of the selectors can match the element.
+    pub fn applies_to(&self, element: &RustleElement) -> bool {
+        self.selectors.iter().any(|selector| selector.applies_to(element))
+    }
+
+    /// Returns the selectors that can't match any element in `fragments`.
+    pub fn unused_selectors(&self, fragments: &[Fragment]) -> Vec<&ScopedSelector> {
+        let mut elements = Vec::new();
+        collect_elements(fragments, &mut elements);
+
+        self.selectors
+            .iter()
+            .filter(|selector| matches!(selector, ScopedSelector::Local { .. }))
+            .filter(|selector| !elements.iter().any(|element| selector.applies_to(element)))
+            .collect()
+    }
+}
+
+/// Returns the value of an attribute without expressions,
+/// `"intro"` -> `intro`, `"a {b}"` and `{b}` -> `None`
+pub fn static_value(value: &Expr) -> Option<String> {
+    match value {
+        Expr::Lit(Lit::Str(str)) => Some(str.value.to_string()),
+        // `"a {b}"` values are kept as raw template strings
+        Expr::Tpl(tpl) if tpl.exprs.is_empty() => {
+            let value = tpl.quasis.iter().map(|q| q.raw.to_string()).collect::<String>();
+            (!value.contains('{')).then_some(value)
+        }
+        _ => None,
+    }
+}
+
+impl ScopedSelector {
+    /// Checks if the selector can match the element, dynamic `class`
+    /// and `id` attributes and spreads are assumed to match.
+    pub fn applies_to(&self, element: &RustleElement) -> bool {
+        if element.is_component {
+            return false;
+        }
+
+        // a `{...spread}` can set any attribute
+        let spread = element.attributes.iter().any(|attr| attr.name == "...");
+        let attribute = |name: &str| {
+            element
+                .attributes
+                .iter()
+                .find(|attr| attr.name == name)
+                .map(|attr| static_value(&attr.value))
+                .or(spread.then_some(None))
+        };
+
+        match self {
+            ScopedSelector::Local {
+                tag,
+                id,
+                classes,
+                attributes,
+                ..
+            } => {
+                // the tag of a `<svelte:element>` is only known when it is static
+                let tag_name = match element.name.as_str() {
+                    "svelte:element" => element.this.as_ref().and_then(static_value),
+                    name => Some(name.to_owned()),
+                };
+                let tag_matches = tag.as_ref().is_none_or(|tag| {
+                    tag_name.as_ref().is_none_or(|name| tag.eq_ignore_ascii_case(name))
+                });
+                let id_matches = id.as_ref().is_none_or(|id| match attribute("id") {
+                    Some(Some(value)) => value.trim() == id,
+                    Some(None) => true,
+                    None => false,
+                });
+                // `class:name` directives may add their class
+                let directives = element
+                    .attributes
+                    .iter()
+                    .filter_map(|attr| attr.name.strip_prefix("class:"));
+                let classes_match = classes.is_empty()
+                    || match attribute("class") {
+                        Some(None) => true,
+                        value => {
+                            let value = value.flatten().unwrap_or_default();
+                            let names = value.split_whitespace().chain(directives).collect::<Vec<&str>>();
+                            classes.iter().all(|class| names.contains(&class.as_str()))
+                        }
+                    };
+                let attributes_match = attributes
+                    .iter()
+                    .all(|name| attribute(name).is_some());
+
+                tag_matches && id_matches && classes_match && attributes_match
+            }
+            ScopedSelector::Global(_) => false,
+        }
+    }
+}
+
+fn collect_elements<'a>(fragments: &'a [Fragment], elements: &mut Vec<&'a RustleElement>) {
+    for fragment in fragments {
+        match fragment {
+            Fragment::Element(element) => {
+                elements.push(element);
+                collect_elements(&element.fragments, elements);
+            }
+            Fragment::IfBlock(block) => {
+                collect_elements(&block.fragments, elements);
+                collect_elements(block.else_fragments.as_deref().unwrap_or_default(), elements);
+            }
+            Fragment::EachBlock(block) => {
+                collect_elements(&block.fragments, elements);
+                collect_elements(block.else_fragments.as_deref().unwrap_or_default(), elements);
+            }
+            Fragment::KeyBlock(block) => collect_elements(&block.fragments, elements),
+            Fragment::AwaitBlock(block) => {
+                collect_elements(&block.pending_fragments, elements);
+                collect_elements(block.then_fragments.as_deref().unwrap_or_default(), elements);
+                collect_elements(block.catch_fragments.as_deref().unwrap_or_default(), elements);
+            }
+            _ => (),
+        }
+    }
+}
+
+struct SelectorVisitor {
+    selectors: Vec<ScopedSelector>,
+}
+
+impl Visit for SelectorVisitor {
+    // selectors nested in pseudo classes like `:not(.a)` are left as they are
+    fn visit_complex_selector(&mut self, selector: &ComplexSelector) {
+        for child in &selector.children {
+            if let ComplexSelectorChildren::CompoundSelector(compound) = child {
+                self.selectors.push(scoped_selector(compound));
+            }
+        }
+    }
+}
+
+fn scoped_selector(compound: &CompoundSelector) -> ScopedSelector {
+    let mut end = compound.span.hi.0 as usize - 1;
+    let mut id = None;
+    let mut classes = Vec::new();
+    let mut attributes = Vec::new();
+    let mut globals = Vec::new();
+    for subclass in &compound.subclass_selectors {
+        match subclass {
+            SubclassSelector::Id(selector) => id = Some(selector.text.value.to_string()),
+            SubclassSelector::Class(selector) => classes.push(selector.text.value.to_string()),
+            SubclassSelector::Attribute(selector) => {
+                attributes.push(selector.name.value.value.to_string())
+            }
+            SubclassSelector::PseudoClass(selector) if &*selector.name.value == "global" => {
+                end = end.min(selector.span.lo.0 as usize - 1);
+                globals.push(global_selector(selector));
+            }
+            // the class goes before pseudo classes and elements like `::before`
+            SubclassSelector::PseudoClass(selector) => {
+                end = end.min(selector.span.lo.0 as usize - 1)
+            }
+            SubclassSelector::PseudoElement(selector) => {
+                end = end.min(selector.span.lo.0 as usize - 1)
+            }
+        }
+    }
+
+    // `:global(.a):global(.b)` has nothing left to scope
+    let global = globals.len() == compound.subclass_selectors.len();
+    if global && compound.type_selector.is_none() {
+        return ScopedSelector::Global(globals);
+    }
+
+    let tag = match compound.type_selector.as_deref() {
+        Some(TypeSelector::TagName(selector)) => Some(selector.name.value.value.to_string()),
+        _ => None,
+    };
+
+    ScopedSelector::Local {
+        start: compound.span.lo.0 as usize - 1,
+        end,
+        tag,
+        id,
+        classes,
+        attributes,
+        globals,
+    }
+}
+
+/// An empty `:global()` is rejected by the parser, see `swc_css_parse`
+fn global_selector(global: &PseudoClassSelector) -> GlobalSelector {
+    let children = global.children.as_deref().unwrap_or_default();
+    let (first, last) = (children.first().unwrap(), children.last().unwrap());
+
+    GlobalSelector {
+        start: global.span.lo.0 as usize - 1,
+        inner: (first.span().lo.0 as usize - 1, last.span().hi.0 as usize - 1),
+        end: global.span.hi.0 as usize - 1,
+    }
+}
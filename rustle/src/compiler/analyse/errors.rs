@@ -0,0 +1,118 @@
+use swc_common::Span;
+
+#[derive(Debug)]
+pub struct Error {
+    pub code: String,
+    pub message: String,
+    /// The script node the error points to
+    pub span: Span,
+}
+
+impl Error {
+    fn new(code: &str, message: &str, span: Span) -> Error {
+        Error {
+            code: code.to_string(),
+            message: message.to_string(),
+            span,
+        }
+    }
+
+    pub fn binding_undeclared(name: &str, span: Span) -> Error {
+        Error::new(
+            "binding-undeclared",
+            &format!("{} is not declared", name),
+            span,
+        )
+    }
+
+    pub fn cyclical_reactive_declaration(cycle: &[String], span: Span) -> Error {
+        Error::new(
+            "cyclical-reactive-declaration",
+            &format!("Cyclical dependency detected: {}", cycle.join(" → ")),
+            span,
+        )
+    }
+
+    pub fn dynamic_slot_name(span: Span) -> Error {
+        Error::new(
+            "dynamic-slot-name",
+            "`name` attribute must be a static value",
+            span,
+        )
+    }
+
+    pub fn invalid_animation_placement(span: Span) -> Error {
+        Error::new(
+            "invalid-animation",
+            "An element that uses the animate directive must be the immediate child of a keyed each block",
+            span,
+        )
+    }
+
+    pub fn invalid_animation_sole(span: Span) -> Error {
+        Error::new(
+            "invalid-animation",
+            "An element that uses the animate directive must be the sole child of a keyed each block",
+            span,
+        )
+    }
+
+    pub fn invalid_binding_block(name: &str, span: Span) -> Error {
+        Error::new(
+            "invalid-binding",
+            &format!(
+                "Cannot bind to {}, declared by a block: bind to one of its properties instead",
+                name
+            ),
+            span,
+        )
+    }
+
+    pub fn invalid_binding_const(span: Span) -> Error {
+        Error::new(
+            "invalid-binding",
+            "Cannot bind to a variable declared with `const`",
+            span,
+        )
+    }
+
+    pub fn invalid_binding_window(name: &str, span: Span) -> Error {
+        Error::new(
+            "invalid-binding",
+            &format!("'{}' is not a valid binding on <svelte:window>", name),
+            span,
+        )
+    }
+
+    pub fn invalid_binding_value(span: Span) -> Error {
+        Error::new(
+            "invalid-directive-value",
+            "Can only bind to an identifier (e.g. `{foo}`) or a member expression (e.g. `{foo.bar}` or `{foo[baz]}`)",
+            span,
+        )
+    }
+
+    pub fn invalid_const_placement(span: Span) -> Error {
+        Error::new(
+            "invalid-const-placement",
+            "{@const} must be the immediate child of {#if}, {:else if}, {:else}, {#each}, {:then}, {:catch} or <Component>",
+            span,
+        )
+    }
+
+    pub fn invalid_slot_attribute(span: Span) -> Error {
+        Error::new(
+            "invalid-slot-attribute",
+            "slot attribute must be a static value",
+            span,
+        )
+    }
+
+    pub fn invalid_slotted_content(span: Span) -> Error {
+        Error::new(
+            "invalid-slotted-content",
+            "Element with a slot='...' attribute must be a child of a component",
+            span,
+        )
+    }
+}
@@ -0,0 +1,52 @@
+use swc_ecma_ast::{Expr, Ident, MemberExpr, MemberProp, Module, PropName};
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// Returns the names of the identifiers an expression reads,
+/// e.g. `a + b.c` -> `a`, `b`
+///
+/// Property names such as `c` in `b.c` or `{ c: 1 }` are not
+/// dependencies and are skipped.
+pub fn extract_dependencies(expr: &Expr) -> Vec<String> {
+    let mut visitor = DependencyVisitor {
+        dependencies: Vec::new(),
+    };
+    expr.visit_with(&mut visitor);
+
+    visitor.dependencies
+}
+
+/// Returns the names of the identifiers the script reads
+pub fn extract_script_dependencies(script: &Module) -> Vec<String> {
+    let mut visitor = DependencyVisitor {
+        dependencies: Vec::new(),
+    };
+    script.visit_with(&mut visitor);
+
+    visitor.dependencies
+}
+
+struct DependencyVisitor {
+    dependencies: Vec<String>,
+}
+
+impl Visit for DependencyVisitor {
+    fn visit_ident(&mut self, i: &Ident) {
+        let name = i.sym.to_string();
+        if !self.dependencies.contains(&name) {
+            self.dependencies.push(name);
+        }
+    }
+
+    fn visit_member_expr(&mut self, me: &MemberExpr) {
+        me.obj.visit_with(self);
+        if let MemberProp::Computed(c) = &me.prop {
+            c.visit_with(self);
+        }
+    }
+
+    fn visit_prop_name(&mut self, pn: &PropName) {
+        if let PropName::Computed(c) = pn {
+            c.visit_with(self);
+        }
+    }
+}
@@ -1,33 +1,98 @@
-use swc_ecma_ast::{BindingIdent, Decl, Script, VarDecl, VarDeclarator};
+use swc_ecma_ast::{
+    AssignPatProp, BindingIdent, Decl, ExportDecl, Expr, ImportSpecifier, Module, ModuleDecl,
+    ModuleItem, Pat, Stmt, VarDeclKind,
+};
+use swc_ecma_visit::{Visit, VisitWith};
 
-pub fn extract_root_variables(script: &Script) -> Vec<String> {
-    let declarations = script
+/// Returns the names of the variables declared at the top-level
+/// of the script, destructured ones included
+pub fn extract_root_variables(script: &Module) -> Vec<String> {
+    script
         .body
-        .clone()
-        .into_iter()
-        .filter_map(|stmt| stmt.decl())
-        .collect::<Vec<Decl>>();
-
-    let var_declarations = declarations
-        .into_iter()
-        .filter_map(|decl| decl.var())
-        .collect::<Vec<Box<VarDecl>>>();
-
-    let var_declarators = var_declarations
-        .into_iter()
-        .map(|var_decls| var_decls.decls)
-        .flatten()
-        .collect::<Vec<VarDeclarator>>();
-
-    let binding_idents = var_declarators
-        .into_iter()
-        .filter_map(|var_declrs| var_declrs.name.ident())
-        .collect::<Vec<BindingIdent>>();
-
-    let names = binding_idents
-        .into_iter()
-        .map(|ident| ident.id.sym.to_string())
-        .collect::<Vec<String>>();
-
-    names
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl))) => Some(var_decl),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+                decl: Decl::Var(var_decl),
+                ..
+            })) => Some(var_decl),
+            _ => None,
+        })
+        .flat_map(|var_decl| &var_decl.decls)
+        .flat_map(|declarator| extract_pattern_names(&declarator.name))
+        .collect()
+}
+
+/// Returns the names of the functions, classes and imports of the
+/// script, the other top-level bindings beside its variables
+pub fn extract_root_declarations(script: &Module) -> Vec<String> {
+    script
+        .body
+        .iter()
+        .flat_map(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(decl))
+            | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+                match decl {
+                    Decl::Fn(fn_decl) => vec![&fn_decl.ident],
+                    Decl::Class(class_decl) => vec![&class_decl.ident],
+                    _ => Vec::new(),
+                }
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => import
+                .specifiers
+                .iter()
+                .map(|specifier| match specifier {
+                    ImportSpecifier::Named(named) => &named.local,
+                    ImportSpecifier::Default(default) => &default.local,
+                    ImportSpecifier::Namespace(namespace) => &namespace.local,
+                })
+                .collect(),
+            _ => Vec::new(),
+        })
+        .map(|ident| ident.sym.to_string())
+        .collect()
+}
+
+/// Returns the props of the component, the variables
+/// declared with `export let` or `export var`
+pub fn extract_props(script: &Module) -> Vec<String> {
+    script
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+                decl: Decl::Var(var_decl),
+                ..
+            })) if var_decl.kind != VarDeclKind::Const => Some(var_decl),
+            _ => None,
+        })
+        .flat_map(|var_decl| &var_decl.decls)
+        .flat_map(|declarator| extract_pattern_names(&declarator.name))
+        .collect()
+}
+
+/// Returns the names a binding pattern declares,
+/// e.g. `{ id, name: title }` -> `id`, `title`
+pub fn extract_pattern_names(pat: &Pat) -> Vec<String> {
+    let mut visitor = PatternVisitor { names: Vec::new() };
+    pat.visit_with(&mut visitor);
+
+    visitor.names
+}
+
+struct PatternVisitor {
+    names: Vec<String>,
+}
+
+impl Visit for PatternVisitor {
+    fn visit_binding_ident(&mut self, bi: &BindingIdent) {
+        self.names.push(bi.id.sym.to_string());
+    }
+
+    fn visit_assign_pat_prop(&mut self, app: &AssignPatProp) {
+        self.names.push(app.key.sym.to_string());
+    }
+
+    // default values and computed keys declare nothing
+    fn visit_expr(&mut self, _: &Expr) {}
 }
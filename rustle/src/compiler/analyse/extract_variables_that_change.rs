@@ -1,83 +1,358 @@
+use std::collections::HashSet;
+
+use swc_common::Span;
 use swc_ecma_ast::{
-    ArrowExpr, Decl, Expr, Ident, Script, Stmt, UpdateExpr, VarDecl, VarDeclarator,
+    ArrowExpr, AssignExpr, BlockStmt, CatchClause, Expr, FnDecl, ForInStmt, ForOfStmt, ForStmt,
+    Function, Module, Pat, PatOrExpr, Stmt, UpdateExpr, VarDecl, VarDeclKind,
 };
+use swc_ecma_visit::{Visit, VisitWith};
+
+use super::extract_variables::{extract_pattern_names, extract_root_variables};
+use super::reactive_declarations::{implicit_declarations, is_reactive_statement};
+use crate::compiler::Fragment;
 
-pub fn extract_variables_that_change(script: &Script) -> Vec<String> {
-    let body = script.body.clone();
+/// An assignment or update expression of the script changing
+/// top-level variables, e.g. `[a, b.c] = [b.c, a]` -> `a`, `b`
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub span: Span,
+    pub names: Vec<String>,
+}
 
-    let mut interpreter = Interpreter;
-    let vars = body
-        .iter()
-        .filter_map(|stmt| Interpreter::visit_stmt(&mut interpreter, stmt))
-        .collect::<Vec<String>>();
+/// Returns the assignments and update expressions of the script
+/// targeting top-level variables, e.g. `count += 1`, `count--`
+/// or `user.name = name`, wherever they are.
+///
+/// Names shadowed by function parameters or local declarations
+/// are skipped, and so are the assignments made by `$:` statements
+/// themselves, which are re-run in the update cycle.
+pub fn extract_variables_that_change(script: &Module) -> Vec<Assignment> {
+    let mut visitor = AssignmentVisitor::new(script);
+    script.visit_with(&mut visitor);
 
-    vars
+    visitor.assignments
 }
 
-trait IdentVisitor<T> {
-    fn visit_stmt(&mut self, s: &Stmt) -> T;
-    fn visit_decl(&mut self, d: &Decl) -> T;
-    fn visit_var_decl(&mut self, vd: &VarDecl) -> T;
-    fn visit_var_declarator(&mut self, vd: &VarDeclarator) -> T;
-    fn visit_expr(&mut self, e: &Expr) -> T;
-    fn visit_arrow_expr(&mut self, ae: &ArrowExpr) -> T;
-    fn visit_update_expr(&mut self, ue: &UpdateExpr) -> T;
-    fn visit_identifier(&mut self, i: &Ident) -> T;
+/// Returns the assignments of the `on:` handlers of the template
+/// changing top-level variables, e.g. `on:click={() => count += 1}`.
+///
+/// The names of the enclosing `{#each}` and `{#await}` blocks
+/// shadow the top-level variables.
+pub fn extract_handler_assignments(fragments: &[Fragment], script: &Module) -> Vec<Assignment> {
+    let mut visitor = AssignmentVisitor::new(script);
+    visit_handlers(fragments, &mut visitor);
+
+    visitor.assignments
 }
 
-struct Interpreter;
-impl IdentVisitor<Option<String>> for Interpreter {
-    fn visit_stmt(&mut self, s: &Stmt) -> Option<String> {
-        match s {
-            Stmt::Decl(d) => self.visit_decl(d),
-            _ => None,
+fn visit_handlers(fragments: &[Fragment], visitor: &mut AssignmentVisitor) {
+    for fragment in fragments {
+        match fragment {
+            Fragment::Element(element) => {
+                for handler in &element.handlers {
+                    if let Some(expression) = &handler.expression {
+                        expression.visit_with(visitor);
+                    }
+                }
+                visit_handlers(&element.fragments, visitor);
+            }
+            Fragment::IfBlock(block) => {
+                visit_handlers(&block.fragments, visitor);
+                visit_handlers(block.else_fragments.as_deref().unwrap_or_default(), visitor);
+            }
+            Fragment::EachBlock(block) => {
+                let mut names = extract_pattern_names(&block.context);
+                names.extend(block.index.clone());
+                visit_scoped(names, &block.fragments, visitor);
+                visit_handlers(block.else_fragments.as_deref().unwrap_or_default(), visitor);
+            }
+            Fragment::KeyBlock(block) => visit_handlers(&block.fragments, visitor),
+            Fragment::AwaitBlock(block) => {
+                visit_handlers(&block.pending_fragments, visitor);
+                let branches = [
+                    (&block.value, &block.then_fragments),
+                    (&block.error, &block.catch_fragments),
+                ];
+                for (pattern, fragments) in branches {
+                    let names = pattern.as_ref().map(extract_pattern_names).unwrap_or_default();
+                    visit_scoped(names, fragments.as_deref().unwrap_or_default(), visitor);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn visit_scoped(names: Vec<String>, fragments: &[Fragment], visitor: &mut AssignmentVisitor) {
+    visitor.enter_scope(names, false);
+    visit_handlers(fragments, visitor);
+    visitor.scopes.pop();
+}
+
+/// The names declared in a function or a block
+struct Scope {
+    names: HashSet<String>,
+    /// Holds the `var` declarations
+    function: bool,
+}
+
+struct AssignmentVisitor {
+    root_variables: Vec<String>,
+    /// The enclosing functions and blocks
+    scopes: Vec<Scope>,
+    /// In a top-level `$:` statement, outside of its functions
+    reactive: bool,
+    assignments: Vec<Assignment>,
+}
+
+impl AssignmentVisitor {
+    fn new(script: &Module) -> Self {
+        let mut root_variables = extract_root_variables(script);
+        root_variables.extend(implicit_declarations(script));
+
+        AssignmentVisitor {
+            root_variables,
+            scopes: Vec::new(),
+            reactive: false,
+            assignments: Vec::new(),
+        }
+    }
+
+    fn assign(&mut self, name: String) {
+        let shadowed = self.scopes.iter().any(|scope| scope.names.contains(&name));
+        let reactive = self.reactive && !self.scopes.iter().any(|scope| scope.function);
+        if shadowed || reactive || !self.root_variables.contains(&name) {
+            return;
+        }
+
+        let names = &mut self.assignments.last_mut().unwrap().names;
+        if !names.contains(&name) {
+            names.push(name);
         }
     }
 
-    fn visit_decl(&mut self, d: &Decl) -> Option<String> {
-        match d {
-            Decl::Var(vd) => self.visit_var_decl(vd),
-            _ => None,
+    fn enter_assignment(&mut self, span: Span) {
+        self.assignments.push(Assignment {
+            span,
+            names: Vec::new(),
+        });
+    }
+
+    /// Drops the last assignment if it changes no top-level variable
+    fn exit_assignment(&mut self) {
+        if self.assignments.last().is_some_and(|assignment| assignment.names.is_empty()) {
+            self.assignments.pop();
         }
     }
 
-    fn visit_var_decl(&mut self, vd: &VarDecl) -> Option<String> {
-        match vd.decls.first() {
-            Some(vd) => self.visit_var_declarator(vd),
-            None => None,
+    fn assign_pat(&mut self, pat: &Pat) {
+        match pat {
+            Pat::Expr(expr) => self.assign_expr(expr),
+            Pat::Array(array) => array.elems.iter().flatten().for_each(|elem| self.assign_pat(elem)),
+            _ => extract_pattern_names(pat)
+                .into_iter()
+                .for_each(|name| self.assign(name)),
         }
     }
 
-    fn visit_var_declarator(&mut self, vd: &VarDeclarator) -> Option<String> {
-        match &vd.init {
-            Some(e) => self.visit_expr(e),
-            None => None,
+    /// Assigning to a member like `a.b[0] = 1` changes the root object `a`
+    fn assign_expr(&mut self, expr: &Expr) {
+        match expr.unwrap_parens() {
+            Expr::Ident(ident) => self.assign(ident.sym.to_string()),
+            Expr::Member(member) => self.assign_expr(&member.obj),
+            _ => (),
         }
     }
 
-    fn visit_expr(&mut self, e: &Expr) -> Option<String> {
-        match e {
-            Expr::Update(ue) => self.visit_update_expr(ue),
-            Expr::Arrow(ae) => self.visit_arrow_expr(ae),
-            _ => None,
+    fn enter_scope(&mut self, names: impl IntoIterator<Item = String>, function: bool) {
+        self.scopes.push(Scope {
+            names: names.into_iter().collect(),
+            function,
+        });
+    }
+
+    fn enter_function<'a>(&mut self, params: impl Iterator<Item = &'a Pat>) {
+        self.enter_scope(params.flat_map(extract_pattern_names), true);
+    }
+
+    /// Visits a node in a new block scope
+    fn visit_block<N: VisitWith<Self>>(&mut self, node: &N, names: Vec<String>) {
+        self.enter_scope(names, false);
+        node.visit_children_with(self);
+        self.scopes.pop();
+    }
+}
+
+impl Visit for AssignmentVisitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if self.scopes.is_empty() && is_reactive_statement(stmt) {
+            self.reactive = true;
+            stmt.visit_children_with(self);
+            self.reactive = false;
+        } else {
+            stmt.visit_children_with(self);
+        }
+    }
+
+    fn visit_assign_expr(&mut self, ae: &AssignExpr) {
+        self.enter_assignment(ae.span);
+        match &ae.left {
+            PatOrExpr::Pat(pat) => self.assign_pat(pat),
+            PatOrExpr::Expr(expr) => self.assign_expr(expr),
         }
+        self.exit_assignment();
+        ae.visit_children_with(self);
+    }
+
+    fn visit_update_expr(&mut self, ue: &UpdateExpr) {
+        self.enter_assignment(ue.span);
+        self.assign_expr(&ue.arg);
+        self.exit_assignment();
+        ue.visit_children_with(self);
     }
 
-    fn visit_arrow_expr(&mut self, ae: &ArrowExpr) -> Option<String> {
-        match &ae.body {
-            swc_ecma_ast::BlockStmtOrExpr::BlockStmt(_) => None,
-            swc_ecma_ast::BlockStmtOrExpr::Expr(e) => self.visit_expr(e),
+    // `var` belongs to the enclosing function, `let` and `const` to the block
+    fn visit_var_decl(&mut self, vd: &VarDecl) {
+        let scope = match vd.kind {
+            VarDeclKind::Var => self.scopes.iter_mut().rev().find(|scope| scope.function),
+            _ => self.scopes.last_mut(),
+        };
+        if let Some(scope) = scope {
+            let names = vd.decls.iter().flat_map(|decl| extract_pattern_names(&decl.name));
+            scope.names.extend(names);
         }
+        vd.visit_children_with(self);
     }
 
-    fn visit_update_expr(&mut self, ue: &UpdateExpr) -> Option<String> {
-        match &ue.arg.unwrap_parens() {
-            Expr::Ident(i) => self.visit_identifier(i),
-            _ => None,
+    fn visit_fn_decl(&mut self, fd: &FnDecl) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(fd.ident.sym.to_string());
         }
+        fd.visit_children_with(self);
     }
 
-    fn visit_identifier(&mut self, i: &Ident) -> Option<String> {
-        Some(i.sym.to_string())
+    fn visit_block_stmt(&mut self, bs: &BlockStmt) {
+        self.visit_block(bs, Vec::new());
+    }
+
+    fn visit_for_stmt(&mut self, fs: &ForStmt) {
+        self.visit_block(fs, Vec::new());
+    }
+
+    fn visit_for_in_stmt(&mut self, fs: &ForInStmt) {
+        self.visit_block(fs, Vec::new());
+    }
+
+    fn visit_for_of_stmt(&mut self, fs: &ForOfStmt) {
+        self.visit_block(fs, Vec::new());
+    }
+
+    fn visit_catch_clause(&mut self, cc: &CatchClause) {
+        let names = cc.param.as_ref().map(extract_pattern_names).unwrap_or_default();
+        self.visit_block(cc, names);
+    }
+
+    fn visit_function(&mut self, f: &Function) {
+        self.enter_function(f.params.iter().map(|param| &param.pat));
+        f.visit_children_with(self);
+        self.scopes.pop();
+    }
+
+    fn visit_arrow_expr(&mut self, ae: &ArrowExpr) {
+        self.enter_function(ae.params.iter());
+        ae.visit_children_with(self);
+        self.scopes.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::parse::Parser;
+
+    use super::{extract_handler_assignments, extract_variables_that_change};
+
+    fn will_change(script: &str) -> Vec<String> {
+        let source = format!("<script>{}</script>", script);
+        let ast = Parser::new(&source).parse().unwrap();
+
+        let mut names = Vec::new();
+        for assignment in extract_variables_that_change(&ast.script) {
+            for name in assignment.names {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn test_extract_variables_that_change() {
+        assert_eq!(will_change("let a = 0; const f = () => a++;"), vec!["a"]);
+        assert_eq!(will_change("let a = 0; const f = () => { a += 1; };"), vec!["a"]);
+        assert_eq!(will_change("let a = 0; function f() { a = a + 1; }"), vec!["a"]);
+        assert_eq!(
+            will_change("let a = 0; const f = () => setTimeout(() => { a--; });"),
+            vec!["a"]
+        );
+        assert_eq!(will_change("let a = { b: 1 }; const f = () => a.b = 2;"), vec!["a"]);
+        assert_eq!(will_change("let a = []; const f = () => a = [...a, 1];"), vec!["a"]);
+        assert_eq!(
+            will_change("let a = 1, b = 2; const swap = () => [a, b] = [b, a];"),
+            vec!["a", "b"]
+        );
+        assert_eq!(will_change("let a = 0; a = 1;"), vec!["a"]);
+        assert_eq!(will_change("$: b = 1; const f = () => b++;"), vec!["b"]);
+    }
+
+    #[test]
+    fn test_extract_variables_that_change_shadowed() {
+        assert!(will_change("let a = 0; const f = (a) => a++;").is_empty());
+        assert!(will_change("let a = 0; function f() { let a = 1; a++; }").is_empty());
+        assert!(will_change("let a = 0; const f = () => { b = 1; };").is_empty());
+        assert!(will_change("let a = 0, b; $: b = a * 2;").is_empty());
+        assert!(will_change("let a = 0; $: { a = 1; }").is_empty());
+    }
+
+    #[test]
+    fn test_extract_variables_that_change_block_scopes() {
+        assert_eq!(will_change("let a = 0; function f(x) { if (x) { let a = 5; } a++; }"), vec!["a"]);
+        assert_eq!(will_change("let a = 0; function f() { { var a; } a++; }"), Vec::<String>::new());
+        assert_eq!(will_change("let i = 0; function f() { for (let i = 0; i < 2; i++) {} i++; }"), vec!["i"]);
+        assert!(will_change("let i = 0; for (const i of [1]) { i++; }").is_empty());
+        assert_eq!(will_change("let e; try {} catch (e) { e = 1; } e = 2;"), vec!["e"]);
+        assert_eq!(will_change("let b = 0; $: if (a) { b = 1; }"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_extract_variables_that_change_destructured() {
+        assert_eq!(
+            will_change("let { a } = { a: 1 }; let [b, ...c] = [2]; let { d = 1 } = {}; const f = () => { a++; b++; c = []; d--; };"),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn test_extract_assignments() {
+        let source = "<script>let a = 0, b = {}; const f = () => { a = b.c++; };</script>";
+        let ast = Parser::new(source).parse().unwrap();
+        let assignments = extract_variables_that_change(&ast.script);
+
+        let names = assignments.iter().map(|a| a.names.clone()).collect::<Vec<_>>();
+        assert_eq!(names, vec![vec!["a"], vec!["b"]]);
+        assert_eq!(&source[assignments[0].span.lo.0 as usize - 1..][..9], "a = b.c++");
+    }
+
+    #[test]
+    fn test_extract_handler_assignments() {
+        let source = "<script>let a = 0, b = [];</script>
+            <button on:click={() => a += 1}>a</button>
+            {#each b as a}<button on:click={() => a = 1}>b</button>{/each}";
+        let ast = Parser::new(source).parse().unwrap();
+        let assignments = extract_handler_assignments(&ast.fragments, &ast.script);
+
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0].names, vec!["a"]);
+        assert_eq!(&source[assignments[0].span.lo.0 as usize - 1..][..6], "a += 1");
     }
 }
@@ -1,44 +1,104 @@
-use swc_ecma_ast::Expr;
-use swc_visit::define;
-
 use self::{
-    extract_variables::extract_root_variables,
-    extract_variables_that_change::extract_variables_that_change,
+    animations::check_animations,
+    bindings::extract_bindings,
+    const_tags::check_const_tags,
+    extract_variables::{extract_props, extract_root_declarations, extract_root_variables},
+    extract_variables_that_change::{extract_handler_assignments, extract_variables_that_change},
+    reactive_declarations::{extract_reactive_declarations, implicit_declarations},
+    slots::extract_slots,
 };
 
+pub use self::bindings::binding_root;
+pub use self::css_scope::{static_value, CssScope, ScopedSelector};
+pub use self::extract_dependencies::extract_dependencies;
+use self::extract_dependencies::extract_script_dependencies;
+pub use self::extract_variables_that_change::Assignment;
+pub use self::reactive_declarations::{is_reactive_statement, ReactiveDeclaration};
+pub use self::extract_variables::extract_pattern_names;
+
+use self::errors::Error;
 use super::{Fragment, RustleAst};
 use std::collections::HashSet;
-use swc_common::{
-    errors::{ColorConfig, Handler},
-    sync::Lrc,
-    SourceMap,
-};
 
+mod animations;
+mod bindings;
+mod const_tags;
+mod css_scope;
+pub mod errors;
+mod extract_dependencies;
 mod extract_variables;
 mod extract_variables_that_change;
+mod reactive_declarations;
+mod slots;
 
 #[derive(Debug)]
 pub struct AnalysisResult {
+    /// The top-level bindings of the script, its variables,
+    /// functions, classes and imports
     pub variables: HashSet<String>,
     pub will_change: HashSet<String>,
+    /// The assignments of the script and of the `on:` handlers
+    /// invalidating `will_change` variables
+    pub assignments: Vec<Assignment>,
+    /// The `export let` variables, set by the parent component
+    pub props: Vec<String>,
+    /// Whether the component reads `$$restProps`, the props
+    /// the parent sets that are not `export let` variables
+    pub rest_props: bool,
+    /// The names assigned by `$: name = ...` without a declaration
+    pub implicit_declarations: Vec<String>,
+    /// The `$:` statements, in the order they run
+    pub reactive_declarations: Vec<ReactiveDeclaration>,
+    /// The names of the `<slot>` elements, `default` when unnamed
+    pub slots: Vec<String>,
     pub will_use_in_template: HashSet<String>,
+    pub css: Option<CssScope>,
 }
 
-pub fn analyse(ast: &RustleAst) -> AnalysisResult {
-    let variables = extract_root_variables(&ast.script);
-    let will_change = extract_variables_that_change(&ast.script);
+pub fn analyse(ast: &RustleAst) -> Result<AnalysisResult, Error> {
+    let implicit_declarations = implicit_declarations(&ast.script);
+    let mut variables = extract_root_variables(&ast.script);
+    variables.extend(implicit_declarations.clone());
 
+    let props = extract_props(&ast.script);
+    let mut assignments = extract_variables_that_change(&ast.script);
+    assignments.extend(extract_handler_assignments(&ast.fragments, &ast.script));
+    let reactive_declarations = extract_reactive_declarations(&ast.script)?;
+    let bindings = extract_bindings(&ast.fragments, &ast.script, &variables)?;
+    let slots = extract_slots(&ast.fragments)?;
+    check_const_tags(&ast.fragments)?;
+    check_animations(&ast.fragments)?;
+    variables.extend(extract_root_declarations(&ast.script));
     let mut will_use_in_template = Vec::new();
     for fragment in &ast.fragments {
         let mut used_variables = traverse_fragment(fragment);
         will_use_in_template.append(&mut used_variables);
     }
 
-    AnalysisResult {
+    let rest_props = will_use_in_template.iter().any(|name| name == "$$restProps")
+        || extract_script_dependencies(&ast.script).iter().any(|name| name == "$$restProps");
+
+    let will_change = assignments
+        .iter()
+        .flat_map(|assignment| assignment.names.clone())
+        .chain(reactive_declarations.iter().flat_map(|d| d.assignments.clone()))
+        .chain(props.clone())
+        .chain(bindings)
+        .chain(rest_props.then(|| "$$restProps".to_owned()))
+        .collect::<Vec<String>>();
+
+    Ok(AnalysisResult {
         variables: HashSet::from_iter(variables),
         will_change: HashSet::from_iter(will_change),
         will_use_in_template: HashSet::from_iter(will_use_in_template),
-    }
+        assignments,
+        props,
+        rest_props,
+        implicit_declarations,
+        reactive_declarations,
+        slots,
+        css: ast.style.as_ref().map(CssScope::new),
+    })
 }
 
 fn traverse_fragment(fragment: &Fragment) -> Vec<String> {
@@ -46,23 +106,102 @@ fn traverse_fragment(fragment: &Fragment) -> Vec<String> {
     match fragment {
         Fragment::Import(_) => (),
         Fragment::Script(_) => (),
+        Fragment::Style(_) => (),
         Fragment::Element(f) => {
+            will_use.extend(f.this.iter().flat_map(extract_dependencies));
+
             for child in &f.fragments {
                 let mut child_vars = traverse_fragment(child);
                 will_use.append(&mut child_vars);
             }
 
             for attr in &f.attributes {
-                match &attr.value {
-                    Expr::Ident(ident) => will_use.push(ident.sym.to_string()),
-                    _ => (),
+                will_use.append(&mut extract_dependencies(&attr.value));
+            }
+
+            for handler in &f.handlers {
+                will_use.extend(handler.expression.iter().flat_map(extract_dependencies));
+            }
+
+            for action in &f.actions {
+                will_use.push(action.name.clone());
+                will_use.extend(action.expression.iter().flat_map(extract_dependencies));
+            }
+
+            for transition in &f.transitions {
+                will_use.push(transition.name.clone());
+                will_use.extend(transition.expression.iter().flat_map(extract_dependencies));
+            }
+
+            if let Some(animation) = &f.animation {
+                will_use.push(animation.name.clone());
+                will_use.extend(animation.expression.iter().flat_map(extract_dependencies));
+            }
+        }
+        Fragment::IfBlock(f) => {
+            will_use.append(&mut extract_dependencies(&f.expression));
+
+            for child in f.fragments.iter().chain(f.else_fragments.iter().flatten()) {
+                let mut child_vars = traverse_fragment(child);
+                will_use.append(&mut child_vars);
+            }
+        }
+        Fragment::EachBlock(f) => {
+            will_use.append(&mut extract_dependencies(&f.expression));
+
+            // the context and index are scoped to the block
+            let mut block_names = extract_pattern_names(&f.context);
+            block_names.extend(f.index.clone());
+
+            let mut block_vars = f.key.as_ref().map(extract_dependencies).unwrap_or_default();
+            for child in &f.fragments {
+                let mut child_vars = traverse_fragment(child);
+                block_vars.append(&mut child_vars);
+            }
+            block_vars.retain(|name| !block_names.contains(name));
+            will_use.append(&mut block_vars);
+
+            for child in f.else_fragments.iter().flatten() {
+                let mut child_vars = traverse_fragment(child);
+                will_use.append(&mut child_vars);
+            }
+        }
+        Fragment::AwaitBlock(f) => {
+            will_use.append(&mut extract_dependencies(&f.expression));
+
+            for child in &f.pending_fragments {
+                let mut child_vars = traverse_fragment(child);
+                will_use.append(&mut child_vars);
+            }
+
+            // the value and error are scoped to their branch
+            let branches = [(&f.value, &f.then_fragments), (&f.error, &f.catch_fragments)];
+            for (pattern, fragments) in branches {
+                let block_names = pattern.as_ref().map(extract_pattern_names).unwrap_or_default();
+
+                let mut block_vars = Vec::new();
+                for child in fragments.iter().flatten() {
+                    let mut child_vars = traverse_fragment(child);
+                    block_vars.append(&mut child_vars);
                 }
+                block_vars.retain(|name| !block_names.contains(name));
+                will_use.append(&mut block_vars);
+            }
+        }
+        Fragment::KeyBlock(f) => {
+            will_use.append(&mut extract_dependencies(&f.expression));
+
+            for child in &f.fragments {
+                let mut child_vars = traverse_fragment(child);
+                will_use.append(&mut child_vars);
             }
         }
-        Fragment::Expression(f) => match f {
-            Expr::Ident(ident) => will_use.push(ident.sym.to_string()),
-            _ => (),
-        },
+        Fragment::RawHtml(f) => will_use.append(&mut extract_dependencies(&f.expression)),
+        Fragment::DebugTag(f) => {
+            will_use.extend(f.identifiers.iter().map(|ident| ident.sym.to_string()))
+        }
+        Fragment::ConstTag(f) => will_use.append(&mut extract_dependencies(&f.init)),
+        Fragment::Expression(f) => will_use.append(&mut extract_dependencies(f)),
         Fragment::Text(_) => (),
     }
 
@@ -74,13 +213,48 @@ mod tests {
     use std::fs;
 
     use crate::compiler::parse::Parser;
+    use crate::compiler::Fragment;
 
     use super::analyse;
 
     #[test]
     fn test_analyse() {
         let source = fs::read_to_string("./tests/demo/app.rustle").unwrap();
-        let ast = Parser::new(&source).parse();
-        let result = analyse(&ast);
+        let ast = Parser::new(&source).parse().unwrap();
+        let result = analyse(&ast).unwrap();
+
+        assert!(result.variables.contains("counter"));
+        assert!(result.will_change.contains("counter"));
+        assert!(result.will_use_in_template.contains("counter"));
+    }
+
+    #[test]
+    fn test_analyse_props() {
+        let source = fs::read_to_string("./tests/component_update/Nested.rustle").unwrap();
+        let ast = Parser::new(&source).parse().unwrap();
+        let result = analyse(&ast).unwrap();
+
+        assert_eq!(result.props, vec!["q"]);
+        assert!(result.variables.contains("q"));
+        assert!(result.will_change.contains("q"));
+    }
+
+    #[test]
+    fn test_analyse_css() {
+        let source = "<p class=\"intro\">a</p><span>b</span><span class:intro>c</span>
+            <style>p.intro, span.intro { color: red; }</style>";
+        let ast = Parser::new(source).parse().unwrap();
+        let result = analyse(&ast).unwrap();
+        let css = result.css.unwrap();
+
+        assert!(css.class.starts_with("svelte-"));
+        match (&ast.fragments[0], &ast.fragments[1], &ast.fragments[2]) {
+            (Fragment::Element(p), Fragment::Element(span), Fragment::Element(directive)) => {
+                assert!(css.applies_to(p));
+                assert!(!css.applies_to(span));
+                assert!(css.applies_to(directive));
+            }
+            _ => panic!(),
+        }
     }
 }
@@ -0,0 +1,333 @@
+use swc_common::Span;
+use swc_ecma_ast::{
+    ArrowExpr, AssignExpr, AssignPatProp, BindingIdent, Expr, Function, Ident, LabeledStmt,
+    MemberExpr, MemberProp, Module, ModuleItem, Pat, PatOrExpr, PropName, Stmt, UpdateExpr,
+};
+use swc_ecma_visit::{Visit, VisitWith};
+
+use super::errors::Error;
+use super::extract_variables::{extract_pattern_names, extract_root_variables};
+
+/// A `$: ...` statement of the script, re-run whenever
+/// one of its `dependencies` changes
+#[derive(Debug, Clone)]
+pub struct ReactiveDeclaration {
+    /// The span of the labeled statement
+    pub span: Span,
+    /// The top-level names the statement assigns
+    pub assignments: Vec<String>,
+    /// The top-level names the statement reads, the targets
+    /// of its assignments like `a` in `a = b` excluded
+    pub dependencies: Vec<String>,
+}
+
+/// Returns the `$:` statements of the script sorted so that every
+/// declaration runs after the ones assigning its dependencies.
+pub fn extract_reactive_declarations(script: &Module) -> Result<Vec<ReactiveDeclaration>, Error> {
+    let statements = reactive_statements(script);
+
+    let mut variables = extract_root_variables(script);
+    variables.extend(implicit_declarations(script));
+
+    let declarations = statements
+        .into_iter()
+        .map(|stmt| {
+            let mut visitor = ReactiveVisitor {
+                depth: 0,
+                assignments: Vec::new(),
+                dependencies: Vec::new(),
+            };
+            stmt.body.visit_with(&mut visitor);
+
+            ReactiveDeclaration {
+                span: stmt.span,
+                assignments: unique(visitor.assignments, &variables),
+                dependencies: unique(visitor.dependencies, &variables),
+            }
+        })
+        .collect::<Vec<ReactiveDeclaration>>();
+
+    sort(declarations)
+}
+
+/// Returns the names assigned by `$: name = ...` statements
+/// that are not declared in the script, like Svelte they are
+/// declared by the compiler.
+pub fn implicit_declarations(script: &Module) -> Vec<String> {
+    let variables = extract_root_variables(script);
+
+    let mut names = Vec::new();
+    for stmt in reactive_statements(script) {
+        if let Stmt::Expr(expr) = &*stmt.body {
+            if let Expr::Assign(AssignExpr {
+                left: PatOrExpr::Pat(pat),
+                ..
+            }) = &*expr.expr
+            {
+                for name in extract_pattern_names(pat) {
+                    if !variables.contains(&name) && !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// Checks if a top-level statement is a `$:` statement
+pub fn is_reactive_statement(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::Labeled(labeled) if &*labeled.label.sym == "$")
+}
+
+fn reactive_statements(script: &Module) -> Vec<&LabeledStmt> {
+    script
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::Stmt(stmt @ Stmt::Labeled(labeled)) if is_reactive_statement(stmt) => {
+                Some(labeled)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn unique(names: Vec<String>, variables: &[String]) -> Vec<String> {
+    let mut unique = Vec::new();
+    for name in names {
+        if variables.contains(&name) && !unique.contains(&name) {
+            unique.push(name);
+        }
+    }
+
+    unique
+}
+
+/// Sorts the declarations depth first, keeping the source order
+/// of independent ones, and reports the first cycle found.
+fn sort(declarations: Vec<ReactiveDeclaration>) -> Result<Vec<ReactiveDeclaration>, Error> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        None,
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        index: usize,
+        declarations: &[ReactiveDeclaration],
+        marks: &mut Vec<Mark>,
+        path: &mut Vec<usize>,
+        sorted: &mut Vec<usize>,
+    ) -> Result<(), Error> {
+        match marks[index] {
+            Mark::Done => return Ok(()),
+            Mark::Visiting => {
+                let start = path.iter().position(|i| *i == index).unwrap();
+                let mut cycle = path[start..]
+                    .iter()
+                    .map(|i| declarations[*i].assignments.join(", "))
+                    .collect::<Vec<String>>();
+                cycle.push(cycle[0].clone());
+                return Err(Error::cyclical_reactive_declaration(
+                    &cycle,
+                    declarations[index].span,
+                ));
+            }
+            Mark::None => (),
+        }
+
+        marks[index] = Mark::Visiting;
+        path.push(index);
+        // `$: if (a > 9) a = 9` reads and assigns `a`, it
+        // doesn't wait on the other declarations assigning it
+        let current = &declarations[index];
+        for (other, declaration) in declarations.iter().enumerate() {
+            let assigns_dependency = declaration.assignments.iter().any(|name| {
+                current.dependencies.contains(name) && !current.assignments.contains(name)
+            });
+            if other != index && assigns_dependency {
+                visit(other, declarations, marks, path, sorted)?;
+            }
+        }
+        path.pop();
+        marks[index] = Mark::Done;
+        sorted.push(index);
+
+        Ok(())
+    }
+
+    let mut marks = vec![Mark::None; declarations.len()];
+    let mut sorted = Vec::new();
+    for index in 0..declarations.len() {
+        visit(index, &declarations, &mut marks, &mut Vec::new(), &mut sorted)?;
+    }
+
+    Ok(sorted.into_iter().map(|index| declarations[index].clone()).collect())
+}
+
+struct ReactiveVisitor {
+    /// The number of enclosing functions, their assignments
+    /// only happen when they are called
+    depth: usize,
+    assignments: Vec<String>,
+    dependencies: Vec<String>,
+}
+
+impl ReactiveVisitor {
+    fn assign_expr(&mut self, expr: &Expr) {
+        match expr.unwrap_parens() {
+            Expr::Ident(ident) => self.assignments.push(ident.sym.to_string()),
+            Expr::Member(member) => self.assign_expr(&member.obj),
+            _ => (),
+        }
+    }
+}
+
+impl Visit for ReactiveVisitor {
+    fn visit_assign_expr(&mut self, ae: &AssignExpr) {
+        if self.depth == 0 {
+            match &ae.left {
+                PatOrExpr::Pat(pat) => match &**pat {
+                    Pat::Expr(expr) => self.assign_expr(expr),
+                    pat => self.assignments.extend(extract_pattern_names(pat)),
+                },
+                PatOrExpr::Expr(expr) => self.assign_expr(expr),
+            }
+        }
+
+        // `a.b = c` reads `a`, `a = c` doesn't
+        match &ae.left {
+            PatOrExpr::Pat(pat) => match &**pat {
+                Pat::Expr(expr) => expr.visit_with(self),
+                Pat::Ident(_) => (),
+                pat => pat.visit_with(self),
+            },
+            PatOrExpr::Expr(expr) => match expr.unwrap_parens() {
+                Expr::Ident(_) => (),
+                expr => expr.visit_with(self),
+            },
+        }
+        ae.right.visit_with(self);
+    }
+
+    fn visit_update_expr(&mut self, ue: &UpdateExpr) {
+        if self.depth == 0 {
+            self.assign_expr(&ue.arg);
+        }
+        ue.visit_children_with(self);
+    }
+
+    fn visit_ident(&mut self, i: &Ident) {
+        self.dependencies.push(i.sym.to_string());
+    }
+
+    // the names a pattern binds or assigns are not read,
+    // its default values and computed keys are
+    fn visit_binding_ident(&mut self, _: &BindingIdent) {}
+
+    fn visit_assign_pat_prop(&mut self, app: &AssignPatProp) {
+        app.value.visit_with(self);
+    }
+
+    fn visit_member_expr(&mut self, me: &MemberExpr) {
+        me.obj.visit_with(self);
+        if let MemberProp::Computed(c) = &me.prop {
+            c.visit_with(self);
+        }
+    }
+
+    fn visit_prop_name(&mut self, pn: &PropName) {
+        if let PropName::Computed(c) = pn {
+            c.visit_with(self);
+        }
+    }
+
+    fn visit_function(&mut self, f: &Function) {
+        self.depth += 1;
+        f.visit_children_with(self);
+        self.depth -= 1;
+    }
+
+    fn visit_arrow_expr(&mut self, ae: &ArrowExpr) {
+        self.depth += 1;
+        ae.visit_children_with(self);
+        self.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::parse::Parser;
+
+    use super::{extract_reactive_declarations, implicit_declarations};
+
+    #[test]
+    fn test_extract_reactive_declarations() {
+        let source = "<script>
+            let count = 0;
+            $: quadrupled = doubled * 2;
+            $: doubled = count * 2;
+            $: { console.log(count, quadrupled); }
+            $: count = count + 0;
+        </script>";
+        let ast = Parser::new(source).parse().unwrap();
+        let declarations = extract_reactive_declarations(&ast.script).unwrap();
+
+        let order = declarations
+            .iter()
+            .map(|d| (d.assignments.clone(), d.dependencies.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (vec!["count".to_owned()], vec!["count".to_owned()]),
+                (vec!["doubled".to_owned()], vec!["count".to_owned()]),
+                (vec!["quadrupled".to_owned()], vec!["doubled".to_owned()]),
+                (vec![], vec!["count".to_owned(), "quadrupled".to_owned()]),
+            ]
+        );
+        assert_eq!(implicit_declarations(&ast.script), vec!["quadrupled", "doubled"]);
+    }
+
+    #[test]
+    fn test_reactive_declaration_reading_its_assignment() {
+        let source = "<script>
+            let count = 0, point = {}, x, y;
+            $: doubled = count * 2;
+            $: if (count >= 10) count = 9;
+            $: total = count;
+            $: total += 1;
+            $: ({ x = count, y } = point);
+        </script>";
+        let ast = Parser::new(source).parse().unwrap();
+        let declarations = extract_reactive_declarations(&ast.script).unwrap();
+
+        let order = declarations
+            .iter()
+            .map(|d| (d.assignments.clone(), d.dependencies.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (vec!["count".to_owned()], vec!["count".to_owned()]),
+                (vec!["doubled".to_owned()], vec!["count".to_owned()]),
+                (vec!["total".to_owned()], vec!["count".to_owned()]),
+                (vec!["total".to_owned()], vec![]),
+                (vec!["x".to_owned(), "y".to_owned()], vec!["count".to_owned(), "point".to_owned()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cyclical_reactive_declarations() {
+        let source = "<script>$: a = b + 1;\n$: b = a + 1;</script>";
+        let ast = Parser::new(source).parse().unwrap();
+        let error = extract_reactive_declarations(&ast.script).err().unwrap();
+
+        assert_eq!(error.code, "cyclical-reactive-declaration");
+        assert_eq!(error.message, "Cyclical dependency detected: a → b → a");
+    }
+}
@@ -0,0 +1,90 @@
+use super::css_scope::static_value;
+use super::errors::Error;
+use crate::compiler::Fragment;
+
+/// Returns the names of the `<slot>` elements of the template,
+/// `default` for the ones without a `name`, checking that the
+/// elements with a `slot` attribute are children of a component.
+pub fn extract_slots(fragments: &[Fragment]) -> Result<Vec<String>, Error> {
+    let mut slots = Vec::new();
+    collect_slots(fragments, false, &mut slots)?;
+
+    Ok(slots)
+}
+
+fn collect_slots(
+    fragments: &[Fragment],
+    in_component: bool,
+    slots: &mut Vec<String>,
+) -> Result<(), Error> {
+    for fragment in fragments {
+        match fragment {
+            Fragment::Element(element) => {
+                if let Some(attr) = element.attributes.iter().find(|attr| attr.name == "slot") {
+                    if !in_component {
+                        return Err(Error::invalid_slotted_content(element.span));
+                    }
+                    if static_value(&attr.value).is_none() {
+                        return Err(Error::invalid_slot_attribute(element.span));
+                    }
+                }
+
+                if element.name == "slot" {
+                    let name = match element.attributes.iter().find(|attr| attr.name == "name") {
+                        Some(attr) => static_value(&attr.value)
+                            .ok_or_else(|| Error::dynamic_slot_name(element.span))?,
+                        None => "default".to_owned(),
+                    };
+                    if !slots.contains(&name) {
+                        slots.push(name);
+                    }
+                }
+
+                collect_slots(&element.fragments, element.is_component, slots)?;
+            }
+            Fragment::IfBlock(block) => {
+                collect_slots(&block.fragments, false, slots)?;
+                collect_slots(block.else_fragments.as_deref().unwrap_or_default(), false, slots)?;
+            }
+            Fragment::EachBlock(block) => {
+                collect_slots(&block.fragments, false, slots)?;
+                collect_slots(block.else_fragments.as_deref().unwrap_or_default(), false, slots)?;
+            }
+            Fragment::KeyBlock(block) => collect_slots(&block.fragments, false, slots)?,
+            Fragment::AwaitBlock(block) => {
+                collect_slots(&block.pending_fragments, false, slots)?;
+                collect_slots(block.then_fragments.as_deref().unwrap_or_default(), false, slots)?;
+                collect_slots(block.catch_fragments.as_deref().unwrap_or_default(), false, slots)?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::parse::Parser;
+
+    #[test]
+    fn test_extract_slots() {
+        let source = "<div><slot /></div>{#if a}<slot name=\"header\">fallback</slot>{/if}";
+        let ast = Parser::new(source).parse().unwrap();
+
+        assert_eq!(analyse(&ast).unwrap().slots, vec!["default", "header"]);
+    }
+
+    #[test]
+    fn test_invalid_slots() {
+        let error = |source: &str| {
+            let ast = Parser::new(source).parse().unwrap();
+            analyse(&ast).err().unwrap().code
+        };
+
+        assert_eq!(error("<slot name={a} />"), "dynamic-slot-name");
+        assert_eq!(error("<div slot=\"header\" />"), "invalid-slotted-content");
+        assert_eq!(error("<Layout><div slot={a} /></Layout>"), "invalid-slot-attribute");
+    }
+}
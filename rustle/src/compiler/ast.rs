@@ -1,33 +1,180 @@
 use serde::Serialize;
-use swc_ecma_ast::{Expr, Script};
+use swc_css::ast::Stylesheet;
+use swc_common::Span;
+use swc_ecma_ast::{Expr, Ident, Module, Pat};
 use swc_html_ast::Text;
 
 #[derive(Serialize)]
 pub struct RustleAst {
     pub import: String,
-    pub script: Script,
+    pub script: Module,
+    pub style: Option<RustleStyle>,
+    /// The `<svelte:options>` tag
+    pub options: Option<RustleOptions>,
     pub fragments: Vec<Fragment>,
 }
 
+/// The component options set by `<svelte:options>`
+#[derive(Serialize)]
+pub struct RustleOptions {
+    pub span: Span,
+    /// The tag name the component is registered as, when
+    /// compiled with `CompileOptions::custom_element`
+    pub custom_element: Option<String>,
+}
+
+/// The top-level `<style>` tag, `content` is the raw css
+/// starting at the byte offset `start` in the component source.
+#[derive(Serialize)]
+pub struct RustleStyle {
+    pub content: String,
+    pub start: usize,
+    pub stylesheet: Stylesheet,
+}
+
+/// `name="value"`, `name={value}` or a directive like `bind:name={value}`,
+/// a `{...value}` spread is named `...`
 #[derive(Serialize)]
 pub struct RustleAttribute {
     pub name: String,
     pub value: Expr,
 }
 
+/// `on:name|modifier={expression}`, without an expression
+/// the event is forwarded to the parent component.
+#[derive(Serialize)]
+pub struct RustleEventHandler {
+    pub span: Span,
+    pub name: String,
+    pub modifiers: Vec<String>,
+    pub expression: Option<Expr>,
+}
+
+/// `use:action={expression}`, the expression is passed
+/// to the action as its parameters
+#[derive(Serialize)]
+pub struct RustleAction {
+    pub span: Span,
+    pub name: String,
+    pub expression: Option<Expr>,
+}
+
+/// `transition:name|modifier={params}`, `in:name` or `out:name`
+#[derive(Serialize)]
+pub struct RustleTransition {
+    pub span: Span,
+    pub name: String,
+    pub expression: Option<Expr>,
+    /// Plays when the element is added, set by `transition:` and `in:`
+    pub intro: bool,
+    /// Plays when the element is removed, set by `transition:` and `out:`
+    pub outro: bool,
+    /// Only plays when the block of the element itself is added or
+    /// removed, not one of the enclosing blocks, unless `|global` is set
+    pub local: bool,
+}
+
 #[derive(Serialize)]
 pub struct RustleElement {
+    pub span: Span,
     pub name: String,
     pub is_component: bool,
+    /// The `this` attribute of `<svelte:component>` and `<svelte:element>`,
+    /// the component constructor or the tag name
+    pub this: Option<Expr>,
     pub attributes: Vec<RustleAttribute>,
+    pub handlers: Vec<RustleEventHandler>,
+    pub actions: Vec<RustleAction>,
+    pub transitions: Vec<RustleTransition>,
+    /// `animate:name={params}`, shaped like an action
+    pub animation: Option<RustleAction>,
+    pub fragments: Vec<Fragment>,
+}
+
+/// `{#if expression}...{:else if other}...{:else}...{/if}`
+///
+/// An `{:else if}` branch is stored as an `else_fragments`
+/// containing a single nested `Fragment::IfBlock`.
+#[derive(Serialize)]
+pub struct RustleIfBlock {
+    pub span: Span,
+    pub expression: Expr,
+    pub fragments: Vec<Fragment>,
+    pub else_fragments: Option<Vec<Fragment>>,
+}
+
+/// `{#each expression as context, index (key)}...{:else}...{/each}`
+#[derive(Serialize)]
+pub struct RustleEachBlock {
+    pub span: Span,
+    pub expression: Expr,
+    pub context: Pat,
+    pub index: Option<String>,
+    pub key: Option<Expr>,
     pub fragments: Vec<Fragment>,
+    pub else_fragments: Option<Vec<Fragment>>,
+}
+
+/// `{#await expression}...{:then value}...{:catch error}...{/await}`
+///
+/// The shorthands `{#await expression then value}` and
+/// `{#await expression catch error}` have no pending fragments.
+#[derive(Serialize)]
+pub struct RustleAwaitBlock {
+    pub span: Span,
+    pub expression: Expr,
+    pub value: Option<Pat>,
+    pub error: Option<Pat>,
+    pub pending_fragments: Vec<Fragment>,
+    pub then_fragments: Option<Vec<Fragment>>,
+    pub catch_fragments: Option<Vec<Fragment>>,
+}
+
+/// `{#key expression}...{/key}`, the fragments are recreated
+/// whenever the value of the expression changes
+#[derive(Serialize)]
+pub struct RustleKeyBlock {
+    pub span: Span,
+    pub expression: Expr,
+    pub fragments: Vec<Fragment>,
+}
+
+/// `{@html expression}`, the value is inserted as raw html
+#[derive(Serialize)]
+pub struct RustleRawHtml {
+    pub span: Span,
+    pub expression: Expr,
+}
+
+/// `{@debug a, b}`, logs the names whenever they change in dev mode,
+/// `{@debug}` alone on every update
+#[derive(Serialize)]
+pub struct RustleDebugTag {
+    pub span: Span,
+    pub identifiers: Vec<Ident>,
+}
+
+/// `{@const pattern = init}`, declared in the enclosing block
+#[derive(Serialize)]
+pub struct RustleConstTag {
+    pub span: Span,
+    pub pattern: Pat,
+    pub init: Expr,
 }
 
 #[derive(Serialize)]
 pub enum Fragment {
     Import(String),
-    Script(Script),
+    Script(Module),
+    Style(RustleStyle),
     Element(RustleElement),
+    IfBlock(RustleIfBlock),
+    EachBlock(RustleEachBlock),
+    AwaitBlock(RustleAwaitBlock),
+    KeyBlock(RustleKeyBlock),
+    RawHtml(RustleRawHtml),
+    DebugTag(RustleDebugTag),
+    ConstTag(RustleConstTag),
     Expression(Expr),
     Text(Text),
 }
@@ -1 +1,468 @@
 pub mod utils;
+
+use magic_string::{GenerateDecodedMapOptions, SourceMap};
+use serde::Serialize;
+use strum_macros::EnumString;
+use swc_common::BytePos;
+
+use super::analyse::{analyse, AnalysisResult, ScopedSelector};
+use super::generate::{generate, generate_css, generate_ssr};
+use super::parse::Parser;
+use super::utils::{CompileError, NewErrorProps, Warning};
+use super::RustleAst;
+
+/// The kind of javascript generated for the component,
+/// `"dom"` and `"ssr"` parse into it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Generate {
+    /// A component creating and updating its dom nodes in the browser
+    #[default]
+    Dom,
+    /// A component rendering its html on the server with
+    /// `render(props) -> { html, css, head }`
+    Ssr,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// The component file name, used in errors, warnings and source maps
+    pub filename: Option<String>,
+    /// Appends the js source map to the code as a data url comment
+    pub inline_sourcemap: bool,
+    /// Generates code for development, `{@debug}` tags log
+    /// their values and pause in the debugger
+    pub dev: bool,
+    /// Generates a dom component or a server side renderer
+    pub generate: Generate,
+    /// Generates a dom component whose `create(target)` claims the nodes
+    /// rendered on the server in `target`, rather than creating them
+    pub hydratable: bool,
+    /// Exports the dom component as a custom element class, registered
+    /// as the tag of `<svelte:options customElement="...">`
+    pub custom_element: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JsResult {
+    pub code: String,
+    pub map: Option<SourceMap>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CssResult {
+    pub code: String,
+    pub map: Option<SourceMap>,
+}
+
+/// A top-level variable declared in the component `<script>`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Var {
+    pub name: String,
+    /// Assigned to after its declaration
+    pub reassigned: bool,
+    /// Used in the template
+    pub referenced: bool,
+}
+
+#[derive(Serialize)]
+pub struct CompileResult {
+    pub js: JsResult,
+    /// `None` if the component has no `<style>` tag
+    pub css: Option<CssResult>,
+    pub warnings: Vec<Warning>,
+    pub ast: RustleAst,
+    pub vars: Vec<Var>,
+}
+
+/// Parses, analyses and generates a component in one go.
+///
+/// The generated javascript imports its helpers from `rustle/internal`,
+/// the module in [`crate::runtime::INTERNAL`] emitted once for all the
+/// components.
+///
+/// # Arguments
+///
+/// * `source` - The content of the `.rustle` file
+/// * `options` - The `CompileOptions`
+///
+/// # Examples
+///
+/// ```
+/// use rustle::{compile, CompileOptions};
+///
+/// let result = compile("<h1>Hello</h1>", CompileOptions::default()).unwrap();
+/// assert!(result.js.code.contains("$$element('h1')"));
+/// assert!(result.css.is_none());
+/// ```
+pub fn compile(source: &str, options: CompileOptions) -> Result<CompileResult, Box<CompileError>> {
+    let mut parser = Parser::new(source);
+    parser.filename = options.filename.clone();
+    let ast = parser.parse()?;
+    let analysis = analyse(&ast).map_err(|error| {
+        CompileError::new(
+            &error.message,
+            NewErrorProps {
+                name: "ValidationError",
+                code: &error.code,
+                source,
+                filename: options.filename.as_deref().unwrap_or_default(),
+                start: span_position(source, error.span.lo),
+                end: Some(span_position(source, error.span.hi)),
+            },
+        )
+    })?;
+
+    let css = generate_css(source, &ast, &analysis);
+
+    let (mut code, mut map) = match options.generate {
+        Generate::Dom => generate(source, &ast, &analysis, &options),
+        // the rendered css is collected along with the html
+        Generate::Ssr => {
+            let css = css.as_ref().map(|css| css.to_string());
+            generate_ssr(source, &ast, &analysis, css.as_deref())
+        }
+    };
+    map.sources = vec![options.filename.clone()];
+    if options.inline_sourcemap {
+        code.push_str(&format!("\n//# sourceMappingURL={}", map.to_url().unwrap()));
+    }
+    let js = JsResult {
+        code,
+        map: Some(map),
+    };
+
+    let css = css.map(|css| CssResult {
+        code: css.to_string(),
+        map: css
+            .generate_map(GenerateDecodedMapOptions {
+                source: options.filename.clone(),
+                include_content: true,
+                ..Default::default()
+            })
+            .ok(),
+    });
+
+    Ok(CompileResult {
+        js,
+        css,
+        warnings: [
+            options_warnings(source, &ast, &options),
+            css_warnings(source, &ast, &analysis, &options),
+        ]
+        .concat(),
+        vars: vars_report(&analysis),
+        ast,
+    })
+}
+
+fn options_warnings(source: &str, ast: &RustleAst, options: &CompileOptions) -> Vec<Warning> {
+    let span = match &ast.options {
+        Some(tag) if tag.custom_element.is_some() && !options.custom_element => tag.span,
+        _ => return Vec::new(),
+    };
+
+    vec![Warning::new(
+        "The 'customElement' option is used when generating a custom element. \
+        Did you forget the 'customElement: true' compile option?",
+        NewErrorProps {
+            name: "Warning",
+            code: "missing-custom-element-compile-options",
+            source,
+            filename: options.filename.as_deref().unwrap_or_default(),
+            start: span_position(source, span.lo),
+            end: Some(span_position(source, span.hi)),
+        },
+    )]
+}
+
+fn css_warnings(
+    source: &str,
+    ast: &RustleAst,
+    analysis: &AnalysisResult,
+    options: &CompileOptions,
+) -> Vec<Warning> {
+    let (style, scope) = match (&ast.style, &analysis.css) {
+        (Some(style), Some(scope)) => (style, scope),
+        _ => return Vec::new(),
+    };
+
+    // selector positions are byte offsets in the style content
+    let index = |offset: usize| source_index(source, style.start + offset);
+
+    scope
+        .unused_selectors(&ast.fragments)
+        .into_iter()
+        .filter_map(|selector| match selector {
+            ScopedSelector::Local { start, end, .. } => Some((*start, *end)),
+            ScopedSelector::Global(_) => None,
+        })
+        .map(|(start, end)| {
+            Warning::new(
+                &format!("Unused CSS selector \"{}\"", &style.content[start..end]),
+                NewErrorProps {
+                    name: "Warning",
+                    code: "css-unused-selector",
+                    source,
+                    filename: options.filename.as_deref().unwrap_or_default(),
+                    start: index(start),
+                    end: Some(index(end)),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Returns the byte offset of a span position, which is shifted by 1
+/// like the spans of SWC, a dummy position being the start of the source
+fn span_position(source: &str, pos: BytePos) -> usize {
+    source_index(source, (pos.0 as usize).saturating_sub(1))
+}
+
+/// Clamps a byte offset to the source, on the start of a character
+fn source_index(source: &str, offset: usize) -> usize {
+    let mut index = offset.min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+fn vars_report(analysis: &AnalysisResult) -> Vec<Var> {
+    let mut vars = analysis
+        .variables
+        .iter()
+        .map(|name| Var {
+            name: name.clone(),
+            reassigned: analysis.will_change.contains(name),
+            referenced: analysis.will_use_in_template.contains(name),
+        })
+        .collect::<Vec<Var>>();
+    vars.sort_by(|a, b| a.name.cmp(&b.name));
+
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_compile_destructured_props() {
+        let source = "<script>export let { a, b: [c] } = defaults, d = 3;</script><p>{a} {c} {d}</p>";
+        for generate in [Generate::Dom, Generate::Ssr] {
+            let options = CompileOptions {
+                generate,
+                ..Default::default()
+            };
+            let code = compile(source, options).unwrap().js.code;
+
+            // the destructured props are read from `$$props` once declared
+            assert!(code.contains("let { d =3  } = $$props, { a , b: [c]  } = defaults;\n({ a =a , c =c  } = $$props);"));
+        }
+    }
+
+    #[test]
+    fn test_compile_global_selectors() {
+        let source = "<p class=\"b\">a</p>
+            <style>:global(.a):global(.b) { color: red; } p:global(.a) { color: blue; } :global(.a).b { margin: 0; }</style>";
+        let result = compile(source, CompileOptions::default()).unwrap();
+        let css = result.css.unwrap().code;
+        let class = css.split_whitespace().find_map(|word| word.strip_prefix("p.")).unwrap();
+        let class = class.strip_suffix(".a").unwrap();
+
+        assert_eq!(
+            css,
+            format!(
+                ".a.b {{ color: red; }} p.{class}.a {{ color: blue; }} .{class}.a.b {{ margin: 0; }}",
+                class = class
+            )
+        );
+        assert!(class.starts_with("svelte-"));
+    }
+
+    #[test]
+    fn test_compile() {
+        let source = fs::read_to_string("./tests/style/app.rustle").unwrap();
+        let result = compile(&source, CompileOptions::default()).unwrap();
+
+        assert!(result.js.code.contains("export default function $$self($$props = {})"));
+        assert!(result.js.code.contains("from 'rustle/internal';"));
+
+        let css = result.css.unwrap();
+        assert!(css.code.starts_with("h1.svelte-"));
+        assert!(css.map.is_some());
+
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].code, "css-unused-selector");
+        assert_eq!(result.warnings[0].message, "Unused CSS selector \"em\"");
+        assert_eq!(result.warnings[0].start.line, 20);
+
+        let names = result.vars.iter().map(|var| var.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["active", "toggle"]);
+        assert_eq!(
+            result.vars[1],
+            Var {
+                name: "toggle".into(),
+                reassigned: false,
+                referenced: true,
+            }
+        );
+
+        let source = "<script>
+            let a = 1, { b } = {}, unused;
+            function increment() { a += 1; }
+        </script>
+        <p on:click={() => increment()}>{a + 1} {b.c}</p>";
+        let result = compile(source, CompileOptions::default()).unwrap();
+        let vars = result
+            .vars
+            .iter()
+            .map(|var| (var.name.as_str(), var.reassigned, var.referenced))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vars,
+            vec![
+                ("a", true, true),
+                ("b", false, true),
+                ("increment", false, true),
+                ("unused", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_span_position() {
+        let source = "<p>é</p>";
+        assert_eq!(span_position(source, BytePos(0)), 0);
+        assert_eq!(span_position(source, BytePos(4)), 3);
+        // inside `é`
+        assert_eq!(span_position(source, BytePos(5)), 3);
+        assert_eq!(span_position(source, BytePos(100)), source.len());
+    }
+
+    #[test]
+    fn test_compile_non_ascii() {
+        let source = "<script>let a = 'é';</script>\n<p title=\"é{a}ü\">ö</p>\n<style>p::after { content: 'ü'; } em { color: red; }</style>";
+        let result = compile(source, CompileOptions::default()).unwrap();
+
+        assert!(result.js.code.contains("`é${a}ü`"));
+        assert!(result.css.unwrap().code.contains("content: 'ü'"));
+        assert_eq!(result.warnings[0].message, "Unused CSS selector \"em\"");
+        assert_eq!((result.warnings[0].start.line, result.warnings[0].start.column), (3, 34));
+    }
+
+    #[test]
+    fn test_compile_sourcemap() {
+        let source = "<script>\n\tlet name = 'world';\n</script>\n\n<h1>Hello {name}</h1>";
+        let options = CompileOptions {
+            filename: Some("App.rustle".into()),
+            inline_sourcemap: true,
+            ..Default::default()
+        };
+        let result = compile(source, options).unwrap();
+
+        let map = result.js.map.unwrap();
+        assert_eq!(map.sources, vec![Some("App.rustle".to_owned())]);
+        assert_eq!(map.sources_content, vec![Some(source.to_owned())]);
+        assert!(!result.js.code.contains('\u{1}'));
+        assert!(result
+            .js
+            .code
+            .contains("//# sourceMappingURL=data:application/json;charset=utf-8;base64,"));
+
+        // the script body and the `<h1>` statements have mappings
+        let lines = map.mappings.split(';').collect::<Vec<&str>>();
+        let code_lines = result.js.code.lines().collect::<Vec<&str>>();
+        let line = |text: &str| code_lines.iter().position(|line| line.contains(text)).unwrap();
+        assert!(!lines[line("let name = 'world'")].is_empty());
+        assert!(!lines[line("$$element('h1')")].is_empty());
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let options = CompileOptions {
+            filename: Some("App.rustle".into()),
+            ..Default::default()
+        };
+        let error = compile("<div>", options).err().unwrap();
+
+        assert_eq!(error.code, "unclosed-element");
+        assert_eq!(error.filename, "App.rustle");
+
+        let source = "<script>\n\t$: a = b;\n\t$: b = a;\n</script>";
+        let error = compile(source, CompileOptions::default()).err().unwrap();
+
+        assert_eq!(error.code, "cyclical-reactive-declaration");
+        assert_eq!(error.start.line, 2);
+    }
+
+    #[test]
+    fn test_compile_dev() {
+        let source = "<script>let a = 1;</script><button on:click={() => a++}>{@debug a}</button>";
+        let result = compile(source, CompileOptions::default()).unwrap();
+        assert!(!result.js.code.contains("debugger"));
+
+        let options = CompileOptions {
+            dev: true,
+            ..Default::default()
+        };
+        let result = compile(source, options).unwrap();
+        assert!(result.js.code.contains("console.log({ a });\ndebugger;"));
+    }
+
+    #[test]
+    fn test_compile_hydratable() {
+        let source = "<script>let a = 1;</script><p on:click={() => a++}>{a}</p>{#if a}<b>a</b>{/if}";
+        let result = compile(source, CompileOptions::default()).unwrap();
+        assert!(!result.js.code.contains("claim"));
+
+        let options = CompileOptions {
+            hydratable: true,
+            ..Default::default()
+        };
+        let result = compile(source, options).unwrap();
+        let code = result.js.code;
+        assert!(code.contains("const $$hydrating = $$start_hydrating(target, $$props.$$nested, false);"));
+        assert!(code.contains("p_1 = $$claim_element(target, anchor, 'p');"));
+        assert!(code.contains("txt_2 = $$claim_text(p_1, null, a);"));
+        assert!(code.contains("= $$claim_comment(target, anchor);"));
+        assert!(code.contains("$$end_hydrating($$hydrating);"));
+    }
+
+    #[test]
+    fn test_compile_custom_element() {
+        let source = "<svelte:options customElement=\"my-counter\" />
+            <script>export let count = 0;</script><p>{count}</p><style>p { color: red; }</style>";
+        let result = compile(source, CompileOptions::default()).unwrap();
+        assert!(result.js.code.contains("export default function $$self"));
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].code, "missing-custom-element-compile-options");
+
+        let options = CompileOptions {
+            custom_element: true,
+            ..Default::default()
+        };
+        let result = compile(source, options).unwrap();
+        let code = result.js.code;
+        assert!(result.warnings.is_empty());
+        assert!(!code.contains("export default function"));
+        assert!(code.contains("const $$Element = $$create_custom_element($$self, [\"count\"], \"p.svelte-"));
+        assert!(code.contains("customElements.define(\"my-counter\", $$Element);"));
+        assert!(code.contains("export default $$Element;"));
+
+        // the css is a javascript string, without rust escapes like `\u{7f}`
+        let source = "<p>a</p><style>p::after { content: \"\u{7f}\\201C\"; }</style>";
+        let options = CompileOptions {
+            custom_element: true,
+            ..Default::default()
+        };
+        let code = compile(source, options).unwrap().js.code;
+        assert!(code.contains("$$create_custom_element($$self, [], \"p.svelte-"));
+        assert!(code.contains("content: \\\"\u{7f}\\\\201C\\\";"));
+        assert!(!code.contains("\\u{7f}"));
+    }
+}
@@ -0,0 +1,45 @@
+use magic_string::MagicString;
+
+use crate::compiler::analyse::{AnalysisResult, ScopedSelector};
+use crate::compiler::RustleAst;
+
+/// Generates the css of the `<style>` tag with every
+/// selector scoped to the component hash class.
+///
+/// The edits are made on the whole component `source`
+/// so maps generated from the result point into it.
+///
+/// Returns `None` if the component has no `<style>` tag.
+pub fn generate_css(source: &str, ast: &RustleAst, analysis: &AnalysisResult) -> Option<MagicString> {
+    let (style, scope) = match (&ast.style, &analysis.css) {
+        (Some(style), Some(scope)) => (style, scope),
+        _ => return None,
+    };
+
+    let start = style.start;
+    let end = start + style.content.len();
+    let position = |offset: usize| (start + offset) as i64;
+
+    let mut css = MagicString::new(source);
+    css.remove(0, start as i64).unwrap();
+    css.remove(end as i64, source.len() as i64).unwrap();
+
+    let class = format!(".{}", scope.class);
+    for selector in &scope.selectors {
+        let globals = match selector {
+            ScopedSelector::Local { end, globals, .. } => {
+                css.append_left(position(*end) as u32, &class).unwrap();
+                globals
+            }
+            ScopedSelector::Global(globals) => globals,
+        };
+        for global in globals {
+            let (inner_start, inner_end) = global.inner;
+            css.remove(position(global.start), position(inner_start)).unwrap();
+            css.remove(position(inner_end), position(global.end)).unwrap();
+        }
+    }
+    css.trim(None).unwrap();
+
+    Some(css)
+}
@@ -1,9 +1,32 @@
 use swc_common::sync::Lrc;
-use swc_common::SourceMap;
-use swc_ecma_ast::{EsVersion, Expr};
+use swc_common::{SourceMap, Span, DUMMY_SP};
+use swc_ecma_ast::{EsVersion, Expr, Pat};
 use swc_ecma_codegen::{text_writer::JsWriter, Config, Emitter, Node};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
 
 pub fn expr_to_string(expr: &Expr) -> String {
+    let mut expr = expr.clone();
+    expr.visit_mut_with(&mut DropSpan);
+    node_to_string(&expr)
+}
+
+pub fn pat_to_string(pat: &Pat) -> String {
+    let mut pat = pat.clone();
+    pat.visit_mut_with(&mut DropSpan);
+    node_to_string(&pat)
+}
+
+/// The emitter looks up the source of spanned lists, which
+/// are not part of its empty `SourceMap`, so spans are dropped
+struct DropSpan;
+
+impl VisitMut for DropSpan {
+    fn visit_mut_span(&mut self, span: &mut Span) {
+        *span = DUMMY_SP;
+    }
+}
+
+fn node_to_string<N: Node>(node: &N) -> String {
     let mut buffer = Vec::new();
     {
         let cm: Lrc<SourceMap> = Default::default();
@@ -20,9 +43,8 @@ pub fn expr_to_string(expr: &Expr) -> String {
             comments: None,
             wr: writer,
         };
-        Node::emit_with(&expr, &mut emmiter).unwrap();
+        node.emit_with(&mut emmiter).unwrap();
     }
 
-    let expr_literal = String::from_utf8(buffer).unwrap();
-    expr_literal
+    String::from_utf8(buffer).unwrap()
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::{
+    AssignExpr, AssignOp, AssignPatProp, Decl, ExportDecl, Expr, ExprStmt, Ident, Module,
+    ModuleDecl, ModuleItem, ObjectPat, ObjectPatProp, ParenExpr, Pat, PatOrExpr, Script, Stmt,
+    VarDecl, VarDeclKind, VarDeclarator,
+};
+
+use crate::compiler::analyse::extract_pattern_names;
+
+/// Splits the module of the `<script>` tag into its imports, hoisted
+/// out of the component function, and the statements of the instance.
+///
+/// `export let name = value` props are read from `$$props` as
+/// `let { name = value } = $$props;`, other exported declarations
+/// lose their `export`. The names of a destructured `export let`
+/// are declared first and then read from `$$props`, see
+/// `destructured_props`.
+pub fn instance_script(module: &Module) -> (Module, Script) {
+    let mut imports = Vec::new();
+    let mut body = Vec::new();
+    for item in &module.body {
+        match item {
+            ModuleItem::Stmt(stmt) => body.push(stmt.clone()),
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => {
+                imports.push(ModuleItem::ModuleDecl(ModuleDecl::Import(import.clone())))
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+                match decl {
+                    Decl::Var(var_decl) if var_decl.kind != VarDeclKind::Const => {
+                        body.push(Stmt::Decl(Decl::Var(Box::new(props_declaration(var_decl)))));
+                        body.extend(destructured_props(var_decl));
+                    }
+                    decl => body.push(Stmt::Decl(decl.clone())),
+                }
+            }
+            // `export { a as b }` and default exports are not supported
+            ModuleItem::ModuleDecl(_) => (),
+        }
+    }
+
+    let imports = Module {
+        span: module.span,
+        body: imports,
+        shebang: module.shebang.clone(),
+    };
+    let script = Script {
+        span: module.span,
+        body,
+        shebang: None,
+    };
+
+    (imports, script)
+}
+
+fn props_declaration(var_decl: &VarDecl) -> VarDecl {
+    let (props, others): (Vec<&VarDeclarator>, Vec<&VarDeclarator>) = var_decl
+        .decls
+        .iter()
+        .partition(|declarator| matches!(declarator.name, Pat::Ident(_)));
+
+    let props = props
+        .into_iter()
+        .map(|declarator| {
+            let key = match &declarator.name {
+                Pat::Ident(binding) => binding.id.clone(),
+                _ => unreachable!(),
+            };
+            ObjectPatProp::Assign(AssignPatProp {
+                span: declarator.span,
+                key,
+                value: declarator.init.clone(),
+            })
+        })
+        .collect::<Vec<ObjectPatProp>>();
+
+    let mut decls = Vec::new();
+    if !props.is_empty() {
+        decls.push(VarDeclarator {
+            span: DUMMY_SP,
+            name: Pat::Object(ObjectPat {
+                span: DUMMY_SP,
+                props,
+                optional: false,
+                type_ann: None,
+            }),
+            init: Some(Box::new(Expr::Ident(Ident::new("$$props".into(), DUMMY_SP)))),
+            definite: false,
+        });
+    }
+    decls.extend(others.into_iter().cloned());
+
+    VarDecl {
+        decls,
+        ..var_decl.clone()
+    }
+}
+
+/// `export let { a, b } = value` keeps its declaration and is followed
+/// by `({ a = a, b = b } = $$props);`, the values of `$$props` override
+/// the destructured ones like the defaults of `export let a = value`.
+fn destructured_props(var_decl: &VarDecl) -> Option<Stmt> {
+    let names = var_decl
+        .decls
+        .iter()
+        .filter(|declarator| !matches!(declarator.name, Pat::Ident(_)))
+        .flat_map(|declarator| extract_pattern_names(&declarator.name))
+        .collect::<Vec<String>>();
+    if names.is_empty() {
+        return None;
+    }
+
+    let props = names
+        .iter()
+        .map(|name| {
+            let ident = Ident::new(name.as_str().into(), DUMMY_SP);
+            ObjectPatProp::Assign(AssignPatProp {
+                span: DUMMY_SP,
+                key: ident.clone(),
+                value: Some(Box::new(Expr::Ident(ident))),
+            })
+        })
+        .collect();
+    let assign = Expr::Assign(AssignExpr {
+        span: DUMMY_SP,
+        op: AssignOp::Assign,
+        left: PatOrExpr::Pat(Box::new(Pat::Object(ObjectPat {
+            span: DUMMY_SP,
+            props,
+            optional: false,
+            type_ann: None,
+        }))),
+        right: Box::new(Expr::Ident(Ident::new("$$props".into(), DUMMY_SP))),
+    });
+
+    Some(Stmt::Expr(ExprStmt {
+        span: DUMMY_SP,
+        expr: Box::new(Expr::Paren(ParenExpr {
+            span: DUMMY_SP,
+            expr: Box::new(assign),
+        })),
+    }))
+}
@@ -0,0 +1,94 @@
+use swc_common::util::take::Take;
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::{CallExpr, Callee, Expr, ExprOrSpread, Ident, Lit, Module, Str};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+use crate::compiler::analyse::Assignment;
+
+/// Wraps the assignments of the script changing top-level variables
+/// in `$$invalidate('name', value)` calls, e.g. `count += 1` becomes
+/// `$$invalidate('count', count += 1)`, marking only them as changed.
+pub fn invalidate_assignments(script: &Module, assignments: &[Assignment]) -> Module {
+    let mut script = script.clone();
+    script.visit_mut_with(&mut Invalidator { assignments });
+
+    script
+}
+
+/// Wraps the assignments of a template expression, like
+/// the inline `on:` handlers, see `invalidate_assignments`
+pub fn invalidate_expr(expr: &Expr, assignments: &[Assignment]) -> Expr {
+    let mut expr = expr.clone();
+    expr.visit_mut_with(&mut Invalidator { assignments });
+
+    expr
+}
+
+struct Invalidator<'a> {
+    assignments: &'a [Assignment],
+}
+
+impl VisitMut for Invalidator<'_> {
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        expr.visit_mut_children_with(self);
+
+        let span = match expr {
+            Expr::Assign(ae) => ae.span,
+            Expr::Update(ue) => ue.span,
+            _ => return,
+        };
+        let assignment = match self.assignments.iter().find(|a| a.span == span) {
+            Some(assignment) => assignment,
+            None => return,
+        };
+
+        for name in assignment.names.iter().rev() {
+            let value = expr.take();
+            *expr = invalidate_call(name, value);
+        }
+    }
+}
+
+fn invalidate_call(name: &str, value: Expr) -> Expr {
+    let name = Expr::Lit(Lit::Str(Str {
+        span: DUMMY_SP,
+        value: name.into(),
+        raw: None,
+    }));
+
+    Expr::Call(CallExpr {
+        span: DUMMY_SP,
+        callee: Callee::Expr(Box::new(Expr::Ident(Ident::new(
+            "$$invalidate".into(),
+            DUMMY_SP,
+        )))),
+        args: vec![name, value]
+            .into_iter()
+            .map(|expr| ExprOrSpread {
+                spread: None,
+                expr: Box::new(expr),
+            })
+            .collect(),
+        type_args: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::compile::CompileOptions;
+    use crate::compiler::generate::generate;
+    use crate::compiler::parse::Parser;
+
+    #[test]
+    fn test_invalidate_assignments() {
+        let source = "<script>let a = 0, b = 1; const f = (c) => { a += 1; c++; [a, b] = [b, a]; };</script>";
+        let ast = Parser::new(source).parse().unwrap();
+        let analysis = analyse(&ast).unwrap();
+        let (code, _) = generate(source, &ast, &analysis, &CompileOptions::default());
+
+        assert!(code.contains("$$invalidate(\"a\", a += 1);"));
+        assert!(code.contains("c++;"));
+        assert!(code.contains("$$invalidate(\"a\", $$invalidate(\"b\", [a, b] = ["));
+    }
+}
@@ -1,229 +1,469 @@
-use super::{analyse::AnalysisResult, Fragment, RustleAst};
-use swc_common::{sync::Lrc, SourceMap};
-use swc_ecma_ast::{EsVersion, Expr, Lit};
+use super::{
+    analyse::{
+        binding_root, extract_dependencies, extract_pattern_names, static_value, AnalysisResult,
+    },
+    Fragment, RustleAction, RustleAst, RustleAttribute, RustleAwaitBlock, RustleConstTag,
+    RustleDebugTag, RustleEachBlock, RustleElement, RustleEventHandler, RustleIfBlock,
+    RustleKeyBlock, RustleRawHtml, RustleTransition,
+};
+use super::compile::CompileOptions;
+use swc_common::{sync::Lrc, FileName, SourceMap, Span, Spanned};
+use swc_ecma_ast::{EsVersion, Expr, Lit, Pat};
 use swc_ecma_codegen::{text_writer::JsWriter, Config, Emitter, Node};
 
+mod css;
 mod generate_helpers;
-use generate_helpers::expr_to_string;
+mod instance;
+mod invalidate;
+mod reactive;
+mod source_map;
+mod ssr;
+pub use css::generate_css;
+use generate_helpers::{expr_to_string, pat_to_string};
+use instance::instance_script;
+use invalidate::{invalidate_assignments, invalidate_expr};
+use reactive::{reactive_script, reactive_updates};
+use source_map::{extract_source_map, mark_entries, mark_script};
+pub use ssr::generate_ssr;
 
 struct Code {
     counter: usize,
+    /// The anchor node the root nodes are inserted before,
+    /// set when generating the content of a block
+    anchor: Option<String>,
+    /// The names scoped to the enclosing `{#each}` and `{#await}` blocks,
+    /// they change whenever the block is updated with a new value
+    contexts: Vec<String>,
+    /// How a `bind:` to one of the `contexts` is assigned, innermost last
+    bindings: Vec<ContextBinding>,
     variables: Vec<String>,
-    components: Vec<(String, String)>,
+    blocks: Vec<String>,
     create: Vec<String>,
+    /// Moves the root nodes of a block before its anchor
+    insert: Vec<String>,
     update: Vec<String>,
-    props_set: Vec<String>,
     destroy: Vec<String>,
+    /// Plays the intros once the block is added, `$$local` when the block
+    /// itself is added rather than one of the enclosing blocks
+    intro: Vec<String>,
+    /// Plays the outros before the block is removed, each one increments
+    /// `$$pending` and calls `$$finish` once done
+    outro: Vec<String>,
+    /// Extra methods of the block lifecycle, like `measure` and `animate`
+    methods: Vec<String>,
+    /// Generating for development, see `CompileOptions::dev`
+    dev: bool,
+    /// Claiming the server rendered nodes, see `CompileOptions::hydratable`
+    hydratable: bool,
 }
 
-pub fn generate(ast: RustleAst, analysis: AnalysisResult) -> String {
-    let mut code = Code {
-        counter: 1,
-        variables: Vec::new(),
-        components: Vec::new(),
-        create: Vec::new(),
-        update: Vec::new(),
-        props_set: Vec::new(),
-        destroy: Vec::new(),
-    };
+impl Code {
+    fn new(
+        counter: usize,
+        anchor: Option<String>,
+        contexts: Vec<String>,
+        dev: bool,
+        hydratable: bool,
+    ) -> Self {
+        Code {
+            counter,
+            anchor,
+            contexts,
+            dev,
+            hydratable,
+            bindings: Vec::new(),
+            variables: Vec::new(),
+            blocks: Vec::new(),
+            create: Vec::new(),
+            insert: Vec::new(),
+            update: Vec::new(),
+            destroy: Vec::new(),
+            intro: Vec::new(),
+            outro: Vec::new(),
+            methods: Vec::new(),
+        }
+    }
 
-    for fragment in ast.fragments {
-        traverse(&fragment, "target".into(), &analysis, &mut code)
+    /// Whether the block plays intros or outros, so it is
+    /// removed only once its outros are done
+    fn has_transitions(&self) -> bool {
+        !self.intro.is_empty() || !self.outro.is_empty()
     }
 
-    let mut buffer = Vec::new();
-    {
-        let cm: Lrc<SourceMap> = Default::default();
-        let writer = JsWriter::new(cm.clone(), "\n", &mut buffer, None);
-        let config = Config {
-            target: EsVersion::latest(),
-            ascii_only: false,
-            minify: false,
-            omit_last_semi: false,
-        };
-        let mut emmiter = Emitter {
-            cfg: config,
-            cm: cm.clone(),
-            comments: None,
-            wr: writer,
-        };
-        emmiter.emit_script(&ast.script).unwrap();
+    fn declarations(&self) -> String {
+        self.variables
+            .iter()
+            .map(|v| format!("let {};", v))
+            .collect::<Vec<String>>()
+            .join("\n")
     }
+}
 
-    let script = String::from_utf8(buffer).unwrap();
+/// Generates the component javascript along with a source map
+/// pointing its statements back into the component `source`.
+pub fn generate(
+    source: &str,
+    ast: &RustleAst,
+    analysis: &AnalysisResult,
+    options: &CompileOptions,
+) -> (String, magic_string::SourceMap) {
+    // the root nodes are inserted before the anchor passed to `create`
+    let mut code = Code::new(
+        1,
+        Some("anchor".into()),
+        Vec::new(),
+        options.dev,
+        options.hydratable,
+    );
+    // the names the parent component changed for the content it slotted
+    if !analysis.slots.is_empty() {
+        code.variables.push("$$scope = []".into());
+    }
 
-    format!(
+    for fragment in &ast.fragments {
+        traverse(fragment, "target".into(), analysis, &mut code)
+    }
+    if !analysis.slots.is_empty() {
+        code.update.push("$$scope = [];".into());
+    }
+    // the components a parent creates claim their nodes along with it
+    let mut internal = INTERNAL_IMPORT.to_owned();
+    if options.custom_element {
+        internal.push_str(CUSTOM_ELEMENT_IMPORT);
+    }
+    if options.hydratable {
+        internal.push_str(HYDRATION_IMPORT);
+        code.create.insert(
+            0,
+            format!(
+                "const $$hydrating = $$start_hydrating(target, $$props.$$nested, {});",
+                options.dev
+            ),
+        );
+        code.create.push("$$end_hydrating($$hydrating);".into());
+    }
+
+    let module = invalidate_assignments(&ast.script, &analysis.assignments);
+    let (imports, script) = instance_script(&module);
+    let imports = emit(&imports, source);
+    let script = emit(&reactive_script(&script, analysis), source);
+
+    let code = format!(
         r#"
 	{}
-	export default function() {{
+	{}
+	{}
+	{}function $$self($$props = {{}}) {{
+		const $$component = new $$Component();
+		const $$invalidate = (name, value) => $$component.$$invalidate(name, value);
+		const $$bubble = (type, event) => $$component.$$bubble(type, event);
+
+		{}
 		{}
 		{}
 		{}
 
-		const lifecycle = {{
-			create(target) {{
+		const lifecycle = Object.assign($$component, {{
+			create(target, anchor) {{
+				$$component.$$created = true;
+				{}
+			}},
+			insert(target, anchor) {{
 				{}
 			}},
 			update(changed) {{
 				{}
+				{}
+				{}
 			}},
-			$set(changedSet) {{
+			$set(props) {{
 				{}
 			}},
+			{}
 			destroy() {{
+				$$component.$$created = false;
 				{}
 			}},
-		}};
+		}});
 		return lifecycle;
 	}}
+	{}
 	"#,
+        internal,
         ast.import,
+        imports,
+        match options.custom_element {
+            true => "",
+            false => "export default ",
+        },
+        rest_props(analysis),
         script,
-        code.variables
-            .iter()
-            .map(|v| format!("let {};", v))
-            .collect::<Vec<String>>()
-            .join("\n"),
-        code.components
-            .iter()
-            .map(|(v, component)| format!("let {} = new {}({{}});", v, component))
-            .collect::<Vec<String>>()
-            .join("\n"),
+        code.declarations(),
+        code.blocks.join("\n"),
         code.create.join("\n"),
+        code.insert.join("\n"),
+        reactive_updates(analysis),
         code.update.join("\n"),
-        code.props_set.join("\n"),
-        code.destroy.join("\n")
+        props_bound(analysis),
+        props_set(analysis),
+        // a parent component or block plays the transitions of the component
+        transition_methods(&code),
+        code.destroy.join("\n"),
+        match options.custom_element {
+            true => custom_element(source, ast, analysis),
+            false => String::new(),
+        }
+    );
+
+    extract_source_map(&code, source)
+}
+
+/// Imports the helpers of the runtime, prefixed to
+/// keep clear of the names of the component
+const INTERNAL_IMPORT: &str = "import { Component as $$Component, element as $$element, text as $$text, \
+    comment as $$comment, attr as $$attr, set_attributes as $$set_attributes, listen as $$listen, \
+    append as $$append, insert as $$insert, detach as $$detach, safe_not_equal as $$safe_not_equal, \
+    transition as $$transition, animate as $$animate, unmoved_blocks as $$unmoved_blocks } from 'rustle/internal';";
+
+/// Imports the helpers claiming the nodes of the server rendered markup
+const HYDRATION_IMPORT: &str = "\nimport { claim_element as $$claim_element, claim_text as $$claim_text, \
+    claim_comment as $$claim_comment, start_hydrating as $$start_hydrating, \
+    end_hydrating as $$end_hydrating } from 'rustle/internal';";
+
+/// Imports the base of the custom elements
+const CUSTOM_ELEMENT_IMPORT: &str =
+    "\nimport { create_custom_element as $$create_custom_element } from 'rustle/internal';";
+
+/// Exports the component wrapped in a custom element class, with its
+/// props as attributes and properties and its css in the shadow root.
+/// The class is registered as the tag of `<svelte:options customElement>`.
+fn custom_element(source: &str, ast: &RustleAst, analysis: &AnalysisResult) -> String {
+    let css = generate_css(source, ast, analysis).map(|css| css.to_string());
+    let define = ast
+        .options
+        .as_ref()
+        .and_then(|options| options.custom_element.as_ref())
+        .map(|name| format!("customElements.define({}, $$Element);", string_literal(name)))
+        .unwrap_or_default();
+
+    format!(
+        r#"
+	const $$Element = $$create_custom_element($$self, [{}], {});
+	{}
+	export default $$Element;
+	"#,
+        analysis
+            .props
+            .iter()
+            .map(|name| string_literal(name))
+            .collect::<Vec<String>>()
+            .join(", "),
+        css.as_deref().map_or_else(|| "null".into(), string_literal),
+        define
     )
 }
 
-fn traverse(node: &Fragment, parent: String, analysis: &AnalysisResult, code: &mut Code) {
-    match node {
-        Fragment::Import(_) => (),
-        Fragment::Script(_) => (),
-        Fragment::Element(f) => {
-            let variable_name = format!("{}_{}", f.name, code.counter);
-            code.counter += 1;
+/// The `intro($$local)` and `outro($$local, $$done)` methods of the
+/// lifecycle of `code`, along with its extra methods.
+///
+/// The parameters are prefixed like the runtime helpers
+/// so they don't shadow the variables of the component.
+fn transition_methods(code: &Code) -> String {
+    format!(
+        r#"
+			intro($$local) {{
+				{}
+			}},
+			outro($$local, $$done) {{
+				let $$pending = 1;
+				const $$finish = () => {{
+					if (--$$pending === 0) $$done();
+				}};
+				{}
+				$$finish();
+			}},
+			{}
+		"#,
+        code.intro.join("\n"),
+        code.outro.join("\n"),
+        code.methods.join("\n")
+    )
+}
 
-            match f.is_component {
-                false => {
-                    code.variables.push(variable_name.clone());
-                    code.create.push(format!(
-                        "{} = document.createElement('{}');",
-                        variable_name, f.name
-                    ));
-                },
-                true => {
-                    code.components.push((variable_name.clone(), f.name.clone()));
-                    code.create.push(format!(
-                        // "create_component({}.$$.fragment);",
-                        "{}.create(target);",
-                        variable_name.clone()
-                    ));
-                }
-            }
+/// Destroys the `block`, once its outros are done for a block with transitions
+fn remove_block(block: &str, transitions: bool) -> String {
+    match transitions {
+        true => format!(
+            "{{ const outro_block = {}; outro_block.outro(true, () => outro_block.destroy()); }}",
+            block
+        ),
+        false => format!("{}.destroy();", block),
+    }
+}
 
-            for attr in &f.attributes {
-                if attr.name.starts_with("on:") {
-                    let event_name = attr.name.chars().skip(3).collect::<String>();
-                    let event_handler = match &attr.value {
-                        Expr::Ident(ident) => ident.sym.to_string(),
-                        _ => panic!(),
-                    };
+/// Removes the `block` of an `{#each}` item, the parameter of a callback
+fn remove_item(transitions: bool) -> &'static str {
+    match transitions {
+        true => "block.outro(true, () => block.destroy())",
+        false => "block.destroy()",
+    }
+}
 
-                    let event_identifier = format!("{}_{}", variable_name, event_name);
-                    code.variables.push(event_identifier.clone());
+/// Plays the intros of the `block` just added by an update
+fn intro_block(block: &str, transitions: bool) -> String {
+    match transitions {
+        true => format!("if ({block}) {block}.intro(true);", block = block),
+        false => String::new(),
+    }
+}
 
-                    code.create.push(format!(
-                        "{}.addEventListener('{}', {} = () => {{ {}(); lifecycle.update({:?} /* update all analysis changes first */) }});",
-                        variable_name, event_name, event_identifier, event_handler, analysis.will_change.clone().into_iter().collect::<Vec<String>>()
-                    ));
+/// Plays the intros of a nested block of `code` once the
+/// enclosing block is added, and its outros before it is removed
+fn nest_transitions(block: &str, code: &mut Code) {
+    code.intro
+        .push(format!("if ({block}) {block}.intro(false);", block = block));
+    code.outro.push(format!(
+        "if ({block}) {{ $$pending++; {block}.outro(false, $$finish); }}",
+        block = block
+    ));
+}
 
-                    code.destroy.push(format!(
-                        "{}.removeEventListener('{}', {});",
-                        variable_name, event_name, event_identifier
-                    ));
-                } else {
-                    let mut value = match &attr.value {
-                        Expr::Ident(ident) => ident.sym.to_string(),
-                        Expr::Lit(Lit::Str(str)) => format!("\"{}\"", str.value),
-                        expr => expr_to_string(expr),
-                        _ => {
-                            todo!()
-                        },
-                    };
-                    {
-                        // replace `{xx} xx` => `${xx} xx`
-                        let mut index = 0usize;
-                        while index < value.len() {
-                            if value.chars().nth(index).unwrap() == '{' {
-                                let mut next_pointer = index + 1;
-                                while next_pointer < value.len() {
-                                    if value.chars().nth(next_pointer).unwrap() == '}' {
-                                        // match
-                                        value.insert(index, '$');
-                                        next_pointer += 1;
-                                        index += 1;
-                                    }
-                                    next_pointer += 1;
-                                }
-                            }
-                            index += 1;
-                        }
-                    }
-                    code.create.push(format!(
-                        "{}.{} = {};",
-                        variable_name, attr.name, value
-                    ));
+/// Emits a node of the script, marked with the
+/// positions of its tokens in the component `source`
+fn emit<N: Node>(node: &N, source: &str) -> String {
+    let mut buffer = Vec::new();
+    let mut positions = Vec::new();
+    {
+        // the script spans are positions in the whole component source
+        let cm: Lrc<SourceMap> = Default::default();
+        cm.new_source_file(FileName::Anon, source.to_owned());
+        let writer = JsWriter::new(cm.clone(), "\n", &mut buffer, Some(&mut positions));
+        let config = Config {
+            target: EsVersion::latest(),
+            ascii_only: false,
+            minify: false,
+            omit_last_semi: false,
+        };
+        let mut emmiter = Emitter {
+            cfg: config,
+            cm: cm.clone(),
+            comments: None,
+            wr: writer,
+        };
+        node.emit_with(&mut emmiter).unwrap();
+    }
 
-                    if analysis.will_change.contains(&value) {
-                        match f.is_component {
-                            true => {
-                                code.update.push(format!(
-                                    r#"
-                                    const {}_changes = {{}};
-                                    if (changed.includes('{}')) {{
-                                        {}_changes['{}'] = {};
-                                    }}
-                                    {}.$set({}_changes);
-                                "#,
-                                    variable_name, value, variable_name, attr.name, value, variable_name, variable_name
-                                ));
-                            },
-                            _ => {
-                                code.update.push(format!(
-                                    r#"
-                                    if (changed.includes('{}')) {{
-                                        {}.{} = {};
-                                    }}
-                                "#,
-                                    value, variable_name, attr.name, value
-                                ));
-                            }
-                        }
-                    }
-                }
-            }
+    mark_script(&String::from_utf8(buffer).unwrap(), &positions)
+}
 
-            for fragment in &f.fragments {
-                traverse(fragment, variable_name.clone(), analysis, code);
-            }
+/// Assigns the props passed to `$set` that changed,
+/// invalidating them for the next update
+fn props_set(analysis: &AnalysisResult) -> String {
+    let mut set = analysis
+        .props
+        .iter()
+        .map(|name| {
+            format!(
+                "if ('{name}' in props && $$safe_not_equal({name}, props.{name})) $$invalidate('{name}', {name} = props.{name});",
+                name = name
+            )
+        })
+        .collect::<Vec<String>>();
+    if analysis.rest_props {
+        set.push("{ const rest = $$rest_props(props); if (Object.keys(rest).length) $$invalidate('$$restProps', $$restProps = Object.assign({}, $$restProps, rest)); }".into());
+    }
+    if !analysis.slots.is_empty() {
+        set.push("if ('$$scope' in props) $$invalidate('$$scope', $$scope = $$scope.concat(props.$$scope));".into());
+    }
 
-            match f.is_component {
-                false => {
-                    code.create
-                    .push(format!("{}.appendChild({});", parent, variable_name));
-                    code.destroy
-                    .push(format!("{}.removeChild({});", parent, variable_name));
-                },
-                true => {
-                    code.create
-                    .push(format!("// mount_component({});", variable_name));
-                    code.destroy
-                    .push(format!("{}.destroy(target);", variable_name));
+    set.join("\n")
+}
 
-                }
+/// Declares `$$restProps`, the props of the parent
+/// that are not declared with `export let`
+fn rest_props(analysis: &AnalysisResult) -> String {
+    if !analysis.rest_props {
+        return String::new();
+    }
+
+    format!(
+        r#"
+		function $$rest_props(props) {{
+			const declared = {:?};
+			return Object.fromEntries(Object.entries(props).filter(([key]) => !declared.includes(key) && !key.startsWith('$$')));
+		}}
+		let $$restProps = $$rest_props($$props);
+		"#,
+        analysis.props
+    )
+}
+
+/// Passes the props that changed back to the
+/// parent components binding them with `bind:`
+fn props_bound(analysis: &AnalysisResult) -> String {
+    analysis
+        .props
+        .iter()
+        .map(|name| {
+            format!(
+                "if (changed.includes('{name}') && $$props.$$bound && $$props.$$bound.{name}) $$props.$$bound.{name}({name});",
+                name = name
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn traverse(node: &Fragment, parent: String, analysis: &AnalysisResult, code: &mut Code) {
+    let (create, update) = (code.create.len(), code.update.len());
+    generate_fragment(node, parent, analysis, code);
+
+    // statements of nested fragments are marked already
+    let span = fragment_span(node);
+    mark_entries(&mut code.create, create, span);
+    mark_entries(&mut code.update, update, span);
+}
+
+fn fragment_span(node: &Fragment) -> Span {
+    match node {
+        Fragment::Element(f) => f.span,
+        Fragment::IfBlock(f) => f.span,
+        Fragment::EachBlock(f) => f.span,
+        Fragment::AwaitBlock(f) => f.span,
+        Fragment::KeyBlock(f) => f.span,
+        Fragment::RawHtml(f) => f.span,
+        Fragment::DebugTag(f) => f.span,
+        Fragment::Expression(f) => f.span(),
+        Fragment::Text(f) => f.span,
+        _ => Span::default(),
+    }
+}
+
+fn generate_fragment(node: &Fragment, parent: String, analysis: &AnalysisResult, code: &mut Code) {
+    match node {
+        Fragment::Import(_) => (),
+        Fragment::Script(_) => (),
+        Fragment::Style(_) => (),
+        Fragment::Element(f) if f.name == "svelte:head" => {
+            // the content is mounted into the head of the document
+            for fragment in &f.fragments {
+                traverse(fragment, "document.head".into(), analysis, code);
             }
         }
+        Fragment::Element(f) if f.name == "svelte:window" || f.name == "svelte:body" => {
+            generate_global(f, analysis, code)
+        }
+        Fragment::Element(f) if f.this.is_some() => generate_dynamic(f, parent, analysis, code),
+        Fragment::Element(f) if f.name == "svelte:self" => {
+            generate_component(f, "$$self", parent, analysis, code)
+        }
+        Fragment::Element(f) if f.is_component => {
+            generate_component(f, &f.name, parent, analysis, code)
+        }
+        Fragment::Element(f) if f.name == "slot" => generate_slot(f, parent, analysis, code),
+        Fragment::Element(f) => {
+            generate_element(f, &format!("'{}'", f.name), parent, analysis, code)
+        }
         Fragment::Expression(f) => {
             let variable_name = format!("txt_{}", code.counter);
             code.counter += 1;
@@ -231,70 +471,2138 @@ fn traverse(node: &Fragment, parent: String, analysis: &AnalysisResult, code: &m
             let expression_name = match f {
                 Expr::Ident(ident) => ident.sym.to_string(),
                 expr => expr_to_string(expr),
-                _ => panic!(),
             };
 
             code.variables.push(variable_name.clone());
-            code.create.push(format!(
-                "{} = document.createTextNode({});",
-                variable_name, expression_name
-            ));
+            let text = create_node(&parent, "text", &expression_name, code);
+            code.create.push(format!("{} = {};", variable_name, text));
 
-            code.create
-                .push(format!("{}.appendChild({});", parent, variable_name));
+            mount(&parent, &variable_name, code);
+            if is_block_root(&parent, code) {
+                unmount(&variable_name, code);
+            }
 
-            if analysis.will_change.contains(&expression_name) {
+            let dependencies = changing_dependencies(f, analysis, code);
+            if !dependencies.is_empty() {
                 code.update.push(format!(
                     r#"
-					if (changed.includes('{}')) {{
+					if ({}) {{
 						{}.data = {};
 					}}
 				"#,
-                    expression_name, variable_name, expression_name
+                    changed_condition(&dependencies), variable_name, expression_name
                 ));
-                code.props_set.push(format!(
+            }
+        }
+        Fragment::Text(f) => {
+            let variable_name = format!("txt_{}", code.counter);
+            code.counter += 1;
+
+            code.variables.push(variable_name.clone());
+            let text = create_node(&parent, "text", &string_literal(&f.data), code);
+            code.create.push(format!("{} = {};", variable_name, text));
+            mount(&parent, &variable_name, code);
+            if is_block_root(&parent, code) {
+                unmount(&variable_name, code);
+            }
+        }
+        Fragment::IfBlock(f) => generate_if_block(f, parent, analysis, code),
+        Fragment::EachBlock(f) => generate_each_block(f, parent, analysis, code),
+        Fragment::AwaitBlock(f) => generate_await_block(f, parent, analysis, code),
+        Fragment::KeyBlock(f) => generate_key_block(f, parent, analysis, code),
+        Fragment::RawHtml(f) => generate_raw_html(f, parent, analysis, code),
+        Fragment::DebugTag(f) => generate_debug_tag(f, analysis, code),
+        // declared by the enclosing block
+        Fragment::ConstTag(_) => (),
+    }
+}
+
+/// Creates the element with its attributes, handlers, children and
+/// bindings, `tag` is the javascript of its tag name.
+fn generate_element(
+    f: &RustleElement,
+    tag: &str,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let variable_name = format!("{}_{}", variable_prefix(&f.name), code.counter);
+    code.counter += 1;
+
+    code.variables.push(variable_name.clone());
+    let element = create_node(&parent, "element", tag, code);
+    code.create.push(format!("{} = {};", variable_name, element));
+
+    // elements matched by the `<style>` selectors get the scope class
+    let scope_class = analysis
+        .css
+        .as_ref()
+        .filter(|scope| scope.applies_to(f))
+        .map(|scope| scope.class.clone());
+    if let Some(class) = &scope_class {
+        if !f.attributes.iter().any(|attr| attr.name == "class") {
+            code.create.push(format!("{}.className = '{}';", variable_name, class));
+        }
+    }
+
+    let spread = f.attributes.iter().any(|attr| attr.name == "...");
+    for attr in &f.attributes {
+        if attr.name.starts_with("bind:") {
+            // bound after the children, `<select>` needs its options
+            continue;
+        } else if attr.name.starts_with("class:") || attr.name.starts_with("style:") {
+            // applied after the `class` and `style` attributes
+            continue;
+        } else if attr.name.starts_with("let:") {
+            // declared by the slot content of the component
+            continue;
+        } else if spread {
+            // set along with the spreads
+            continue;
+        } else {
+            let value = attribute_value(&attr.value);
+            let property = match attr.name.as_str() {
+                "class" => "className",
+                name => name,
+            };
+            let property_value = match (&scope_class, attr.name.as_str()) {
+                (Some(class), "class") => format!("({}) + ' {}'", value, class),
+                _ => value.clone(),
+            };
+            // names like `aria-label` are not properties of the element
+            let set = match property.contains(|c: char| !c.is_ascii_alphanumeric() && c != '_') {
+                true => format!("$$attr({}, '{}', {});", variable_name, property, property_value),
+                false => format!("{}.{} = {};", variable_name, property, property_value),
+            };
+            code.create.push(set.clone());
+
+            if analysis.will_change.contains(&value) {
+                code.update.push(format!(
                     r#"
-					if ('{}' in changedSet) {{
-						{}.data = changedSet['{}'];
-					}}
-				"#,
-                    expression_name, variable_name, expression_name
+                    if (changed.includes('{}')) {{
+                        {}
+                    }}
+                "#,
+                    value, set
                 ));
             } else {
-                for change in analysis.will_change.iter() {
-                    if expression_name.contains(change) {
-                        code.update.push(format!(
-                            r#"
-                            if (changed.includes('{}')) {{
-                                {}.data = {};
-                            }}
-                        "#,
-                            change, variable_name, expression_name
-                        ));
-                        code.props_set.push(format!(
-                            r#"
-                            if ('{}' in changedSet) {{
-                                {}.data = changedSet['{}'];
-                            }}
-                        "#,
-                            expression_name, variable_name, expression_name
-                        ));
+                let dependencies = changing_dependencies(&attr.value, analysis, code);
+                if !dependencies.is_empty() {
+                    code.update.push(format!(
+                        r#"
+                        if ({}) {{
+                            {}
+                        }}
+                    "#,
+                        changed_condition(&dependencies), set
+                    ));
+                }
+            }
+        }
+    }
+
+    if spread {
+        generate_spread(f, &variable_name, scope_class.as_deref(), analysis, code);
+    }
+
+    let directives = f
+        .attributes
+        .iter()
+        .filter(|attr| attr.name.starts_with("class:") || attr.name.starts_with("style:"));
+    for attr in directives {
+        generate_directive(f, &variable_name, attr, analysis, code);
+    }
+
+    for handler in &f.handlers {
+        generate_event_handler(&variable_name, handler, analysis, code);
+    }
+
+    for fragment in &f.fragments {
+        traverse(fragment, variable_name.clone(), analysis, code);
+    }
+
+    for attr in f.attributes.iter().filter(|attr| attr.name.starts_with("bind:")) {
+        generate_binding(f, &variable_name, attr, code);
+    }
+
+    mount(&parent, &variable_name, code);
+    for action in &f.actions {
+        generate_action(&variable_name, action, analysis, code);
+    }
+    if !f.transitions.is_empty() {
+        generate_transitions(&variable_name, &f.transitions, code);
+    }
+    if let Some(animation) = &f.animation {
+        generate_animation(&variable_name, animation, code);
+    }
+    unmount(&variable_name, code);
+}
+
+/// Plays the intro and outro of the element, a local transition
+/// only when the block of the element itself is added or removed.
+fn generate_transitions(variable_name: &str, transitions: &[RustleTransition], code: &mut Code) {
+    let transition_name = format!("{}_transition", variable_name);
+    code.variables.push(transition_name.clone());
+
+    for transition in transitions {
+        let parameters = transition
+            .expression
+            .as_ref()
+            .map(expr_to_string)
+            .unwrap_or_else(|| "{}".into());
+        let wrap = |statement: String| match transition.local {
+            true => format!("if ($$local) {{ {} }}", statement),
+            false => format!("{{ {} }}", statement),
+        };
+
+        if transition.intro {
+            code.intro.push(wrap(format!(
+                "{t} = $$transition({node}, {name}, {params}, true, {t});",
+                t = transition_name,
+                node = variable_name,
+                name = transition.name,
+                params = parameters
+            )));
+        }
+        if transition.outro {
+            code.outro.push(wrap(format!(
+                "$$pending++; {t} = $$transition({node}, {name}, {params}, false, {t}, $$finish);",
+                t = transition_name,
+                node = variable_name,
+                name = transition.name,
+                params = parameters
+            )));
+        }
+    }
+
+    code.destroy.push(format!(
+        "if ({t}) {t}.abort();",
+        t = transition_name
+    ));
+}
+
+/// Measures the element before its keyed `{#each}` block reorders
+/// the items and animates it from there once moved.
+fn generate_animation(variable_name: &str, animation: &RustleAction, code: &mut Code) {
+    let animation_name = format!("{}_animation", variable_name);
+    code.variables.push(animation_name.clone());
+
+    let parameters = animation
+        .expression
+        .as_ref()
+        .map(expr_to_string)
+        .unwrap_or_else(|| "{}".into());
+    code.methods.push(format!(
+        r#"
+				measure() {{
+					return {node}.getBoundingClientRect();
+				}},
+				animate(from) {{
+					{a} = $$animate({node}, from, {name}, {params}, {a});
+				}},
+		"#,
+        node = variable_name,
+        a = animation_name,
+        name = animation.name,
+        params = parameters
+    ));
+    code.destroy.push(format!(
+        "if ({a}) {a}.abort();",
+        a = animation_name
+    ));
+}
+
+/// Generates `<svelte:window>` and `<svelte:body>`, listening to the
+/// events of the window or the body and binding the window properties.
+fn generate_global(f: &RustleElement, analysis: &AnalysisResult, code: &mut Code) {
+    let (prefix, node) = match f.name.as_str() {
+        "svelte:window" => ("window", "window"),
+        _ => ("body", "document.body"),
+    };
+    let variable_name = format!("{}_{}", prefix, code.counter);
+    code.counter += 1;
+
+    code.variables.push(variable_name.clone());
+    code.create.push(format!("{} = {};", variable_name, node));
+
+    for handler in &f.handlers {
+        generate_event_handler(&variable_name, handler, analysis, code);
+    }
+    for attr in f.attributes.iter().filter(|attr| attr.name.starts_with("bind:")) {
+        generate_window_binding(&variable_name, attr, code);
+    }
+    for action in &f.actions {
+        generate_action(&variable_name, action, analysis, code);
+    }
+}
+
+/// Binds a property of the window to a top-level variable, assigned
+/// from the window events. The scroll position is also scrolled to
+/// when the variable changes.
+fn generate_window_binding(variable_name: &str, attr: &RustleAttribute, code: &mut Code) {
+    let binding = attr.name.trim_start_matches("bind:");
+    let value = expr_to_string(&attr.value);
+    let root = binding_root(&attr.value).unwrap();
+
+    let (events, get) = match binding {
+        "scrollX" | "scrollY" => (vec!["scroll"], format!("{}.{}", variable_name, binding)),
+        "online" => (vec!["online", "offline"], "navigator.onLine".to_owned()),
+        _ => (vec!["resize"], format!("{}.{}", variable_name, binding)),
+    };
+
+    let listener = format!("{}_{}_binding", variable_name, binding);
+    code.variables.push(listener.clone());
+    code.create.push(format!(
+        "{} = () => $$invalidate('{}', {} = {});",
+        listener, root, value, get
+    ));
+    code.create.push(format!("{}();", listener));
+    for event in &events {
+        let remove = format!("{}_{}", listener, event);
+        code.variables.push(remove.clone());
+        code.create.push(format!(
+            "{} = $$listen({}, '{}', {});",
+            remove, variable_name, event, listener
+        ));
+        code.destroy.push(format!("{}();", remove));
+    }
+
+    let scroll_to = match binding {
+        "scrollX" => format!("{}.scrollTo({}, {}.scrollY);", variable_name, value, variable_name),
+        "scrollY" => format!("{}.scrollTo({}.scrollX, {});", variable_name, variable_name, value),
+        _ => return,
+    };
+    code.update.push(format!(
+        r#"
+		if (changed.includes('{}') && {} !== {}) {{
+			{}
+		}}
+	"#,
+        root, value, get, scroll_to
+    ));
+}
+
+/// Creates a child component with its attributes as initial props,
+/// and passes the props whose dependencies changed to its `$set`.
+fn generate_component(
+    f: &RustleElement,
+    constructor: &str,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let variable_name = format!("{}_{}", variable_prefix(&f.name), code.counter);
+    code.counter += 1;
+
+    // `bind:name={value}` passes `value` as the `name` prop
+    let props = f
+        .attributes
+        .iter()
+        .filter_map(|attr| match attr.name.strip_prefix("bind:") {
+            Some(name) => Some((name, attr)),
+            None if !attr.name.contains(':') => Some((attr.name.as_str(), attr)),
+            None => None,
+        })
+        .map(|(name, attr)| (name, attr, attribute_value(&attr.value)))
+        .collect::<Vec<_>>();
+
+    // and gets the values the child assigns to it back through `$$bound`
+    let bound = f
+        .attributes
+        .iter()
+        .filter_map(|attr| Some((attr.name.strip_prefix("bind:")?, attr)))
+        .map(|(name, attr)| {
+            format!(
+                "{:?}: $$value => {{ if ({} !== $$value) {}; }}",
+                name,
+                expr_to_string(&attr.value),
+                binding_assignment(&attr.value, "$$value", code)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    let slots = generate_slotted_content(f, &variable_name, analysis, code);
+
+    // `{...spread}` props are spread in order, later props take precedence
+    let spread = props.iter().any(|(name, _, _)| *name == "...");
+    let levels = props
+        .iter()
+        .map(|(name, _, value)| match *name {
+            "..." => format!("...{}", value),
+            name => format!("{:?}: {}", name, value),
+        })
+        .collect::<Vec<String>>();
+    let props_name = format!("{}_props", variable_name);
+
+    let mut initial_props = match spread {
+        true => vec![format!("...{}", props_name)],
+        false => levels.clone(),
+    };
+    if !bound.is_empty() {
+        initial_props.push(format!("$$bound: {{ {} }}", bound.join(", ")));
+    }
+    if !slots.is_empty() {
+        initial_props.push(format!("$$slots: {{ {} }}", slots.join(", ")));
+    }
+    // only the component created by the app starts the hydration
+    if code.hydratable {
+        initial_props.push("$$nested: true".into());
+    }
+
+    code.variables.push(variable_name.clone());
+    if spread {
+        code.variables.push(props_name.clone());
+        code.create.push(format!("{} = {{ {} }};", props_name, levels.join(", ")));
+    }
+    code.create.push(format!(
+        "{} = new {}({{ {} }});",
+        variable_name,
+        constructor,
+        initial_props.join(", ")
+    ));
+    for handler in &f.handlers {
+        let listener = listener_name(&variable_name, handler, code);
+        let mut body = vec![handler_call(handler, analysis)];
+        if handler.modifiers.iter().any(|m| m == "once") {
+            body.insert(0, format!("{}();", listener));
+        }
+        code.create.push(format!(
+            "{} = {}.$on('{}', function (event) {{ {} }});",
+            listener,
+            variable_name,
+            handler.name,
+            body.join(" ")
+        ));
+        code.destroy.push(format!("{}();", listener));
+    }
+    // the child removes its own root nodes
+    if is_block_root(&parent, code) {
+        code.create.push(format!("{}.create(target, anchor);", variable_name));
+        code.insert.push(format!("{}.insert(target, anchor);", variable_name));
+    } else {
+        code.create.push(format!("{}.create({});", variable_name, parent));
+    }
+    code.destroy.push(format!("{}.destroy();", variable_name));
+    code.intro.push(format!("{}.intro(false);", variable_name));
+    code.outro
+        .push(format!("$$pending++; {}.outro(false, $$finish);", variable_name));
+
+    let mut changes = match spread {
+        false => props
+            .iter()
+            .filter_map(|(name, attr, value)| {
+                let dependencies = changing_dependencies(&attr.value, analysis, code);
+                (!dependencies.is_empty()).then(|| {
+                    format!(
+                        "if ({}) changes[{:?}] = {};",
+                        changed_condition(&dependencies),
+                        name,
+                        value
+                    )
+                })
+            })
+            .collect::<Vec<String>>(),
+        // all the props are set again, the ones no longer spread become undefined
+        true => {
+            let mut dependencies: Vec<String> = Vec::new();
+            for (_, attr, _) in &props {
+                for dependency in changing_dependencies(&attr.value, analysis, code) {
+                    if !dependencies.contains(&dependency) {
+                        dependencies.push(dependency);
                     }
                 }
             }
+            match dependencies.is_empty() {
+                true => Vec::new(),
+                false => vec![format!(
+                    r#"
+				if ({changed}) {{
+					const props = {{ {levels} }};
+					for (const key in {previous}) if (!(key in props)) changes[key] = undefined;
+					Object.assign(changes, props);
+					{previous} = props;
+				}}
+				"#,
+                    changed = changed_condition(&dependencies),
+                    levels = levels.join(", "),
+                    previous = props_name
+                )],
+            }
         }
-        Fragment::Text(f) => {
-            let variable_name = format!("txt_{}", code.counter);
-            code.counter += 1;
+    };
+    // the slotted content is updated by the child with the names changed here
+    if !slots.is_empty() {
+        changes.push("changes.$$scope = changed;".into());
+    }
+    if !changes.is_empty() {
+        code.update.push(format!(
+            r#"
+			{{
+				const changes = {{}};
+				{}
+				{}.$set(changes);
+			}}
+		"#,
+            changes.join("\n"),
+            variable_name
+        ));
+    }
+}
 
-            code.variables.push(variable_name.clone());
-            code.create.push(format!(
-                "{} = document.createTextNode(`{}`);",
-                variable_name.clone(),
-                f.data.to_string()
-            ));
-            code.create
-                .push(format!("{}.appendChild({});", parent, variable_name));
+/// Sets the attributes of an element with `{...spread}` attributes
+/// at once, later attributes taking precedence, and again whenever
+/// one of them changes, removing the attributes no longer spread.
+fn generate_spread(
+    f: &RustleElement,
+    variable_name: &str,
+    scope_class: Option<&str>,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let attributes = f
+        .attributes
+        .iter()
+        .filter(|attr| attr.name == "..." || !attr.name.contains(':'))
+        .collect::<Vec<&RustleAttribute>>();
+
+    let mut dependencies: Vec<String> = Vec::new();
+    for attr in &attributes {
+        for dependency in changing_dependencies(&attr.value, analysis, code) {
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
         }
     }
+    let levels = attributes
+        .iter()
+        .map(|attr| match attr.name.as_str() {
+            "..." => format!("...{}", attribute_value(&attr.value)),
+            name => format!("{:?}: {}", name, attribute_value(&attr.value)),
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let attributes_name = format!("{}_attributes", variable_name);
+    code.variables.push(format!("{} = {{}}", attributes_name));
+
+    let mut set = format!(
+        "{attributes} = $$set_attributes({el}, {attributes}, {{ {levels} }});",
+        attributes = attributes_name,
+        el = variable_name,
+        levels = levels
+    );
+    if let Some(class) = scope_class {
+        set.push_str(&format!("\n{}.classList.add('{}');", variable_name, class));
+    }
+    code.create.push(set.clone());
+
+    if !dependencies.is_empty() {
+        code.update.push(format!(
+            r#"
+			if ({}) {{
+				{}
+			}}
+		"#,
+            changed_condition(&dependencies),
+            set
+        ));
+    }
+}
+
+/// Toggles a class for `class:name={condition}` or sets a style
+/// property for `style:property={value}`, again whenever the value or
+/// the `class` and `style` attribute or spreads overwriting them change.
+fn generate_directive(
+    f: &RustleElement,
+    variable_name: &str,
+    attr: &RustleAttribute,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let (directive, name) = attr.name.split_once(':').unwrap();
+    let mut modifiers = name.split('|');
+    let name = modifiers.next().unwrap();
+    let value = attribute_value(&attr.value);
+
+    let set = match directive {
+        "class" => format!("{}.classList.toggle('{}', !!({}));", variable_name, name, value),
+        _ => {
+            let priority = match modifiers.any(|modifier| modifier == "important") {
+                true => ", 'important'",
+                false => "",
+            };
+            format!(
+                "{}.style.setProperty('{}', {}{});",
+                variable_name, name, value, priority
+            )
+        }
+    };
+    code.create.push(set.clone());
+
+    let mut dependencies = changing_dependencies(&attr.value, analysis, code);
+    let overwriting = f
+        .attributes
+        .iter()
+        .filter(|attribute| attribute.name == directive || attribute.name == "...");
+    for attribute in overwriting {
+        for dependency in changing_dependencies(&attribute.value, analysis, code) {
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+    }
+    if !dependencies.is_empty() {
+        code.update.push(format!(
+            r#"
+			if ({}) {{
+				{}
+			}}
+		"#,
+            changed_condition(&dependencies),
+            set
+        ));
+    }
+}
+
+/// Generates a block function for each slot the children of the
+/// component fill, returns the `name: create_block` entries of `$$slots`.
+///
+/// Children with a `slot="name"` attribute fill the named slot, the
+/// other ones the default slot. The `let:name={alias}` directives of the
+/// component, or of the named slot child, read the slot props.
+fn generate_slotted_content(
+    f: &RustleElement,
+    variable_name: &str,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) -> Vec<String> {
+    let slot_name = |fragment: &Fragment| match fragment {
+        Fragment::Element(element) => element
+            .attributes
+            .iter()
+            .find(|attr| attr.name == "slot")
+            .and_then(|attr| static_value(&attr.value)),
+        _ => None,
+    };
+
+    let mut slots: Vec<(String, &[RustleAttribute], Vec<&Fragment>)> = Vec::new();
+    let default = f.fragments.iter().filter(|fragment| slot_name(fragment).is_none());
+    slots.push(("default".into(), &f.attributes, default.collect()));
+    for fragment in &f.fragments {
+        if let (Some(name), Fragment::Element(element)) = (slot_name(fragment), fragment) {
+            slots.push((name, &element.attributes, vec![fragment]));
+        }
+    }
+
+    slots
+        .into_iter()
+        .filter(|(_, _, fragments)| !fragments.is_empty())
+        .map(|(name, attributes, fragments)| {
+            let lets = attributes
+                .iter()
+                .filter_map(|attr| Some((attr.name.strip_prefix("let:")?, &attr.value)))
+                .collect::<Vec<_>>();
+            let context = (!lets.is_empty()).then(|| BlockContext {
+                names: lets.iter().flat_map(|(_, value)| extract_dependencies(value)).collect(),
+                assign: format!(
+                    "({{ {} }} = context_value);",
+                    lets.iter()
+                        .map(|(key, value)| format!("{}: {}", key, expr_to_string(value)))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+                bindings: Vec::new(),
+                first: None,
+            });
+
+            let block_name = format!("create_{}_slot_{}", variable_name, name.replace('-', "_"));
+            generate_block(&block_name, fragments, context, analysis, code);
+            format!("{:?}: {}", name, block_name)
+        })
+        .collect()
+}
+
+/// Generates a `<slot>`, rendering the content the parent component
+/// passed in `$$slots` with the other attributes as slot props, or the
+/// fallback content.
+///
+/// The slotted content is updated with the names the parent changed,
+/// kept in `$$scope`, and with the slot props when they change.
+fn generate_slot(f: &RustleElement, parent: String, analysis: &AnalysisResult, code: &mut Code) {
+    let block_name = format!("slot_{}", code.counter);
+    code.counter += 1;
+    let anchor_name = format!("{}_anchor", block_name);
+    let fallback_name = format!("create_{}_fallback", block_name);
+
+    let name = f
+        .attributes
+        .iter()
+        .find(|attr| attr.name == "name")
+        .and_then(|attr| static_value(&attr.value))
+        .unwrap_or_else(|| "default".into());
+    let slotted = format!("$$props.$$slots && $$props.$$slots[{:?}]", name);
+
+    let slot_props = f.attributes.iter().filter(|attr| attr.name != "name");
+    let mut dependencies: Vec<String> = Vec::new();
+    let mut props = Vec::new();
+    for attr in slot_props {
+        for dependency in changing_dependencies(&attr.value, analysis, code) {
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+        props.push(format!("{:?}: {}", attr.name, attribute_value(&attr.value)));
+    }
+    let props = format!("{{ {} }}", props.join(", "));
+
+    let fallback = match f.fragments.is_empty() {
+        true => "null".to_owned(),
+        false => {
+            generate_block(&fallback_name, &f.fragments, None, analysis, code);
+            format!("{}()", fallback_name)
+        }
+    };
+
+    code.variables.push(anchor_name.clone());
+    code.variables.push(block_name.clone());
+
+    let comment = create_node(&parent, "comment", "", code);
+    code.create.push(format!("{} = {};", anchor_name, comment));
+    mount(&parent, &anchor_name, code);
+    code.create.push(format!(
+        r#"
+		{block} = {slotted} ? {slotted}({props}) : {fallback};
+		if ({block}) {block}.create({anchor}.parentNode, {anchor});
+		"#,
+        block = block_name,
+        slotted = slotted,
+        props = props,
+        fallback = fallback,
+        anchor = anchor_name
+    ));
+    if is_block_root(&parent, code) {
+        code.insert.push(format!(
+            "if ({}) {}.insert(target, {});",
+            block_name, block_name, anchor_name
+        ));
+    }
+
+    code.update.push(format!(
+        r#"
+		if ({slotted}) {{
+			if ({changed}) {block}.update($$scope, {props});
+			else {block}.update($$scope);
+		}} else if ({block}) {{
+			{block}.update(changed);
+		}}
+		"#,
+        slotted = slotted,
+        changed = changed_condition(&dependencies),
+        block = block_name,
+        props = props
+    ));
+
+    code.destroy
+        .push(format!("if ({}) {}.destroy();", block_name, block_name));
+    unmount(&anchor_name, code);
+}
+
+/// Calls the action with the element once it is mounted, the
+/// returned `update` with the new parameters when they change
+/// and `destroy` when the element is removed.
+fn generate_action(
+    variable_name: &str,
+    action: &RustleAction,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let action_name = format!(
+        "{}_{}_action",
+        variable_name,
+        action.name.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    );
+    code.variables.push(action_name.clone());
+
+    let parameters = action.expression.as_ref().map(expr_to_string);
+    code.create.push(format!(
+        "{} = {}({}{});",
+        action_name,
+        action.name,
+        variable_name,
+        parameters.as_ref().map(|p| format!(", {}", p)).unwrap_or_default()
+    ));
+
+    if let (Some(expression), Some(parameters)) = (&action.expression, &parameters) {
+        let dependencies = changing_dependencies(expression, analysis, code);
+        if !dependencies.is_empty() {
+            code.update.push(format!(
+                r#"
+			if (({changed}) && {action} && typeof {action}.update === 'function') {{
+				{action}.update({parameters});
+			}}
+		"#,
+                changed = changed_condition(&dependencies),
+                action = action_name,
+                parameters = parameters
+            ));
+        }
+    }
+
+    code.destroy.push(format!(
+        "if ({action} && typeof {action}.destroy === 'function') {action}.destroy();",
+        action = action_name
+    ));
+}
+
+/// Listens to an event of the element, the modifiers become checks
+/// before the handler is called and options of the listener.
+fn generate_event_handler(
+    variable_name: &str,
+    handler: &RustleEventHandler,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let mut checks = Vec::new();
+    let mut options = Vec::new();
+    for modifier in &handler.modifiers {
+        match modifier.as_str() {
+            "self" => checks.push("if (event.target !== this) return;".to_owned()),
+            "trusted" => checks.push("if (!event.isTrusted) return;".to_owned()),
+            "capture" | "once" | "passive" => options.push(format!("{}: true", modifier)),
+            "nonpassive" => options.push("passive: false".to_owned()),
+            method => checks.push(format!("event.{}();", method)),
+        }
+    }
+    // `self` and `trusted` come first, the event is left untouched otherwise
+    checks.sort_by_key(|check| !check.starts_with("if"));
+
+    let options = match options.is_empty() {
+        true => String::new(),
+        false => format!(", {{ {} }}", options.join(", ")),
+    };
+
+    checks.push(handler_call(handler, analysis));
+
+    // the listener variable holds the function removing it
+    let listener = listener_name(variable_name, handler, code);
+    code.create.push(format!(
+        "{} = $$listen({}, '{}', function (event) {{ {} }}{});",
+        listener,
+        variable_name,
+        handler.name,
+        checks.join(" "),
+        options
+    ));
+    code.destroy.push(format!("{}();", listener));
+}
+
+/// Declares the variable holding the listener of an event handler
+fn listener_name(variable_name: &str, handler: &RustleEventHandler, code: &mut Code) -> String {
+    let event = handler
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let mut listener = format!("{}_{}", variable_name, event);
+    if code.variables.contains(&listener) {
+        listener = format!("{}_{}", listener, code.counter);
+        code.counter += 1;
+    }
+
+    code.variables.push(listener.clone());
+    listener
+}
+
+/// Calls the handler with the event, its assignments invalidated,
+/// an `on:name` without a handler forwards the event to the parent
+fn handler_call(handler: &RustleEventHandler, analysis: &AnalysisResult) -> String {
+    match &handler.expression {
+        Some(expr) => match invalidate_expr(expr, &analysis.assignments) {
+            expr @ (Expr::Ident(_) | Expr::Member(_)) => {
+                format!("return {}.call(this, event);", expr_to_string(&expr))
+            }
+            expr => format!("return ({}).call(this, event);", expr_to_string(&expr)),
+        },
+        None => format!("$$bubble('{}', event);", handler.name),
+    }
+}
+
+/// Binds a property of the element to a top-level variable or a
+/// name of an enclosing block, assigning it from the element events
+/// and the element property when it changes.
+fn generate_binding(f: &RustleElement, variable_name: &str, attr: &RustleAttribute, code: &mut Code) {
+    let binding = attr.name.trim_start_matches("bind:");
+    let value = expr_to_string(&attr.value);
+    let root = binding_root(&attr.value).unwrap();
+
+    if binding == "this" {
+        code.create.push(format!("{};", binding_assignment(&attr.value, variable_name, code)));
+        code.destroy.push(format!("{};", binding_assignment(&attr.value, "null", code)));
+        return;
+    }
+
+    let input_type = f
+        .attributes
+        .iter()
+        .find(|attr| attr.name == "type")
+        .and_then(|attr| static_value(&attr.value));
+
+    // the statement setting the element from the variable, and the
+    // expression reading the variable from the element
+    let (event, set, get) = match (binding, input_type.as_deref()) {
+        ("group", Some("checkbox")) => (
+            "change",
+            format!("{el}.checked = {value}.includes({el}.value);", el = variable_name, value = value),
+            format!(
+                "{el}.checked ? [...{value}, {el}.value] : {value}.filter(item => item !== {el}.value)",
+                el = variable_name,
+                value = value
+            ),
+        ),
+        ("group", _) => (
+            "change",
+            format!("{el}.checked = {el}.value === {value};", el = variable_name, value = value),
+            format!("{}.value", variable_name),
+        ),
+        ("value", Some("number" | "range")) => (
+            "input",
+            format!("{el}.value = {value};", el = variable_name, value = value),
+            format!("{el}.value === '' ? null : +{el}.value", el = variable_name),
+        ),
+        (property, _) => {
+            let event = match (f.name.as_str(), property) {
+                ("select", _) | (_, "checked") => "change",
+                _ => "input",
+            };
+            (
+                event,
+                format!(
+                    "if ({el}.{property} !== {value}) {el}.{property} = {value};",
+                    el = variable_name,
+                    property = property,
+                    value = value
+                ),
+                format!("{}.{}", variable_name, property),
+            )
+        }
+    };
+
+    let listener = format!("{}_{}_binding", variable_name, binding);
+    code.variables.push(listener.clone());
+    code.create.push(set.clone());
+    code.create.push(format!(
+        "{} = $$listen({}, '{}', () => {});",
+        listener,
+        variable_name,
+        event,
+        binding_assignment(&attr.value, &get, code)
+    ));
+    code.update.push(format!(
+        r#"
+		if (changed.includes('{}')) {{
+			{}
+		}}
+	"#,
+        root, set
+    ));
+    code.destroy.push(format!("{}();", listener));
+}
+
+/// Returns the assignment of `value` to a bound expression wrapped in
+/// the `$$invalidate` calls of the variables it changes, a binding to a
+/// name of an enclosing block invalidates the variables of the block.
+fn binding_assignment(expr: &Expr, value: &str, code: &Code) -> String {
+    let root = binding_root(expr).unwrap();
+    let (target, invalidate) = match code.bindings.iter().rev().find(|b| b.name == root) {
+        Some(binding) => {
+            let target = match (expr, &binding.item) {
+                (Expr::Ident(_), Some(item)) => item.clone(),
+                _ => expr_to_string(expr),
+            };
+            (target, binding.invalidate.clone())
+        }
+        None => (expr_to_string(expr), vec![root]),
+    };
+
+    invalidate
+        .iter()
+        .rev()
+        .fold(format!("{} = {}", target, value), |assignment, name| {
+            format!("$$invalidate('{}', {})", name, assignment)
+        })
+}
+
+/// Returns the variables a binding to a name read from `expr`
+/// changes, resolving the names of the enclosing blocks
+fn binding_invalidations(expr: &Expr, analysis: &AnalysisResult, code: &Code) -> Vec<String> {
+    let mut invalidate: Vec<String> = Vec::new();
+    for name in extract_dependencies(expr) {
+        let names = match code.bindings.iter().rev().find(|b| b.name == name) {
+            Some(binding) => binding.invalidate.clone(),
+            None if analysis.will_change.contains(&name) => vec![name],
+            None => Vec::new(),
+        };
+        for name in names {
+            if !invalidate.contains(&name) {
+                invalidate.push(name);
+            }
+        }
+    }
+
+    invalidate
+}
+
+/// Checks if an element of `fragments` binds `name` itself, `bind:value={name}`
+fn binds_directly(fragments: &[Fragment], name: &str) -> bool {
+    fragments.iter().any(|fragment| match fragment {
+        Fragment::Element(f) => {
+            f.attributes.iter().any(|attr| {
+                attr.name.starts_with("bind:")
+                    && matches!(&attr.value, Expr::Ident(ident) if &*ident.sym == name)
+            }) || binds_directly(&f.fragments, name)
+        }
+        Fragment::IfBlock(f) => {
+            binds_directly(&f.fragments, name)
+                || binds_directly(f.else_fragments.as_deref().unwrap_or_default(), name)
+        }
+        Fragment::EachBlock(f) => binds_directly(&f.fragments, name),
+        Fragment::KeyBlock(f) => binds_directly(&f.fragments, name),
+        Fragment::AwaitBlock(f) => {
+            binds_directly(&f.pending_fragments, name)
+                || binds_directly(f.then_fragments.as_deref().unwrap_or_default(), name)
+                || binds_directly(f.catch_fragments.as_deref().unwrap_or_default(), name)
+        }
+        _ => false,
+    })
+}
+
+/// Returns the javascript of an attribute value, the `{}`
+/// of quoted values become template literal placeholders
+fn attribute_value(value: &Expr) -> String {
+    let value = match value {
+        Expr::Ident(ident) => return ident.sym.to_string(),
+        Expr::Lit(Lit::Str(str)) => return format!("\"{}\"", str.value),
+        Expr::Tpl(_) => expr_to_string(value),
+        expr => return expr_to_string(expr),
+    };
+
+    // replace `{xx} xx` => `${xx} xx`
+    value
+        .char_indices()
+        .fold(String::with_capacity(value.len()), |mut result, (offset, char)| {
+            if char == '{' && value[offset..].contains('}') {
+                result.push('$');
+            }
+            result.push(char);
+            result
+        })
+}
+
+/// Returns the dependencies of `expr` that can change: the variables
+/// reassigned in the script and the names of the enclosing `{#each}` blocks
+fn changing_dependencies(expr: &Expr, analysis: &AnalysisResult, code: &Code) -> Vec<String> {
+    extract_dependencies(expr)
+        .into_iter()
+        .filter(|name| analysis.will_change.contains(name) || code.contexts.contains(name))
+        .collect()
+}
+
+/// `changed.includes('a') || changed.includes('b')`
+fn changed_condition(dependencies: &[String]) -> String {
+    if dependencies.is_empty() {
+        return "false".to_owned();
+    }
+
+    dependencies
+        .iter()
+        .map(|name| format!("changed.includes('{}')", name))
+        .collect::<Vec<String>>()
+        .join(" || ")
+}
+
+/// Generates an `{#if}` block. Every branch becomes a block function,
+/// `select_if_block_*` returns the one matching the current state
+/// and the update swaps them once a condition dependency changes.
+fn generate_if_block(
+    block: &RustleIfBlock,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let block_name = format!("if_block_{}", code.counter);
+    code.counter += 1;
+    let anchor_name = format!("{}_anchor", block_name);
+    let current_name = format!("{}_current", block_name);
+    let select_name = format!("select_{}", block_name);
+
+    // flatten the `{:else if}` chain into a list of branches
+    let mut branches = Vec::new();
+    let mut next = Some(block);
+    while let Some(block) = next.take() {
+        branches.push((Some(&block.expression), block.fragments.as_slice()));
+        match block.else_fragments.as_deref() {
+            Some([Fragment::IfBlock(else_if)]) => next = Some(else_if),
+            Some(fragments) => branches.push((None, fragments)),
+            None => (),
+        }
+    }
+
+    let mut dependencies: Vec<String> = Vec::new();
+    let mut select = Vec::new();
+    let mut transitions = false;
+    for (index, (condition, fragments)) in branches.into_iter().enumerate() {
+        let branch_name = format!("create_{}_{}", block_name, index);
+        transitions |= generate_block(&branch_name, fragments, None, analysis, code);
+
+        match condition {
+            Some(condition) => {
+                for dependency in changing_dependencies(condition, analysis, code) {
+                    if !dependencies.contains(&dependency) {
+                        dependencies.push(dependency);
+                    }
+                }
+                select.push(format!(
+                    "if ({}) return {};",
+                    expr_to_string(condition),
+                    branch_name
+                ));
+            }
+            None => select.push(format!("return {};", branch_name)),
+        }
+    }
+    if !select.last().is_some_and(|s| s.starts_with("return")) {
+        select.push("return null;".into());
+    }
+
+    code.blocks.push(format!(
+        r#"
+		function {}() {{
+			{}
+		}}
+		"#,
+        select_name,
+        select.join("\n")
+    ));
+
+    code.variables.push(anchor_name.clone());
+    code.variables.push(current_name.clone());
+    code.variables.push(block_name.clone());
+
+    let comment = create_node(&parent, "comment", "", code);
+    code.create.push(format!("{} = {};", anchor_name, comment));
+    mount(&parent, &anchor_name, code);
+    code.create.push(format!(
+        r#"
+		{current} = {select}();
+		{block} = {current} && {current}();
+		if ({block}) {block}.create({anchor}.parentNode, {anchor});
+		"#,
+        current = current_name,
+        select = select_name,
+        block = block_name,
+        anchor = anchor_name
+    ));
+    if is_block_root(&parent, code) {
+        code.insert
+            .push(format!("if ({}) {}.insert(target, {});", block_name, block_name, anchor_name));
+    }
+
+    code.update.push(format!(
+        r#"
+		if (({changed}) && {select}() !== {current}) {{
+			if ({block}) {remove}
+			{current} = {select}();
+			{block} = {current} && {current}();
+			if ({block}) {block}.create({anchor}.parentNode, {anchor});
+			{intro}
+		}} else if ({block}) {{
+			{block}.update(changed);
+		}}
+		"#,
+        changed = changed_condition(&dependencies),
+        current = current_name,
+        select = select_name,
+        block = block_name,
+        anchor = anchor_name,
+        remove = remove_block(&block_name, transitions),
+        intro = intro_block(&block_name, transitions)
+    ));
+    if transitions {
+        nest_transitions(&block_name, code);
+    }
+
+    code.destroy
+        .push(format!("if ({}) {}.destroy();", block_name, block_name));
+    unmount(&anchor_name, code);
+}
+
+/// Generates a `{#key}` block, the block is destroyed and created
+/// again when the value of its expression changes.
+fn generate_key_block(
+    block: &RustleKeyBlock,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let block_name = format!("key_block_{}", code.counter);
+    code.counter += 1;
+    let create_name = format!("create_{}", block_name);
+
+    let transitions = generate_block(&create_name, &block.fragments, None, analysis, code);
+    generate_keyed(&block_name, &block.expression, false, transitions, parent, analysis, code);
+}
+
+/// Generates `<svelte:element>` and `<svelte:component>`, the element
+/// or component is created again whenever `this` changes, and not at
+/// all while it is falsy.
+fn generate_dynamic(f: &RustleElement, parent: String, analysis: &AnalysisResult, code: &mut Code) {
+    let block_name = format!("{}_{}", variable_prefix(&f.name), code.counter);
+    code.counter += 1;
+    let create_name = format!("create_{}", block_name);
+    let value_name = format!("{}_value", block_name);
+
+    let mut block = Code::new(
+        code.counter,
+        Some("anchor".into()),
+        code.contexts.clone(),
+        code.dev,
+        code.hydratable,
+    );
+    block.bindings = code.bindings.clone();
+    match f.is_component {
+        true => generate_component(f, &value_name, "target".into(), analysis, &mut block),
+        false => generate_element(f, &value_name, "target".into(), analysis, &mut block),
+    }
+    code.counter = block.counter;
+    push_block(&create_name, "", String::new(), String::new(), &block, code);
+
+    let this = f.this.as_ref().unwrap();
+    let transitions = block.has_transitions();
+    generate_keyed(&block_name, this, true, transitions, parent, analysis, code);
+}
+
+/// Creates the block of `create_{block}` before an anchor, destroying and
+/// creating it again when the value of `expression`, kept in `{block}_value`,
+/// changes. An `optional` block is only created for a truthy value.
+fn generate_keyed(
+    block_name: &str,
+    expression: &Expr,
+    optional: bool,
+    transitions: bool,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let anchor_name = format!("{}_anchor", block_name);
+    let value_name = format!("{}_value", block_name);
+    let create_name = format!("create_{}", block_name);
+
+    code.variables.push(anchor_name.clone());
+    code.variables.push(value_name.clone());
+    code.variables.push(block_name.to_owned());
+
+    let create_block = match optional {
+        true => format!(
+            "{block} = {value} ? {create}() : null;\nif ({block}) {block}.create({anchor}.parentNode, {anchor});",
+            block = block_name,
+            value = value_name,
+            create = create_name,
+            anchor = anchor_name
+        ),
+        false => format!(
+            "{block} = {create}();\n{block}.create({anchor}.parentNode, {anchor});",
+            block = block_name,
+            create = create_name,
+            anchor = anchor_name
+        ),
+    };
+
+    let value = expr_to_string(expression);
+    let comment = create_node(&parent, "comment", "", code);
+    code.create.push(format!("{} = {};", anchor_name, comment));
+    mount(&parent, &anchor_name, code);
+    code.create.push(format!("{} = {};", value_name, value));
+    code.create.push(create_block.clone());
+    if is_block_root(&parent, code) {
+        code.insert.push(format!(
+            "if ({block}) {block}.insert(target, {anchor});",
+            block = block_name,
+            anchor = anchor_name
+        ));
+    }
+
+    let dependencies = changing_dependencies(expression, analysis, code);
+    let update = format!("if ({block}) {block}.update(changed);", block = block_name);
+    match dependencies.is_empty() {
+        true => code.update.push(update),
+        false => code.update.push(format!(
+            r#"
+		if (({changed}) && $$safe_not_equal({value_name}, {value_name} = {value})) {{
+			if ({block}) {remove}
+			{create_block}
+			{intro}
+		}} else {{
+			{update}
+		}}
+		"#,
+            changed = changed_condition(&dependencies),
+            value_name = value_name,
+            value = value,
+            block = block_name,
+            remove = remove_block(block_name, transitions),
+            create_block = create_block,
+            intro = intro_block(block_name, transitions),
+            update = update
+        )),
+    }
+    if transitions {
+        nest_transitions(block_name, code);
+    }
+
+    code.destroy
+        .push(format!("if ({block}) {block}.destroy();", block = block_name));
+    unmount(&anchor_name, code);
+}
+
+/// The item and index of an `{#each}` block or the settled value
+/// of an `{#await}` block, passed to its block function
+struct BlockContext {
+    /// The names declared by the context pattern and the index
+    names: Vec<String>,
+    /// Assigns `context_value` and `context_index` to the names
+    assign: String,
+    bindings: Vec<ContextBinding>,
+    /// An empty text inserted before the nodes of the block, returned by
+    /// its `first()` method to insert the other blocks before it
+    first: Option<String>,
+}
+
+/// A name of an enclosing block a `bind:` directive can assign
+#[derive(Clone)]
+struct ContextBinding {
+    name: String,
+    /// The variables invalidated by a binding to the name, the
+    /// ones the list of an `{#each}` block is read from
+    invalidate: Vec<String>,
+    /// The list item a binding to the `{#each}` item itself assigns,
+    /// like `items[index]`
+    item: Option<String>,
+}
+
+/// Generates a block function named `name` rendering `fragments`.
+///
+/// The block function returns a lifecycle with `create(target, anchor)`,
+/// `update(changed)`, `insert(target, anchor)` and `destroy()`; its root
+/// nodes are inserted before `anchor` inside `target`.
+///
+/// With a `context` the block function accepts `(context_value, context_index)`
+/// and so does `update(changed, context_value, context_index)`, marking the
+/// context names as changed.
+///
+/// Returns whether the block plays transitions, then its lifecycle also has
+/// `intro($$local)` and `outro($$local, $$done)`.
+fn generate_block<'a>(
+    name: &str,
+    fragments: impl IntoIterator<Item = &'a Fragment>,
+    context: Option<BlockContext>,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) -> bool {
+    let fragments = fragments.into_iter().collect::<Vec<&Fragment>>();
+    let consts = fragments
+        .iter()
+        .filter_map(|fragment| match fragment {
+            Fragment::ConstTag(tag) => Some(tag),
+            _ => None,
+        })
+        .collect::<Vec<&RustleConstTag>>();
+    let const_names = consts
+        .iter()
+        .flat_map(|tag| extract_pattern_names(&tag.pattern))
+        .collect::<Vec<String>>();
+
+    let mut contexts = code.contexts.clone();
+    if let Some(context) = &context {
+        contexts.extend(context.names.clone());
+    }
+    contexts.extend(const_names.clone());
+
+    let mut block = Code::new(
+        code.counter,
+        Some("anchor".into()),
+        contexts,
+        code.dev,
+        code.hydratable,
+    );
+    block.bindings = code.bindings.clone();
+    if let Some(context) = &context {
+        block.bindings.extend(context.bindings.clone());
+    }
+    if let Some(first) = context.as_ref().and_then(|context| context.first.as_ref()) {
+        block.variables.push(first.clone());
+        let text = create_node("target", "text", "''", &block);
+        block.create.push(format!("{} = {};", first, text));
+        mount("target", first, &mut block);
+        unmount(first, &mut block);
+        block.methods.push(format!(
+            r#"
+				first() {{
+					return {};
+				}},
+		"#,
+            first
+        ));
+    }
+    for fragment in fragments {
+        traverse(fragment, "target".into(), analysis, &mut block);
+    }
+    code.counter = block.counter;
+
+    // the `{@const}` tags are assigned again when their dependencies change
+    let (set_consts, update_consts) = match consts.is_empty() {
+        true => (String::new(), String::new()),
+        false => {
+            let mut dependencies: Vec<String> = Vec::new();
+            for tag in &consts {
+                for dependency in changing_dependencies(&tag.init, analysis, &block) {
+                    if !dependencies.contains(&dependency) {
+                        dependencies.push(dependency);
+                    }
+                }
+            }
+            let assign = consts
+                .iter()
+                .map(|tag| format!("({} = {});", pat_to_string(&tag.pattern), expr_to_string(&tag.init)))
+                .collect::<Vec<String>>()
+                .join("\n");
+            (
+                format!(
+                    r#"
+			let {};
+			function set_consts() {{
+				{}
+			}}
+			set_consts();
+			"#,
+                    const_names.join(", "),
+                    assign
+                ),
+                format!(
+                    r#"
+					if ({}) {{
+						set_consts();
+						changed = changed.concat({:?});
+					}}
+					"#,
+                    changed_condition(&dependencies),
+                    const_names
+                ),
+            )
+        }
+    };
+
+    let (params, set_context, update_context) = match &context {
+        Some(context) => (
+            "context_value, context_index",
+            format!(
+                r#"
+			let {};
+			function set_context(context_value, context_index) {{
+				{}
+			}}
+			set_context(context_value, context_index);
+			"#,
+                context.names.join(", "),
+                context.assign
+            ),
+            format!(
+                r#"
+					if (context.length) {{
+						set_context(...context);
+						changed = changed.concat({:?});
+					}}
+					"#,
+                context.names
+            ),
+        ),
+        None => ("", String::new(), String::new()),
+    };
+
+    push_block(
+        name,
+        params,
+        set_context + &set_consts,
+        update_context + &update_consts,
+        &block,
+        code,
+    );
+    block.has_transitions()
+}
+
+/// Pushes the block function named `name` returning the lifecycle of the
+/// `block` code, `set_context` runs when the function is called and
+/// `update_context` at the start of `update`.
+///
+/// The transition methods are only added for a block with transitions.
+fn push_block(
+    name: &str,
+    params: &str,
+    set_context: String,
+    update_context: String,
+    block: &Code,
+    code: &mut Code,
+) {
+    let transitions = match block.has_transitions() || !block.methods.is_empty() {
+        true => transition_methods(block),
+        false => String::new(),
+    };
+
+    code.blocks.push(format!(
+        r#"
+		function {}({}) {{
+			{}
+			{}
+			{}
+
+			return {{
+				create(target, anchor) {{
+					{}
+				}},
+				update(changed, ...context) {{
+					{}
+					{}
+				}},
+				insert(target, anchor) {{
+					{}
+				}},
+				{}
+				destroy() {{
+					{}
+				}},
+			}};
+		}}
+		"#,
+        name,
+        params,
+        set_context,
+        block.declarations(),
+        block.blocks.join("\n"),
+        block.create.join("\n"),
+        update_context,
+        block.update.join("\n"),
+        block.insert.join("\n"),
+        transitions,
+        block.destroy.join("\n")
+    ));
+}
+
+/// Generates an `{#each}` block. Every item gets its own block
+/// created by `create_each_block_*(context_value, context_index)` and
+/// `update_each_block_*` reconciles them with the current list.
+///
+/// Without a key, blocks are reused by position. With a key, blocks
+/// are looked up by key and reused, only the ones out of order are
+/// moved, and an item with `animate:` is animated from where it was
+/// before. In dev mode, duplicate keys throw.
+fn generate_each_block(
+    block: &RustleEachBlock,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let block_name = format!("each_block_{}", code.counter);
+    code.counter += 1;
+    let anchor_name = format!("{}_anchor", block_name);
+    let keys_name = format!("{}_keys", block_name);
+    let else_name = format!("{}_else", block_name);
+    let create_name = format!("create_{}", block_name);
+    let create_else_name = format!("create_{}_else", block_name);
+    let update_name = format!("update_{}", block_name);
+    let key_name = format!("key_{}", block_name);
+
+    let context = pat_to_string(&block.context);
+    let mut names = extract_pattern_names(&block.context);
+    let mut assign = format!("({} = context_value);", context);
+    if let Some(index) = &block.index {
+        names.push(index.clone());
+        assign.push_str(&format!("\n{} = context_index;", index));
+    }
+
+    // a binding to the item itself assigns it in the list, at an
+    // index kept in a variable of its own when the block has none
+    let mut item = None;
+    if let (Pat::Ident(ident), Expr::Ident(_) | Expr::Member(_)) =
+        (&block.context, block.expression.unwrap_parens())
+    {
+        if binds_directly(&block.fragments, &ident.id.sym) {
+            let index = match &block.index {
+                Some(index) => index.clone(),
+                None => {
+                    let index = format!("{}_index", block_name);
+                    names.push(index.clone());
+                    assign.push_str(&format!("\n{} = context_index;", index));
+                    index
+                }
+            };
+            let list_item = format!("{}[{}]", expr_to_string(&block.expression), index);
+            item = Some((ident.id.sym.to_string(), list_item));
+        }
+    }
+
+    // the items belong to the variables the list is read from
+    let invalidate = binding_invalidations(&block.expression, analysis, code);
+    let bindings = names
+        .iter()
+        .map(|name| ContextBinding {
+            name: name.clone(),
+            invalidate: match invalidate.is_empty() {
+                true => vec![name.clone()],
+                false => invalidate.clone(),
+            },
+            item: item
+                .as_ref()
+                .filter(|(item_name, _)| item_name == name)
+                .map(|(_, item)| item.clone()),
+        })
+        .collect();
+
+    let transitions = generate_block(
+        &create_name,
+        &block.fragments,
+        Some(BlockContext {
+            names,
+            assign,
+            bindings,
+            first: block.key.as_ref().map(|_| format!("{}_first", block_name)),
+        }),
+        analysis,
+        code,
+    );
+    // the intros only play for the items added by an update
+    let intro = match transitions {
+        true => "if ($$intro) block.intro(true);",
+        false => "",
+    };
+    let animated = block.key.is_some()
+        && block
+            .fragments
+            .iter()
+            .any(|fragment| matches!(fragment, Fragment::Element(f) if f.animation.is_some()));
+
+    let dependencies = changing_dependencies(&block.expression, analysis, code);
+    let items = format!("Array.from({})", expr_to_string(&block.expression));
+
+    let reconcile = match &block.key {
+        Some(key) => {
+            code.blocks.push(format!(
+                r#"
+		function {}({}, {}) {{
+			return {};
+		}}
+		"#,
+                key_name,
+                context,
+                block.index.clone().unwrap_or_else(|| "index".into()),
+                expr_to_string(key)
+            ));
+
+            let (measure, animate) = match animated {
+                true => (
+                    format!("const each_rects = new Map({}.map(block => [block, block.measure()]));", block_name),
+                    r#"
+			each_blocks.forEach(block => {
+				const rect = each_rects.get(block);
+				if (rect) block.animate(rect);
+			});
+			"#,
+                ),
+                false => (String::new(), ""),
+            };
+
+            // duplicate keys would share a block
+            let validate = match code.dev {
+                true => format!(
+                    r#"
+			const each_indexes = new Map();
+			each_items.forEach((item, index) => {{
+				const key = {key}(item, index);
+				if (each_indexes.has(key)) {{
+					throw new Error(`Cannot have duplicate keys in a keyed each: Keys at index ${{each_indexes.get(key)}} and ${{index}} with value '${{String(key)}}' are duplicates`);
+				}}
+				each_indexes.set(key, index);
+			}});
+			"#,
+                    key = key_name
+                ),
+                false => String::new(),
+            };
+
+            // only the added blocks and the ones out of order are inserted,
+            // before the first block after them left in place
+            format!(
+                r#"
+			{validate}
+			{measure}
+			const each_lookup = new Map({keys}.map((key, index) => [key, {block}[index]]));
+			const each_previous = new Map({block}.map((block, index) => [block, index]));
+			const each_blocks = [];
+			const each_keys = [];
+			for (let index = 0; index < each_items.length; index++) {{
+				const key = {key}(each_items[index], index);
+				let block = each_lookup.get(key);
+				if (block) {{
+					each_lookup.delete(key);
+					block.update(changed, each_items[index], index);
+				}} else {{
+					block = {create}(each_items[index], index);
+				}}
+				each_blocks.push(block);
+				each_keys.push(key);
+			}}
+			each_lookup.forEach(block => {remove});
+
+			const each_unmoved = $$unmoved_blocks(each_blocks, each_previous);
+			const each_anchors = [];
+			for (let index = each_blocks.length - 1, next = {anchor}; index >= 0; index--) {{
+				each_anchors[index] = next;
+				if (each_unmoved.has(each_blocks[index])) next = each_blocks[index].first();
+			}}
+			each_blocks.forEach((block, index) => {{
+				if (!each_previous.has(block)) {{
+					block.create(each_target, each_anchors[index]);
+					{intro}
+				}} else if (!each_unmoved.has(block)) {{
+					block.insert(each_target, each_anchors[index]);
+				}}
+			}});
+			{animate}
+			{block} = each_blocks;
+			{keys} = each_keys;
+			"#,
+                validate = validate,
+                measure = measure,
+                keys = keys_name,
+                block = block_name,
+                key = key_name,
+                create = create_name,
+                anchor = anchor_name,
+                intro = intro,
+                remove = remove_item(transitions),
+                animate = animate
+            )
+        }
+        None => format!(
+            r#"
+			for (let index = 0; index < each_items.length; index++) {{
+				if (index < {block}.length) {{
+					{block}[index].update(changed, each_items[index], index);
+				}} else {{
+					const block = {create}(each_items[index], index);
+					block.create(each_target, {anchor});
+					{intro}
+					{block}.push(block);
+				}}
+			}}
+			{block}.splice(each_items.length).forEach(block => {remove});
+			"#,
+            block = block_name,
+            create = create_name,
+            anchor = anchor_name,
+            intro = intro,
+            remove = remove_item(transitions)
+        ),
+    };
+
+    let mut else_transitions = false;
+    let reconcile_else = match &block.else_fragments {
+        Some(fragments) => {
+            else_transitions = generate_block(&create_else_name, fragments, None, analysis, code);
+            format!(
+                r#"
+			if (each_items.length === 0 && !{else_block}) {{
+				{else_block} = {create}();
+				{else_block}.create(each_target, {anchor});
+				{intro}
+			}} else if (each_items.length === 0) {{
+				{else_block}.update(changed);
+			}} else if ({else_block}) {{
+				{remove}
+				{else_block} = null;
+			}}
+			"#,
+                else_block = else_name,
+                create = create_else_name,
+                anchor = anchor_name,
+                intro = match else_transitions {
+                    true => format!("if ($$intro) {}.intro(true);", else_name),
+                    false => String::new(),
+                },
+                remove = remove_block(&else_name, else_transitions)
+            )
+        }
+        None => String::new(),
+    };
+
+    code.blocks.push(format!(
+        r#"
+		function {}(changed, $$intro) {{
+			const each_target = {}.parentNode;
+			const each_items = {};
+			{}
+			{}
+		}}
+		"#,
+        update_name, anchor_name, items, reconcile, reconcile_else
+    ));
+
+    code.variables.push(anchor_name.clone());
+    code.variables.push(format!("{} = []", block_name));
+    if block.key.is_some() {
+        code.variables.push(format!("{} = []", keys_name));
+    }
+    if block.else_fragments.is_some() {
+        code.variables.push(else_name.clone());
+    }
+
+    let comment = create_node(&parent, "comment", "", code);
+    code.create.push(format!("{} = {};", anchor_name, comment));
+    mount(&parent, &anchor_name, code);
+    code.create.push(format!("{}([]);", update_name));
+    if is_block_root(&parent, code) {
+        code.insert.push(format!(
+            "{}.forEach(block => block.insert(target, {}));",
+            block_name, anchor_name
+        ));
+        if block.else_fragments.is_some() {
+            code.insert.push(format!(
+                "if ({}) {}.insert(target, {});",
+                else_name, else_name, anchor_name
+            ));
+        }
+    }
+
+    let update_else = match &block.else_fragments {
+        Some(_) => format!("if ({}) {}.update(changed);", else_name, else_name),
+        None => String::new(),
+    };
+    code.update.push(format!(
+        r#"
+		if ({}) {{
+			{}(changed, true);
+		}} else {{
+			{}.forEach(block => block.update(changed));
+			{}
+		}}
+		"#,
+        changed_condition(&dependencies),
+        update_name,
+        block_name,
+        update_else
+    ));
+    if transitions {
+        code.intro
+            .push(format!("{}.forEach(block => block.intro(false));", block_name));
+        code.outro.push(format!(
+            "{}.forEach(block => {{ $$pending++; block.outro(false, $$finish); }});",
+            block_name
+        ));
+    }
+    if else_transitions {
+        nest_transitions(&else_name, code);
+    }
+
+    code.destroy
+        .push(format!("{}.forEach(block => block.destroy());", block_name));
+    if block.else_fragments.is_some() {
+        code.destroy
+            .push(format!("if ({}) {}.destroy();", else_name, else_name));
+    }
+    unmount(&anchor_name, code);
+}
+
+/// Generates an `{#await}` block. `update_await_block_*` renders the
+/// pending branch and swaps in the `then` or `catch` branch once the
+/// promise settles, unless the expression changed in the meantime.
+///
+/// A value that is not a promise renders the `then` branch right away,
+/// a rejection without a `{:catch}` branch is thrown again.
+fn generate_await_block(
+    block: &RustleAwaitBlock,
+    parent: String,
+    analysis: &AnalysisResult,
+    code: &mut Code,
+) {
+    let block_name = format!("await_block_{}", code.counter);
+    code.counter += 1;
+    let anchor_name = format!("{}_anchor", block_name);
+    let token_name = format!("{}_token", block_name);
+    let update_name = format!("update_{}", block_name);
+    let switch_name = format!("switch_{}", block_name);
+
+    let pending_name = format!("create_{}_pending", block_name);
+    let mut transitions =
+        generate_block(&pending_name, &block.pending_fragments, None, analysis, code);
+
+    let mut branch = |suffix: &str, pattern: &Option<Pat>, fragments: &Option<Vec<Fragment>>| {
+        let fragments = match fragments {
+            Some(fragments) => fragments,
+            None => return "null".to_owned(),
+        };
+        // the value isn't stored in a variable, a binding to it
+        // only updates the block
+        let context = pattern.as_ref().map(|pattern| BlockContext {
+            names: extract_pattern_names(pattern),
+            assign: format!("({} = context_value);", pat_to_string(pattern)),
+            bindings: extract_pattern_names(pattern)
+                .into_iter()
+                .map(|name| ContextBinding {
+                    invalidate: vec![name.clone()],
+                    name,
+                    item: None,
+                })
+                .collect(),
+            first: None,
+        });
+
+        let name = format!("create_{}_{}", block_name, suffix);
+        transitions |= generate_block(&name, fragments, context, analysis, code);
+        name
+    };
+    let then_name = branch("then", &block.value, &block.then_fragments);
+    let catch_name = branch("catch", &block.error, &block.catch_fragments);
+
+    code.blocks.push(format!(
+        r#"
+		function {switch}(create, value, $$intro) {{
+			if ({block}) {remove}
+			{block} = create && create(value);
+			if ({block}) {block}.create({anchor}.parentNode, {anchor});
+			{intro}
+		}}
+
+		function {update}($$intro) {{
+			const await_token = {token} = {{}};
+			const await_value = {expression};
+			if (await_value && typeof await_value.then === 'function') {{
+				{switch}({pending}, undefined, $$intro);
+				await_value.then(
+					value => {{
+						if (await_token === {token}) {switch}({then}, value, true);
+					}},
+					error => {{
+						if (await_token === {token}) {switch}({catch}, error, true);{rethrow}
+					}}
+				);
+			}} else {{
+				{switch}({then}, await_value, $$intro);
+			}}
+		}}
+		"#,
+        remove = remove_block(&block_name, transitions),
+        intro = match transitions {
+            true => format!("if ({block} && $$intro) {block}.intro(true);", block = block_name),
+            false => String::new(),
+        },
+        switch = switch_name,
+        update = update_name,
+        block = block_name,
+        anchor = anchor_name,
+        token = token_name,
+        expression = expr_to_string(&block.expression),
+        pending = pending_name,
+        then = then_name,
+        // without a `{:catch}` branch the rejection is left unhandled
+        rethrow = match block.catch_fragments {
+            Some(_) => "",
+            None => "\n\t\t\t\t\t\tthrow error;",
+        },
+        catch = catch_name
+    ));
+
+    code.variables.push(anchor_name.clone());
+    code.variables.push(token_name.clone());
+    code.variables.push(block_name.clone());
+
+    let comment = create_node(&parent, "comment", "", code);
+    code.create.push(format!("{} = {};", anchor_name, comment));
+    mount(&parent, &anchor_name, code);
+    code.create.push(format!("{}();", update_name));
+    if is_block_root(&parent, code) {
+        code.insert.push(format!(
+            "if ({}) {}.insert(target, {});",
+            block_name, block_name, anchor_name
+        ));
+    }
+
+    let dependencies = changing_dependencies(&block.expression, analysis, code);
+    code.update.push(format!(
+        r#"
+		if ({}) {{
+			{}(true);
+		}} else if ({}) {{
+			{}.update(changed);
+		}}
+		"#,
+        changed_condition(&dependencies),
+        update_name,
+        block_name,
+        block_name
+    ));
+    if transitions {
+        nest_transitions(&block_name, code);
+    }
+
+    code.destroy.push(format!(
+        "{} = null;\nif ({}) {}.destroy();",
+        token_name, block_name, block_name
+    ));
+    unmount(&anchor_name, code);
+}
+
+/// Generates an `{@html}` tag, the nodes parsed from the value are
+/// inserted before an anchor and replaced whenever the value changes.
+fn generate_raw_html(f: &RustleRawHtml, parent: String, analysis: &AnalysisResult, code: &mut Code) {
+    let nodes_name = format!("html_{}", code.counter);
+    code.counter += 1;
+    let anchor_name = format!("{}_anchor", nodes_name);
+    let set_name = format!("set_{}", nodes_name);
+
+    code.blocks.push(format!(
+        r#"
+		function {set}(value) {{
+			{nodes}.forEach($$detach);
+			const template = $$element('template');
+			template.innerHTML = value == null ? '' : value;
+			{nodes} = Array.from(template.content.childNodes);
+			{nodes}.forEach(node => $$insert({anchor}.parentNode, node, {anchor}));
+		}}
+		"#,
+        set = set_name,
+        nodes = nodes_name,
+        anchor = anchor_name
+    ));
+
+    code.variables.push(anchor_name.clone());
+    code.variables.push(format!("{} = []", nodes_name));
+
+    let value = expr_to_string(&f.expression);
+    let comment = create_node(&parent, "comment", "", code);
+    code.create.push(format!("{} = {};", anchor_name, comment));
+    mount(&parent, &anchor_name, code);
+    code.create.push(format!("{}({});", set_name, value));
+    if is_block_root(&parent, code) {
+        code.insert.push(format!(
+            "{}.forEach(node => $$insert(target, node, {}));",
+            nodes_name, anchor_name
+        ));
+    }
+
+    let dependencies = changing_dependencies(&f.expression, analysis, code);
+    if !dependencies.is_empty() {
+        code.update.push(format!(
+            r#"
+			if ({}) {{
+				{}({});
+			}}
+		"#,
+            changed_condition(&dependencies),
+            set_name,
+            value
+        ));
+    }
+
+    code.destroy.push(format!("{}.forEach($$detach);", nodes_name));
+    unmount(&anchor_name, code);
+}
+
+/// Generates a `{@debug}` tag in dev mode, logging its names and pausing
+/// in the debugger once created and whenever one of them changes.
+fn generate_debug_tag(f: &RustleDebugTag, analysis: &AnalysisResult, code: &mut Code) {
+    if !code.dev {
+        return;
+    }
+
+    let names = f
+        .identifiers
+        .iter()
+        .map(|ident| ident.sym.to_string())
+        .collect::<Vec<String>>();
+    let log = format!("console.log({{ {} }});\ndebugger;", names.join(", "));
+    code.create.push(log.clone());
+
+    let dependencies = names
+        .iter()
+        .filter(|name| analysis.will_change.contains(*name) || code.contexts.contains(name))
+        .cloned()
+        .collect::<Vec<String>>();
+    let condition = match names.is_empty() {
+        true => "changed.length".to_owned(),
+        false if dependencies.is_empty() => return,
+        false => changed_condition(&dependencies),
+    };
+    code.update.push(format!(
+        r#"
+		if ({}) {{
+			{}
+		}}
+	"#,
+        condition, log
+    ));
+}
+
+/// The prefix of the variables of an element or component,
+/// `svelte:self` -> `svelte_self`
+fn variable_prefix(name: &str) -> String {
+    name.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+}
+
+/// Returns the javascript string literal of `value`, quoted and escaped
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+/// Whether `parent` is the target of a block, so nodes appended
+/// to it are the root nodes of that block
+fn is_block_root(parent: &str, code: &Code) -> bool {
+    code.anchor.is_some() && parent == "target"
+}
+
+/// The javascript creating a node with `$$element`, `$$text` or `$$comment`.
+/// Generating a hydratable component, the node is claimed among the
+/// children of its parent, up to the block anchor for the root nodes.
+fn create_node(parent: &str, kind: &str, args: &str, code: &Code) -> String {
+    // the content of `<svelte:head>` is always created
+    if !code.hydratable || parent == "document.head" {
+        return format!("$${}({})", kind, args);
+    }
+
+    let anchor = match &code.anchor {
+        Some(anchor) if is_block_root(parent, code) => anchor.as_str(),
+        _ => "null",
+    };
+    let args = [parent, anchor, args]
+        .into_iter()
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<&str>>();
+    format!("$$claim_{}({})", kind, args.join(", "))
+}
+
+/// Appends the node to its parent, or inserts it before
+/// the block anchor for the root nodes of a block
+fn mount(parent: &str, variable_name: &str, code: &mut Code) {
+    match code.anchor.clone() {
+        Some(anchor) if is_block_root(parent, code) => {
+            let insert = format!("$$insert({}, {}, {});", parent, variable_name, anchor);
+            code.create.push(insert.clone());
+            code.insert.push(insert);
+        }
+        _ => code
+            .create
+            .push(format!("$$append({}, {});", parent, variable_name)),
+    }
+}
+
+/// Removes the node from its parent on destroy
+fn unmount(variable_name: &str, code: &mut Code) {
+    code.destroy.push(format!("$$detach({});", variable_name));
 }
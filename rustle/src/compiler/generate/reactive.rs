@@ -0,0 +1,153 @@
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::{
+    BlockStmt, CallExpr, Callee, Decl, Expr, ExprStmt, FnDecl, Function, Ident, Pat, Script, Stmt,
+    VarDecl, VarDeclKind, VarDeclarator,
+};
+
+use super::changed_condition;
+use crate::compiler::analyse::{is_reactive_statement, AnalysisResult};
+
+/// Moves the `$:` statements of the script into `$$reactive_{index}`
+/// functions, called once at the end of the script in the sorted
+/// order, and declares the names they assign implicitly.
+pub fn reactive_script(script: &Script, analysis: &AnalysisResult) -> Script {
+    let mut body = Vec::new();
+    if !analysis.implicit_declarations.is_empty() {
+        body.push(Stmt::Decl(Decl::Var(Box::new(VarDecl {
+            span: DUMMY_SP,
+            kind: VarDeclKind::Let,
+            declare: false,
+            decls: analysis
+                .implicit_declarations
+                .iter()
+                .map(|name| VarDeclarator {
+                    span: DUMMY_SP,
+                    name: Pat::Ident(ident(name).into()),
+                    init: None,
+                    definite: false,
+                })
+                .collect(),
+        }))));
+    }
+
+    let mut functions = Vec::new();
+    for stmt in &script.body {
+        let labeled = match stmt {
+            Stmt::Labeled(labeled) if is_reactive_statement(stmt) => labeled,
+            stmt => {
+                body.push(stmt.clone());
+                continue;
+            }
+        };
+        let index = analysis
+            .reactive_declarations
+            .iter()
+            .position(|declaration| declaration.span == labeled.span)
+            .unwrap();
+        functions.push((index, reactive_function(index, &labeled.body)));
+    }
+    functions.sort_by_key(|(index, _)| *index);
+
+    let calls = functions
+        .iter()
+        .map(|(index, _)| reactive_call(*index))
+        .collect::<Vec<Stmt>>();
+    body.extend(functions.into_iter().map(|(_, function)| function));
+    body.extend(calls);
+
+    Script {
+        span: script.span,
+        body,
+        shebang: script.shebang.clone(),
+    }
+}
+
+/// Re-runs the `$:` statements whose dependencies changed,
+/// marking the names they assign as changed in turn.
+pub fn reactive_updates(analysis: &AnalysisResult) -> String {
+    analysis
+        .reactive_declarations
+        .iter()
+        .enumerate()
+        .filter(|(_, declaration)| !declaration.dependencies.is_empty())
+        .map(|(index, declaration)| {
+            format!(
+                r#"
+				if ({}) {{
+					$$reactive_{}();
+					changed = changed.concat({:?});
+				}}
+			"#,
+                changed_condition(&declaration.dependencies),
+                index,
+                declaration.assignments
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn ident(name: &str) -> Ident {
+    Ident::new(name.into(), DUMMY_SP)
+}
+
+fn reactive_function(index: usize, body: &Stmt) -> Stmt {
+    let body = match body {
+        Stmt::Block(block) => block.clone(),
+        stmt => BlockStmt {
+            span: DUMMY_SP,
+            stmts: vec![stmt.clone()],
+        },
+    };
+
+    Stmt::Decl(Decl::Fn(FnDecl {
+        ident: ident(&format!("$$reactive_{}", index)),
+        declare: false,
+        function: Box::new(Function {
+            params: Vec::new(),
+            decorators: Vec::new(),
+            span: DUMMY_SP,
+            body: Some(body),
+            is_generator: false,
+            is_async: false,
+            type_params: None,
+            return_type: None,
+        }),
+    }))
+}
+
+fn reactive_call(index: usize) -> Stmt {
+    Stmt::Expr(ExprStmt {
+        span: DUMMY_SP,
+        expr: Box::new(Expr::Call(CallExpr {
+            span: DUMMY_SP,
+            callee: Callee::Expr(Box::new(Expr::Ident(ident(&format!(
+                "$$reactive_{}",
+                index
+            ))))),
+            args: Vec::new(),
+            type_args: None,
+        })),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::compile::CompileOptions;
+    use crate::compiler::generate::generate;
+    use crate::compiler::parse::Parser;
+
+    #[test]
+    fn test_reactive_declarations() {
+        let source = "<script>let count = 0; $: quadrupled = doubled * 2; $: doubled = count * 2;</script>";
+        let ast = Parser::new(source).parse().unwrap();
+        let analysis = analyse(&ast).unwrap();
+        let (code, _) = generate(source, &ast, &analysis, &CompileOptions::default());
+
+        assert!(code.contains("let quadrupled, doubled;"));
+        assert!(code.contains("function $$reactive_0() {\n    doubled = count * 2;"));
+        assert!(code.contains("$$reactive_0();\n$$reactive_1();"));
+        assert!(code.contains("changed = changed.concat([\"quadrupled\"]);"));
+    }
+}
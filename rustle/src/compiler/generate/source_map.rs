@@ -0,0 +1,155 @@
+use magic_string::{DecodedMap, SourceMap};
+use swc_common::{BytePos, LineCol, Span};
+
+/// Generated statements are prefixed with the position in the source they
+/// come from, wrapped in this character. The markers are removed from the
+/// final code and turned into the mappings of the source map.
+const MARKER: char = '\u{1}';
+
+/// Returns the marker for the start of `span`, or an empty
+/// string if the span doesn't point into the source.
+pub fn mark(span: Span) -> String {
+    match span.is_dummy() {
+        true => String::new(),
+        false => format!("{}{}{}", MARKER, span.lo.0 - 1, MARKER),
+    }
+}
+
+/// Marks the statements of `entries` from index `from`, after
+/// their leading whitespace, unless they are marked already.
+pub fn mark_entries(entries: &mut [String], from: usize, span: Span) {
+    let marker = mark(span);
+    if marker.is_empty() {
+        return;
+    }
+
+    for entry in entries.iter_mut().skip(from) {
+        let index = entry.len() - entry.trim_start().len();
+        if !entry[index..].starts_with(MARKER) {
+            entry.insert_str(index, &marker);
+        }
+    }
+}
+
+/// Marks the emitted `script` at the positions recorded by the writer
+pub fn mark_script(script: &str, positions: &[(BytePos, LineCol)]) -> String {
+    let mut positions = positions
+        .iter()
+        .filter(|(pos, _)| pos.0 > 0)
+        .map(|(pos, line_col)| (line_col.line as usize, line_col.col as usize, pos.0 - 1))
+        .collect::<Vec<_>>();
+    positions.sort_by_key(|(line, col, _)| (*line, *col));
+    positions.dedup_by_key(|(line, col, _)| (*line, *col));
+
+    let mut marked = String::new();
+    let mut positions = positions.into_iter().peekable();
+    for (line, text) in script.split('\n').enumerate() {
+        if line > 0 {
+            marked.push('\n');
+        }
+        for (col, char) in text.chars().chain(std::iter::once('\0')).enumerate() {
+            while let Some((_, _, pos)) = positions.next_if(|(l, c, _)| *l == line && *c == col) {
+                marked.push_str(&format!("{}{}{}", MARKER, pos, MARKER));
+            }
+            if char != '\0' {
+                marked.push(char);
+            }
+        }
+    }
+
+    marked
+}
+
+/// Removes the markers from the generated `code` and returns it
+/// with a source map pointing the marked statements into `source`.
+pub fn extract_source_map(code: &str, source: &str) -> (String, SourceMap) {
+    let line_starts = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+        .collect::<Vec<usize>>();
+    let locate = |pos: usize| {
+        let pos = pos.min(source.len());
+        let line = line_starts.partition_point(|start| *start <= pos) - 1;
+        let column = source[line_starts[line]..pos].encode_utf16().count();
+        (line as i64, column as i64)
+    };
+
+    let mut output = String::with_capacity(code.len());
+    let mut mappings = vec![Vec::new()];
+    let mut column = 0;
+    let mut chars = code.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            MARKER => {
+                let pos = chars
+                    .by_ref()
+                    .take_while(|c| *c != MARKER)
+                    .collect::<String>()
+                    .parse::<usize>()
+                    .unwrap();
+                let (line, original_column) = locate(pos);
+                let segments = mappings.last_mut().unwrap();
+                if !segments.iter().any(|segment: &Vec<i64>| segment[0] == column) {
+                    segments.push(vec![column, 0, line, original_column]);
+                }
+            }
+            '\n' => {
+                output.push(char);
+                mappings.push(Vec::new());
+                column = 0;
+            }
+            _ => {
+                output.push(char);
+                column += char.len_utf16() as i64;
+            }
+        }
+    }
+
+    let map = SourceMap::new_from_decoded(DecodedMap {
+        file: None,
+        sources: vec![None],
+        source_root: None,
+        sources_content: vec![Some(source.to_owned())],
+        names: Vec::new(),
+        mappings,
+    })
+    .unwrap();
+
+    (output, map)
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_common::{BytePos, LineCol, Span};
+
+    use super::*;
+
+    #[test]
+    fn test_extract_source_map() {
+        let source = "<h1>\n\t{name}\n</h1>";
+        let span = Span::new(BytePos(7), BytePos(13), Default::default());
+        let code = format!("let a;\n  {}a = name;", mark(span));
+
+        let (output, map) = extract_source_map(&code, source);
+        assert_eq!(output, "let a;\n  a = name;");
+        assert_eq!(map.mappings, ";EACC");
+    }
+
+    #[test]
+    fn test_mark_script() {
+        let positions = vec![
+            (BytePos(11), LineCol { line: 0, col: 0 }),
+            (BytePos(20), LineCol { line: 1, col: 4 }),
+        ];
+        let marked = mark_script("let a;\nlet b;", &positions);
+        assert_eq!(marked, "\u{1}10\u{1}let a;\nlet \u{1}19\u{1}b;");
+    }
+
+    #[test]
+    fn test_mark_entries() {
+        let span = Span::new(BytePos(5), BytePos(6), Default::default());
+        let mut entries = vec!["a();".to_owned(), "\n  b();".to_owned()];
+        mark_entries(&mut entries, 1, span);
+        mark_entries(&mut entries, 0, span);
+        assert_eq!(entries, vec!["\u{1}4\u{1}a();", "\n  \u{1}4\u{1}b();"]);
+    }
+}
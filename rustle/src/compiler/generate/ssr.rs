@@ -0,0 +1,450 @@
+use super::{
+    attribute_value, emit, expr_to_string, instance_script, pat_to_string, reactive_script,
+    rest_props, string_literal,
+};
+use super::source_map::extract_source_map;
+use crate::compiler::analyse::{static_value, AnalysisResult};
+use crate::compiler::{
+    Fragment, RustleAst, RustleAttribute, RustleAwaitBlock, RustleConstTag, RustleEachBlock,
+    RustleElement, RustleIfBlock,
+};
+use crate::shared::is_void;
+
+/// Imports the server side helpers of the runtime, prefixed
+/// like the ones of the dom components
+const SSR_IMPORT: &str = "import { create_ssr_component as $$create_ssr_component, \
+    escape as $$escape, add_attribute as $$add_attribute, spread as $$spread } from 'rustle/internal';";
+
+/// Generates the server side rendering of the component along with a
+/// source map of its script.
+///
+/// The default export has a `render(props)` method returning the `html`
+/// of the component, its `css` and the `head` content of `<svelte:head>`.
+/// The template becomes a single template literal of escaped values,
+/// the child components render theirs with `$$render`, and the event
+/// listeners, actions and transitions are left out.
+pub fn generate_ssr(
+    source: &str,
+    ast: &RustleAst,
+    analysis: &AnalysisResult,
+    css: Option<&str>,
+) -> (String, magic_string::SourceMap) {
+    let (imports, script) = instance_script(&ast.script);
+    let imports = emit(&imports, source);
+    let script = emit(&reactive_script(&script, analysis), source);
+
+    let css = css
+        .map(|css| format!("$$result.css.add({});", string_literal(css)))
+        .unwrap_or_default();
+
+    let code = format!(
+        r#"
+	{}
+	{}
+	{}
+	const $$self = $$create_ssr_component(($$result, $$props, $$slots) => {{
+		{}
+		{}
+		{}
+		return `{}`;
+	}});
+	export default $$self;
+	"#,
+        SSR_IMPORT,
+        ast.import,
+        imports,
+        css,
+        rest_props(analysis),
+        script,
+        render_fragments(&ast.fragments, analysis)
+    );
+
+    extract_source_map(&code, source)
+}
+
+/// Renders the fragments into the content of a template literal
+fn render_fragments<'a>(
+    fragments: impl IntoIterator<Item = &'a Fragment>,
+    analysis: &AnalysisResult,
+) -> String {
+    fragments
+        .into_iter()
+        .map(|fragment| render_fragment(fragment, analysis))
+        .collect()
+}
+
+/// Renders the fragments of a block, declaring its `{@const}` tags first
+fn render_block<'a>(
+    fragments: impl IntoIterator<Item = &'a Fragment>,
+    analysis: &AnalysisResult,
+) -> String {
+    let fragments = fragments.into_iter().collect::<Vec<&Fragment>>();
+    let consts = fragments
+        .iter()
+        .filter_map(|fragment| match fragment {
+            Fragment::ConstTag(tag) => Some(tag),
+            _ => None,
+        })
+        .collect::<Vec<&RustleConstTag>>();
+
+    let content = render_fragments(fragments.iter().copied(), analysis);
+    match consts.is_empty() {
+        true => content,
+        false => format!(
+            "${{(() => {{ {} return `{}`; }})()}}",
+            consts
+                .iter()
+                .map(|tag| format!("const {} = {};", pat_to_string(&tag.pattern), expr_to_string(&tag.init)))
+                .collect::<Vec<String>>()
+                .join(" "),
+            content
+        ),
+    }
+}
+
+/// Renders a fragment into the content of a template literal, the blocks
+/// are wrapped in `<!--[-->` and `<!--]-->` comments, the end one being
+/// claimed as the anchor of the block when hydrating.
+fn render_fragment(fragment: &Fragment, analysis: &AnalysisResult) -> String {
+    match fragment {
+        Fragment::Import(_) | Fragment::Script(_) | Fragment::Style(_) => String::new(),
+        Fragment::Element(f) if f.name == "svelte:head" => format!(
+            "${{($$result.head += `{}`, '')}}",
+            render_fragments(&f.fragments, analysis)
+        ),
+        Fragment::Element(f) if f.name == "svelte:window" || f.name == "svelte:body" => {
+            String::new()
+        }
+        Fragment::Element(f) if f.this.is_some() => {
+            let this = expr_to_string(f.this.as_ref().unwrap());
+            let content = match f.is_component {
+                true => render_component(f, "$$constructor", analysis),
+                false => render_element(f, "${$$constructor}", analysis),
+            };
+            format!(
+                "<!--[-->${{(($$constructor) => $$constructor ? `{}` : '')({})}}<!--]-->",
+                content, this
+            )
+        }
+        Fragment::Element(f) if f.name == "svelte:self" => render_component(f, "$$self", analysis),
+        Fragment::Element(f) if f.is_component => render_component(f, &f.name, analysis),
+        Fragment::Element(f) if f.name == "slot" => render_slot(f, analysis),
+        Fragment::Element(f) => render_element(f, &f.name, analysis),
+        Fragment::IfBlock(f) => render_if_block(f, analysis),
+        Fragment::EachBlock(f) => render_each_block(f, analysis),
+        Fragment::AwaitBlock(f) => render_await_block(f, analysis),
+        Fragment::KeyBlock(f) => {
+            format!("<!--[-->{}<!--]-->", render_block(&f.fragments, analysis))
+        }
+        Fragment::RawHtml(f) => {
+            format!("<!--[-->${{({}) ?? ''}}<!--]-->", expr_to_string(&f.expression))
+        }
+        // only logged in the browser, declared by the enclosing block
+        Fragment::DebugTag(_) | Fragment::ConstTag(_) => String::new(),
+        Fragment::Expression(f) => format!("${{$$escape({})}}", expr_to_string(f)),
+        Fragment::Text(f) => escape_template(&f.data),
+    }
+}
+
+/// Escapes the text for a template literal
+fn escape_template(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace("${", "\\${")
+}
+
+/// Renders the element with its attributes and children, `tag`
+/// is its tag name in the template literal
+fn render_element(f: &RustleElement, tag: &str, analysis: &AnalysisResult) -> String {
+    let attributes = render_attributes(f, analysis);
+
+    // the value of a `<textarea>` is its content
+    let value = f
+        .attributes
+        .iter()
+        .find(|attr| f.name == "textarea" && (attr.name == "value" || attr.name == "bind:value"));
+    let children = match value {
+        Some(attr) => format!("${{$$escape({})}}", attribute_value(&attr.value)),
+        None => render_fragments(&f.fragments, analysis),
+    };
+
+    match is_void(&f.name) {
+        true => format!("<{}{}>", tag, attributes),
+        false => format!("<{}{}>{}</{}>", tag, attributes, children, tag),
+    }
+}
+
+/// Renders the attributes of an element, the initial values of its
+/// `bind:value` and `bind:checked` bindings included. The spreads
+/// and the `class:` and `style:` directives are merged at runtime.
+fn render_attributes(f: &RustleElement, analysis: &AnalysisResult) -> String {
+    let scope_class = analysis
+        .css
+        .as_ref()
+        .filter(|scope| scope.applies_to(f))
+        .map(|scope| scope.class.clone());
+
+    let attributes = f
+        .attributes
+        .iter()
+        .filter_map(|attr| match attr.name.strip_prefix("bind:") {
+            Some(name @ ("value" | "checked")) if f.name != "textarea" => Some((name, attr)),
+            Some(_) => None,
+            None if attr.name == "value" && f.name == "textarea" => None,
+            None if attr.name == "..." || !attr.name.contains(':') => Some((attr.name.as_str(), attr)),
+            None => None,
+        })
+        .collect::<Vec<(&str, &RustleAttribute)>>();
+    let directives = f
+        .attributes
+        .iter()
+        .filter_map(|attr| attr.name.split_once(':').map(|(directive, name)| (directive, name, attr)))
+        .filter(|(directive, _, _)| *directive == "class" || *directive == "style")
+        .collect::<Vec<_>>();
+
+    if attributes.iter().any(|(name, _)| *name == "...") || !directives.is_empty() {
+        let levels = attributes
+            .iter()
+            .map(|(name, attr)| match *name {
+                "..." => format!("...{}", expr_to_string(&attr.value)),
+                name => format!("{}: {}", string_literal(name), attribute_value(&attr.value)),
+            })
+            .collect::<Vec<String>>();
+        let mut classes = scope_class.iter().map(|class| string_literal(class)).collect::<Vec<String>>();
+        let mut styles = Vec::new();
+        for (directive, name, attr) in directives {
+            let name = name.split('|').next().unwrap_or_default();
+            match directive {
+                "class" => classes.push(format!("({}) && {}", attribute_value(&attr.value), string_literal(name))),
+                _ => styles.push(format!("{}: {}", string_literal(name), attribute_value(&attr.value))),
+            }
+        }
+        return format!(
+            "${{$$spread({}, [{}], {})}}",
+            object(&levels),
+            classes.join(", "),
+            object(&styles)
+        );
+    }
+
+    let mut rendered = String::new();
+    if let Some(class) = &scope_class {
+        if !attributes.iter().any(|(name, _)| *name == "class") {
+            rendered.push_str(&format!(" class=\"{}\"", class));
+        }
+    }
+    for (name, attr) in attributes {
+        let scoped = scope_class.as_ref().filter(|_| name == "class");
+        match (static_value(&attr.value), scoped) {
+            // a value without expressions is written as is
+            (Some(value), scoped) => {
+                let value = match scoped {
+                    Some(class) => format!("{} {}", value, class),
+                    None => value,
+                };
+                rendered.push_str(&format!(" {}=\"{}\"", name, escape_template(&escape_attribute(&value))));
+            }
+            (None, Some(class)) => rendered.push_str(&format!(
+                "${{$$add_attribute(\"class\", ({}) + ' {}')}}",
+                attribute_value(&attr.value),
+                class
+            )),
+            (None, None) => rendered.push_str(&format!(
+                "${{$$add_attribute({}, {})}}",
+                string_literal(name),
+                attribute_value(&attr.value)
+            )),
+        }
+    }
+
+    rendered
+}
+
+/// Writes the entries as an object literal
+fn object(entries: &[String]) -> String {
+    match entries.is_empty() {
+        true => "{}".into(),
+        false => format!("{{ {} }}", entries.join(", ")),
+    }
+}
+
+/// Escapes a static attribute value for a double quoted attribute
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+/// Renders a child component with `$$render`, passing its attributes as
+/// props and its children as slot functions, `let:` directives reading
+/// their slot props.
+fn render_component(f: &RustleElement, constructor: &str, analysis: &AnalysisResult) -> String {
+    let props = f
+        .attributes
+        .iter()
+        .filter_map(|attr| match attr.name.strip_prefix("bind:") {
+            Some(name) => Some((name, attr)),
+            None if !attr.name.contains(':') => Some((attr.name.as_str(), attr)),
+            None => None,
+        })
+        .map(|(name, attr)| match name {
+            "..." => format!("...{}", expr_to_string(&attr.value)),
+            name => format!("{}: {}", string_literal(name), attribute_value(&attr.value)),
+        })
+        .collect::<Vec<String>>();
+
+    let slot_name = |fragment: &Fragment| match fragment {
+        Fragment::Element(element) => element
+            .attributes
+            .iter()
+            .find(|attr| attr.name == "slot")
+            .and_then(|attr| static_value(&attr.value)),
+        _ => None,
+    };
+    let mut slots: Vec<(String, &[RustleAttribute], Vec<&Fragment>)> = Vec::new();
+    let default = f.fragments.iter().filter(|fragment| slot_name(fragment).is_none());
+    slots.push(("default".into(), &f.attributes, default.collect()));
+    for fragment in &f.fragments {
+        if let (Some(name), Fragment::Element(element)) = (slot_name(fragment), fragment) {
+            slots.push((name, &element.attributes, vec![fragment]));
+        }
+    }
+
+    let slots = slots
+        .into_iter()
+        .filter(|(_, _, fragments)| !fragments.is_empty())
+        .map(|(name, attributes, fragments)| {
+            let lets = attributes
+                .iter()
+                .filter_map(|attr| Some((attr.name.strip_prefix("let:")?, &attr.value)))
+                .map(|(key, value)| format!("{}: {}", key, expr_to_string(value)))
+                .collect::<Vec<String>>();
+            format!(
+                "{}: ({} = {{}}) => `{}`",
+                string_literal(&name),
+                object(&lets),
+                render_block(fragments, analysis)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    format!(
+        "${{{}.$$render($$result, {}, {})}}",
+        constructor,
+        object(&props),
+        object(&slots)
+    )
+}
+
+/// Renders a `<slot>` with the content the parent component passed
+/// in `$$slots`, the other attributes being its slot props, or the
+/// fallback content.
+fn render_slot(f: &RustleElement, analysis: &AnalysisResult) -> String {
+    let name = f
+        .attributes
+        .iter()
+        .find(|attr| attr.name == "name")
+        .and_then(|attr| static_value(&attr.value))
+        .unwrap_or_else(|| "default".into());
+    let props = f
+        .attributes
+        .iter()
+        .filter(|attr| attr.name != "name")
+        .map(|attr| format!("{}: {}", string_literal(&attr.name), attribute_value(&attr.value)))
+        .collect::<Vec<String>>();
+
+    format!(
+        "<!--[-->${{$$slots[{name:?}] ? $$slots[{name:?}]({props}) : `{fallback}`}}<!--]-->",
+        name = name,
+        props = object(&props),
+        fallback = render_block(&f.fragments, analysis)
+    )
+}
+
+/// Renders the branch of an `{#if}` block matching its conditions
+fn render_if_block(block: &RustleIfBlock, analysis: &AnalysisResult) -> String {
+    let mut branches = Vec::new();
+    let mut next = Some(block);
+    let mut otherwise = String::new();
+    while let Some(block) = next.take() {
+        branches.push(format!(
+            "({}) ? `{}` : ",
+            expr_to_string(&block.expression),
+            render_block(&block.fragments, analysis)
+        ));
+        match block.else_fragments.as_deref() {
+            Some([Fragment::IfBlock(else_if)]) => next = Some(else_if),
+            Some(fragments) => otherwise = render_block(fragments, analysis),
+            None => (),
+        }
+    }
+
+    format!("<!--[-->${{{}`{}`}}<!--]-->", branches.join(""), otherwise)
+}
+
+/// Renders an `{#each}` block item by item, or its `{:else}` fragments
+/// for an empty list
+fn render_each_block(block: &RustleEachBlock, analysis: &AnalysisResult) -> String {
+    let index = block.index.clone().unwrap_or_else(|| "$$index".into());
+    let otherwise = block
+        .else_fragments
+        .as_ref()
+        .map(|fragments| render_block(fragments, analysis))
+        .unwrap_or_default();
+
+    format!(
+        "<!--[-->${{(($$items) => $$items.length ? $$items.map(({}, {}) => `{}`).join('') : `{}`)(Array.from({}))}}<!--]-->",
+        pat_to_string(&block.context),
+        index,
+        render_block(&block.fragments, analysis),
+        otherwise,
+        expr_to_string(&block.expression)
+    )
+}
+
+/// Renders the pending branch of an `{#await}` block for a promise,
+/// the `then` branch with the value otherwise
+fn render_await_block(block: &RustleAwaitBlock, analysis: &AnalysisResult) -> String {
+    let then = match (&block.then_fragments, &block.value) {
+        (Some(fragments), Some(value)) => format!(
+            "(({}) => `{}`)($$value)",
+            pat_to_string(value),
+            render_block(fragments, analysis)
+        ),
+        (Some(fragments), None) => format!("`{}`", render_block(fragments, analysis)),
+        (None, _) => "''".to_owned(),
+    };
+    format!(
+        "<!--[-->${{(($$value) => $$value && typeof $$value.then === 'function' ? `{}` : {})({})}}<!--]-->",
+        render_block(&block.pending_fragments, analysis),
+        then,
+        expr_to_string(&block.expression)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::analyse::analyse;
+    use crate::compiler::parse::Parser;
+
+    use super::generate_ssr;
+
+    #[test]
+    fn test_generate_ssr() {
+        let source = "<script>export let name = 'world'; let items = ['a', 'b'];</script>
+            <h1 class=\"title\" on:click={() => name = ''}>Hello {name}!</h1>
+            {#each items as item, i}<p>{i}: {item}</p>{:else}<p>empty</p>{/each}
+            {#if name}<Nested {name} />{/if}";
+        let ast = Parser::new(source).parse().unwrap();
+        let analysis = analyse(&ast).unwrap();
+        let (code, _) = generate_ssr(source, &ast, &analysis, None);
+
+        assert!(code.contains("const $$self = $$create_ssr_component(($$result, $$props, $$slots) => {"));
+        assert!(code.contains("} = $$props;"));
+        assert!(code.contains("<h1 class=\"title\">Hello ${$$escape(name)}!</h1>"));
+        assert!(code.contains("$$items.map((item, i) => `<p>${$$escape(i)}: ${$$escape(item)}</p>`)"));
+        assert!(code.contains("${(name) ? `${Nested.$$render($$result, { \"name\": name }, {})}` : ``}"));
+        assert!(!code.contains("click"));
+    }
+}
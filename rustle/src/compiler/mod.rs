@@ -1,9 +1,9 @@
 pub mod analyse;
 pub mod ast;
-//pub mod compile;
+pub mod compile;
 //pub mod interfaces;
 //pub mod node;
 pub mod parse;
-//pub mod utils;
+pub mod utils;
 pub mod generate;
 pub use ast::*;
@@ -18,6 +18,13 @@ impl Error {
         Error::new("css-syntax-error", message)
     }
 
+    pub fn duplicate_animation() -> Error {
+        Error::new(
+            "duplicate-animation",
+            "An element can only have one 'animate' directive",
+        )
+    }
+
     pub fn duplicate_attribute() -> Error {
         Error::new("duplicate-attribute", "Attributes need to be unique")
     }
@@ -29,6 +36,18 @@ impl Error {
         )
     }
 
+    pub fn duplicate_transition(first: &str, second: &str) -> Error {
+        let message = if first == second {
+            format!("An element can only have one '{}' directive", first)
+        } else {
+            format!(
+                "An element cannot have both '{}' directive and '{}' directive",
+                first, second
+            )
+        };
+        Error::new("duplicate-transition", &message)
+    }
+
     pub fn duplicate_style() -> Error {
         Error::new(
             "duplicate-style",
@@ -55,13 +74,38 @@ impl Error {
     }
 
     pub fn expected_block_type() -> Error {
-        Error::new("expected-block-type", "Expected if, each or await")
+        Error::new("expected-block-type", "Expected if, each, await or key")
+    }
+
+    pub fn expected_tag_type() -> Error {
+        Error::new("expected-tag-type", "Expected html, debug or const")
     }
 
     pub fn expected_name() -> Error {
         Error::new("expected-name", "Expected name")
     }
 
+    pub fn invalid_action() -> Error {
+        Error::new(
+            "invalid-action",
+            "Actions can only be applied to DOM elements, not components",
+        )
+    }
+
+    pub fn invalid_animation() -> Error {
+        Error::new(
+            "invalid-animation",
+            "Animations can only be applied to DOM elements, not components",
+        )
+    }
+
+    pub fn invalid_binding_value() -> Error {
+        Error::new(
+            "invalid-directive-value",
+            "Can only bind to an identifier (e.g. `{foo}`) or a member expression (e.g. `{foo.bar}` or `{foo[baz]}`)",
+        )
+    }
+
     // TODO: block is of type any - &str is a placeholder
     pub fn invalid_catch_placement_unclosed_block(block: &str) -> Error {
         Error::new(
@@ -73,7 +117,7 @@ impl Error {
     pub fn invalid_catch_placement_without_await() -> Error {
         Error::new(
             "invalid-catch-placement",
-            "Cannot have an {{:catch}} block outside an {{#await ...}} block",
+            "Cannot have an {:catch} block outside an {#await ...} block",
         )
     }
 
@@ -104,6 +148,17 @@ impl Error {
         )
     }
 
+    pub fn invalid_const_args() -> Error {
+        Error::new("invalid-const-args", "{@const ...} must be an assignment")
+    }
+
+    pub fn invalid_custom_element_attribute() -> Error {
+        Error::new(
+            "invalid-customElement-attribute",
+            "'customElement' must be a string literal defining a valid custom element name",
+        )
+    }
+
     pub fn invalid_debug_args() -> Error {
         Error::new(
             "invalid-debug-args",
@@ -175,6 +230,34 @@ impl Error {
         )
     }
 
+    pub fn invalid_event_modifier(modifiers: &[&str]) -> Error {
+        Error::new(
+            "invalid-event-modifier",
+            &format!("Valid event modifiers are {}", list!(modifiers)),
+        )
+    }
+
+    pub fn invalid_event_modifier_combination(first: &str, second: &str) -> Error {
+        Error::new(
+            "invalid-event-modifier",
+            &format!("The '{}' and '{}' modifiers cannot be used together", first, second),
+        )
+    }
+
+    pub fn invalid_event_modifier_component() -> Error {
+        Error::new(
+            "invalid-event-modifier",
+            "Event modifiers other than 'once' can only be used on DOM elements",
+        )
+    }
+
+    pub fn invalid_options_attribute_unknown(name: &str) -> Error {
+        Error::new(
+            "invalid-options-attribute",
+            &format!("<svelte:options> unknown attribute '{}'", name),
+        )
+    }
+
     pub fn invalid_ref_directive(name: &str) -> Error {
         Error::new(
             "invalid-ref-directive",
@@ -224,12 +307,19 @@ impl Error {
         )
     }
 
+    pub fn invalid_style_directive_modifier() -> Error {
+        Error::new(
+            "invalid-style-directive-modifier",
+            "Valid modifiers for style directives are: important",
+        )
+    }
+
     pub fn invalid_tag_name() -> Error {
         Error::new("invalid-tag-name", "Expected valid tag name")
     }
 
     pub fn invalid_tag_name_svelte_element(tags: &[&str], match_str: &str) -> Error {
-        if match_str.len() > 0 {
+        if !match_str.is_empty() {
             Error::new(
                 "invalid-tag-name",
                 &format!(
@@ -246,6 +336,34 @@ impl Error {
         }
     }
 
+    pub fn invalid_tag_property() -> Error {
+        Error::new(
+            "invalid-tag-property",
+            "tag name must be two or more words joined by the '-' character",
+        )
+    }
+
+    pub fn invalid_transition() -> Error {
+        Error::new(
+            "invalid-transition",
+            "Transitions can only be applied to DOM elements, not components",
+        )
+    }
+
+    pub fn invalid_transition_modifier() -> Error {
+        Error::new(
+            "invalid-transition-modifier",
+            "Valid modifiers for transitions are: local, global",
+        )
+    }
+
+    pub fn invalid_transition_modifier_combination() -> Error {
+        Error::new(
+            "invalid-transition-modifier",
+            "The 'local' and 'global' modifiers cannot be used together",
+        )
+    }
+
     pub fn invalid_then_placement_unclosed_block(block: &str) -> Error {
         Error::new(
             "invalid-then-placement",
@@ -291,6 +409,18 @@ impl Error {
         )
     }
 
+    pub fn parse_error(message: &str) -> Error {
+        Error::new("parse-error", message)
+    }
+
+    pub fn unclosed_block() -> Error {
+        Error::new("unclosed-block", "Block was left open")
+    }
+
+    pub fn unclosed_element(name: &str) -> Error {
+        Error::new("unclosed-element", &format!("<{}> was left open", name))
+    }
+
     pub fn unclosed_script() -> Error {
         Error::new("unclosed-script", "<script> must have a closing tag")
     }
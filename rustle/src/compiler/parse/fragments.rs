@@ -1,21 +1,52 @@
-use crate::compiler::{Fragment, RustleAttribute, RustleElement};
+use crate::compiler::utils::CompileError;
+use crate::compiler::{
+    Fragment, RustleAction, RustleAttribute, RustleAwaitBlock, RustleConstTag, RustleDebugTag,
+    RustleEachBlock, RustleElement, RustleEventHandler, RustleIfBlock, RustleKeyBlock, RustleRawHtml, RustleStyle,
+    RustleTransition,
+};
 
+use super::errors::Error;
 use super::parser::Parser;
-use super::swc_helpers::{parse_expression_at, swc_parse};
+use super::swc_helpers::{parse_expression_at, swc_css_parse, swc_parse, swc_parse_pattern};
 
 use lazy_static::lazy_static;
 use regex::Regex;
 use swc_common::Span;
-use swc_ecma_ast::{Expr, Script};
+use swc_ecma_ast::{AssignOp, Expr, Ident, Module, Pat, PatOrExpr};
 use swc_html_ast::Text;
 
 lazy_static! {
     static ref COMPONENT_TAG_PREFIX: Regex = Regex::new("[A-Z]").unwrap();
-    static ref ELEMENT_TAG_NAME: Regex = Regex::new("[a-z]").unwrap();
-    static ref ATTRIBUTE_NAME: Regex = Regex::new("[^=]").unwrap();
+    static ref ELEMENT_TAG_NAME: Regex = Regex::new("[a-zA-Z0-9-]").unwrap();
+    static ref ATTRIBUTE_NAME: Regex = Regex::new("[^=\\s/>]").unwrap();
     static ref READ_TEXT: Regex = Regex::new("[^<{]").unwrap();
+    static ref CLOSING_TAG_NAME: Regex = Regex::new("[^>]").unwrap();
+    static ref ATTRIBUTE_STRING_VALUE: Regex = Regex::new("[^\"]").unwrap();
+    static ref IDENTIFIER: Regex = Regex::new("[a-zA-Z0-9_$]").unwrap();
 }
 
+const SVELTE_TAGS: [&str; 7] = [
+    "svelte:head",
+    "svelte:options",
+    "svelte:window",
+    "svelte:body",
+    "svelte:element",
+    "svelte:self",
+    "svelte:component",
+];
+
+const EVENT_MODIFIERS: [&str; 9] = [
+    "preventDefault",
+    "stopPropagation",
+    "stopImmediatePropagation",
+    "capture",
+    "once",
+    "passive",
+    "nonpassive",
+    "self",
+    "trusted",
+];
+
 /// Parses fragments given an end condition.
 ///
 /// # Arguments
@@ -24,15 +55,15 @@ lazy_static! {
 pub fn parse_fragments<F: Fn(&mut Parser) -> bool>(
     parser: &mut Parser,
     condition: F,
-) -> Vec<Fragment> {
+) -> Result<Vec<Fragment>, Box<CompileError>> {
     let mut fragments = Vec::new();
     while condition(parser) {
-        if let Some(fragment) = parse_fragment(parser) {
+        if let Some(fragment) = parse_fragment(parser)? {
             fragments.push(fragment);
         }
     }
 
-    fragments
+    Ok(fragments)
 }
 
 /// Parses a fragment given a parser struct. If it can't parse
@@ -40,65 +71,122 @@ pub fn parse_fragments<F: Fn(&mut Parser) -> bool>(
 ///
 /// # Arguments
 /// * `parser` - The `parser` struct containing the content to parse.
-pub fn parse_fragment(parser: &mut Parser) -> Option<Fragment> {
-    if let Some(str) = parse_import(parser) {
-        return Some(Fragment::Import(str));
+pub fn parse_fragment(parser: &mut Parser) -> Result<Option<Fragment>, Box<CompileError>> {
+    if let Some(str) = parse_import(parser)? {
+        return Ok(Some(Fragment::Import(str)));
+    }
+
+    if let Some(script) = parse_script(parser)? {
+        return Ok(Some(Fragment::Script(script)));
+    }
+
+    if let Some(style) = parse_style(parser)? {
+        return Ok(Some(Fragment::Style(style)));
+    }
+
+    if let Some(element) = parse_element(parser)? {
+        return Ok(Some(Fragment::Element(element)));
     }
 
-    if let Some(script) = parse_script(parser) {
-        return Some(Fragment::Script(script));
+    if let Some(block) = parse_block(parser)? {
+        return Ok(Some(block));
     }
 
-    if let Some(element) = parse_element(parser) {
-        return Some(Fragment::Element(element));
+    if parser.match_str("{:") || parser.match_str("{/") {
+        return Err(unexpected_block_tag(parser, None));
     }
 
-    if let Some(expression) = parse_expression(parser) {
-        return Some(Fragment::Expression(expression));
+    if let Some(tag) = parse_tag(parser)? {
+        return Ok(Some(tag));
+    }
+
+    if let Some(expression) = parse_expression(parser)? {
+        return Ok(Some(Fragment::Expression(expression)));
     }
 
     if let Some(text) = parse_text(parser) {
-        return Some(Fragment::Text(text));
+        return Ok(Some(Fragment::Text(text)));
     }
 
-    None
+    Ok(None)
 }
 
-fn parse_import(parser: &mut Parser) -> Option<String> {
+fn parse_import(parser: &mut Parser) -> Result<Option<String>, Box<CompileError>> {
     if parser.match_str("import") {
         let start_index = parser.index;
-        let end_index = parser.content.find("<script>").unwrap();
-        let code = parser.content.get(start_index..end_index).unwrap();
+        let end_index = match parser.rest().find("<script>") {
+            Some(offset) => start_index + offset,
+            None => return Err(parser.error(Error::unexpected_token("<script>"), None)),
+        };
+        let code = parser.content.get(start_index..end_index).unwrap_or_default();
+        let code = String::from(code);
         parser.index = end_index;
 
-        return Some(String::from(code));
+        return Ok(Some(code));
     }
 
-    None
+    Ok(None)
 }
 
 /// Checks if the index starts at a `<script>` tag and parses
 /// the content between it and a `</script>` tag using SWC
-/// and returns a `swc_ecma_ast::Script`.
+/// and returns a `swc_ecma_ast::Module`.
 ///
 /// Sets the `parser.index` to the end of the closing `</script>` tag.
 ///
 /// Returns `None` if the current index doesn't start at a `<script>` tag.
-fn parse_script(parser: &mut Parser) -> Option<Script> {
+fn parse_script(parser: &mut Parser) -> Result<Option<Module>, Box<CompileError>> {
     if parser.match_str("<script>") {
-        parser.eat("<script>");
+        let tag_index = parser.index;
+        parser.eat("<script>")?;
         let start_index = parser.index;
-        let end_index = parser.content.find("</script>").unwrap();
-        let code = parser.content.get(start_index..end_index).unwrap();
-        let script = swc_parse(code);
+        let end_index = match parser.rest().find("</script>") {
+            Some(offset) => start_index + offset,
+            None => return Err(parser.error(Error::unclosed_script(), Some(tag_index))),
+        };
+        let code = parser.content.get(start_index..end_index).unwrap_or_default();
+        let script = swc_parse(parser, code, start_index)?;
 
         parser.index = end_index;
-        parser.eat("</script>");
+        parser.eat("</script>")?;
 
-        return Some(script);
+        return Ok(Some(script));
     }
 
-    None
+    Ok(None)
+}
+
+/// Checks if the index starts at a `<style>` tag and parses
+/// the content between it and a `</style>` tag using SWC
+/// and returns a `RustleStyle`.
+///
+/// Sets the `parser.index` to the end of the closing `</style>` tag.
+///
+/// Returns `None` if the current index doesn't start at a `<style>` tag.
+fn parse_style(parser: &mut Parser) -> Result<Option<RustleStyle>, Box<CompileError>> {
+    if parser.match_str("<style>") {
+        let tag_index = parser.index;
+        parser.eat("<style>")?;
+        let start_index = parser.index;
+        let end_index = match parser.rest().find("</style>") {
+            Some(offset) => start_index + offset,
+            None => return Err(parser.error(Error::unclosed_style(), Some(tag_index))),
+        };
+        let content = parser.content.get(start_index..end_index).unwrap_or_default();
+        let content = String::from(content);
+        let stylesheet = swc_css_parse(parser, &content, start_index)?;
+
+        parser.index = end_index;
+        parser.eat("</style>")?;
+
+        return Ok(Some(RustleStyle {
+            content,
+            start: start_index,
+            stylesheet,
+        }));
+    }
+
+    Ok(None)
 }
 
 /// Checks if the index starts at an opening `<` tag
@@ -110,78 +198,663 @@ fn parse_script(parser: &mut Parser) -> Option<Script> {
 /// Also parses recursively into elements like `<div><ul><li></li></ul></div>.
 ///
 /// Sets the `parser.index` to the ending of the element.
-fn parse_element(parser: &mut Parser) -> Option<RustleElement> {
+fn parse_element(parser: &mut Parser) -> Result<Option<RustleElement>, Box<CompileError>> {
     if parser.match_str("<") {
-        parser.eat("<");
+        let start_index = parser.index;
+        parser.eat("<")?;
 
         if parser.match_str("!--") {
-            parser.eat("!--");
-            while !Regex::new(r"-->").unwrap().is_match(&parser.content[parser.index..parser.index+3]) {
-                parser.index += 1;
+            parser.eat("!--")?;
+            match parser.rest().find("-->") {
+                Some(offset) => parser.index += offset,
+                None => return Err(parser.error(Error::unclosed_comment(), Some(start_index))),
             }
-            parser.eat("-->");
-            return None;
+            parser.eat("-->")?;
+            return Ok(None);
+        }
+
+        if parser.match_str("/") {
+            parser.eat("/")?;
+            let name = parser.read_while_matching(&CLOSING_TAG_NAME);
+            return Err(parser.error(
+                Error::invalid_closing_tag_unopened(&name),
+                Some(start_index),
+            ));
         }
 
         let prefix = parser.read_while_matching(&COMPONENT_TAG_PREFIX);
         let tag_name = parser.read_while_matching(&ELEMENT_TAG_NAME);
-        let tag_name = format!("{}{}", prefix, tag_name);
-        let attributes = parse_attribute_list(parser);
-        let is_component = prefix.len() > 0;
+        let mut tag_name = format!("{}{}", prefix, tag_name);
+        if tag_name.is_empty() {
+            return Err(parser.error(Error::invalid_tag_name(), None));
+        }
+        if tag_name == "svelte" && parser.match_str(":") {
+            parser.eat(":")?;
+            tag_name = format!("svelte:{}", parser.read_while_matching(&ELEMENT_TAG_NAME));
+            check_svelte_tag(parser, &tag_name, start_index)?;
+        }
+        let is_component =
+            !prefix.is_empty() || tag_name == "svelte:self" || tag_name == "svelte:component";
+        let AttributeList {
+            mut attributes,
+            handlers,
+            actions,
+            transitions,
+            animation,
+        } = parse_attribute_list(parser, is_component)?;
+        let this = parse_this(parser, &tag_name, &mut attributes, start_index)?;
 
         if parser.match_str("/>") {
-            parser.eat("/>");
+            parser.eat("/>")?;
 
             let element = Some(RustleElement {
+                span: parser.span(start_index, parser.index),
                 name: tag_name,
                 is_component,
-                attributes: attributes,
+                this,
+                attributes,
+                handlers,
+                actions,
+                transitions,
+                animation,
                 fragments: vec![],
             });
-            return element;
+            return Ok(element);
         }
 
-        parser.eat(">");
+        parser.eat(">")?;
+        let content_index = parser.index;
 
         let end_tag = format!("</{}>", tag_name);
 
+        parser.stack.push(tag_name.clone());
+        let fragments = parse_fragments(parser, |parser| {
+            parser.index < parser.content.len()
+                && !parser.match_str(&end_tag)
+                && !parser.match_str("{:")
+                && !parser.match_str("{/")
+        })?;
+        parser.stack.pop();
+
+        // `<svelte:window>` and `<svelte:body>` only listen and bind
+        let has_content = fragments
+            .iter()
+            .any(|fragment| !matches!(fragment, Fragment::Text(text) if text.data.trim().is_empty()));
+        let empty = ["svelte:window", "svelte:body", "svelte:options"];
+        if has_content && empty.contains(&tag_name.as_str()) {
+            let slug = tag_name.trim_start_matches("svelte:");
+            return Err(parser.error(
+                Error::invalid_element_content(slug, &tag_name),
+                Some(content_index),
+            ));
+        }
+
+        if parser.match_str("{:") || parser.match_str("{/") {
+            return Err(unexpected_block_tag(parser, Some(&format!("<{}>", tag_name))));
+        }
+
+        if !parser.match_str(&end_tag) {
+            return Err(parser.error(Error::unclosed_element(&tag_name), Some(start_index)));
+        }
+
+        parser.eat(end_tag.as_str())?;
+
         let element = Some(RustleElement {
+            span: parser.span(start_index, parser.index),
             name: tag_name,
             is_component,
-            attributes: attributes,
-            fragments: parse_fragments(parser, |parser| !parser.match_str(&end_tag)),
+            this,
+            attributes,
+            handlers,
+            actions,
+            transitions,
+            animation,
+            fragments,
         });
+        return Ok(element);
+    }
+
+    Ok(None)
+}
 
-        parser.eat(end_tag.as_str());
-        return element;
+/// Checks that a `<svelte:...>` tag exists and is placed where it can be,
+/// the meta tags at the top level only and once per component.
+fn check_svelte_tag(parser: &mut Parser, tag_name: &str, start_index: usize) -> Result<(), Box<CompileError>> {
+    if !SVELTE_TAGS.contains(&tag_name) {
+        return Err(parser.error(
+            Error::invalid_tag_name_svelte_element(&SVELTE_TAGS, ""),
+            Some(start_index + 1),
+        ));
     }
 
-    None
+    match tag_name {
+        "svelte:head" | "svelte:window" | "svelte:body" | "svelte:options" => {
+            let slug = tag_name.trim_start_matches("svelte:");
+            if !parser.stack.is_empty() {
+                return Err(parser.error(
+                    Error::invalid_element_placement(slug, tag_name),
+                    Some(start_index),
+                ));
+            }
+            if parser.meta_tags.iter().any(|name| name == tag_name) {
+                return Err(parser.error(
+                    Error::duplicate_element(slug, tag_name),
+                    Some(start_index),
+                ));
+            }
+            parser.meta_tags.push(tag_name.to_owned());
+        }
+        // a component rendering itself unconditionally would never end
+        "svelte:self" => {
+            let conditional = parser.stack.iter().any(|name| {
+                name == "if"
+                    || name == "each"
+                    || name == "svelte:self"
+                    || name == "svelte:component"
+                    || COMPONENT_TAG_PREFIX.is_match(&name[..1])
+            });
+            if !conditional {
+                return Err(parser.error(Error::invalid_self_placement(), Some(start_index)));
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Takes the `this` attribute out of `<svelte:component>` and
+/// `<svelte:element>`, the component must be an expression while
+/// the element can also be a static tag name.
+fn parse_this(
+    parser: &Parser,
+    tag_name: &str,
+    attributes: &mut Vec<RustleAttribute>,
+    start_index: usize,
+) -> Result<Option<Expr>, Box<CompileError>> {
+    if tag_name != "svelte:component" && tag_name != "svelte:element" {
+        return Ok(None);
+    }
+
+    let this = match attributes.iter().position(|attr| attr.name == "this") {
+        Some(index) => attributes.remove(index).value,
+        None if tag_name == "svelte:component" => {
+            return Err(parser.error(Error::missing_component_definition(), Some(start_index)))
+        }
+        None => return Err(parser.error(Error::missing_element_definition(), Some(start_index))),
+    };
+    // quoted values are kept as template strings
+    if tag_name == "svelte:component" && matches!(this, Expr::Lit(_) | Expr::Tpl(_)) {
+        return Err(parser.error(Error::invalid_component_definition(), Some(start_index)));
+    }
+
+    Ok(Some(this))
+}
+
+/// Checks if the index is at a block opening `{#` and parses the block
+/// untill its closing tag, for example `{#if visible}...{/if}`.
+///
+/// Sets the `parser.index` to the end of the closing tag.
+fn parse_block(parser: &mut Parser) -> Result<Option<Fragment>, Box<CompileError>> {
+    if !parser.match_str("{#") {
+        return Ok(None);
+    }
+
+    let start_index = parser.index;
+    parser.eat("{#")?;
+
+    if parser.match_str("if") {
+        parser.eat("if")?;
+        parser.stack.push("if".into());
+        let block = parse_if_block(parser, start_index)?;
+        parser.stack.pop();
+        return Ok(Some(Fragment::IfBlock(block)));
+    }
+
+    if parser.match_str("each") {
+        parser.eat("each")?;
+        parser.stack.push("each".into());
+        let block = parse_each_block(parser, start_index)?;
+        parser.stack.pop();
+        return Ok(Some(Fragment::EachBlock(block)));
+    }
+
+    if parser.match_str("await") {
+        parser.eat("await")?;
+        parser.stack.push("await".into());
+        let block = parse_await_block(parser, start_index)?;
+        parser.stack.pop();
+        return Ok(Some(Fragment::AwaitBlock(block)));
+    }
+
+    if parser.match_str("key") {
+        parser.eat("key")?;
+        parser.stack.push("key".into());
+        let block = parse_key_block(parser, start_index)?;
+        parser.stack.pop();
+        return Ok(Some(Fragment::KeyBlock(block)));
+    }
+
+    Err(parser.error(Error::expected_block_type(), None))
+}
+
+/// Parses the `{#key}` block after the `key` keyword
+/// untill the closing `{/key}`.
+fn parse_key_block(parser: &mut Parser, start_index: usize) -> Result<RustleKeyBlock, Box<CompileError>> {
+    parser.skip_whitespace();
+    let expression = parse_javascript(parser)?;
+    parser.skip_whitespace();
+    parser.eat("}")?;
+
+    let fragments = parse_block_fragments(parser, start_index)?;
+
+    if parser.match_str("{:") {
+        return Err(unexpected_block_tag(parser, None));
+    }
+    parser.eat("{/key}")?;
+
+    Ok(RustleKeyBlock {
+        span: parser.span(start_index, parser.index),
+        expression,
+        fragments,
+    })
+}
+
+/// Parses the `{#if}` block after the `if` keyword, including its
+/// `{:else if}` and `{:else}` branches and the closing `{/if}`.
+fn parse_if_block(parser: &mut Parser, start_index: usize) -> Result<RustleIfBlock, Box<CompileError>> {
+    parser.skip_whitespace();
+    let expression = parse_javascript(parser)?;
+    parser.skip_whitespace();
+    parser.eat("}")?;
+
+    let fragments = parse_block_fragments(parser, start_index)?;
+
+    let else_fragments = if parser.match_str("{:else") {
+        let else_index = parser.index;
+        parser.eat("{:else")?;
+        parser.skip_whitespace();
+
+        if parser.match_str("if") {
+            parser.eat("if")?;
+            let block = parse_if_block(parser, else_index)?;
+            return Ok(RustleIfBlock {
+                span: parser.span(start_index, parser.index),
+                expression,
+                fragments,
+                else_fragments: Some(vec![Fragment::IfBlock(block)]),
+            });
+        }
+
+        if parser.match_str("elseif") {
+            return Err(parser.error(Error::invalid_elseif(), Some(else_index)));
+        }
+
+        parser.eat("}")?;
+        Some(parse_block_fragments(parser, else_index)?)
+    } else {
+        None
+    };
+
+    if parser.match_str("{:") {
+        return Err(unexpected_block_tag(parser, None));
+    }
+    parser.eat("{/if}")?;
+
+    Ok(RustleIfBlock {
+        span: parser.span(start_index, parser.index),
+        expression,
+        fragments,
+        else_fragments,
+    })
+}
+
+/// Parses the `{#each}` block after the `each` keyword,
+/// `{#each items as item, index (key)}`, including its `{:else}`
+/// branch and the closing `{/each}`.
+fn parse_each_block(
+    parser: &mut Parser,
+    start_index: usize,
+) -> Result<RustleEachBlock, Box<CompileError>> {
+    parser.skip_whitespace();
+    let expression = parse_javascript(parser)?;
+    parser.skip_whitespace();
+    parser.eat("as")?;
+    parser.skip_whitespace();
+
+    let context_index = parser.index;
+    let context_source = read_context(parser);
+    if context_source.is_empty() {
+        return Err(parser.error(Error::unexpected_token_destructure(), None));
+    }
+    let context = swc_parse_pattern(parser, &context_source, context_index)?;
+    parser.skip_whitespace();
+
+    let index = if parser.match_str(",") {
+        parser.eat(",")?;
+        parser.skip_whitespace();
+        let name = parser.read_while_matching(&IDENTIFIER);
+        if name.is_empty() {
+            return Err(parser.error(Error::expected_name(), None));
+        }
+        parser.skip_whitespace();
+        Some(name)
+    } else {
+        None
+    };
+
+    let key = if parser.match_str("(") {
+        parser.eat("(")?;
+        parser.skip_whitespace();
+        let key = parse_javascript(parser)?;
+        parser.skip_whitespace();
+        parser.eat(")")?;
+        parser.skip_whitespace();
+        Some(key)
+    } else {
+        None
+    };
+    parser.eat("}")?;
+
+    let fragments = parse_block_fragments(parser, start_index)?;
+
+    let else_fragments = if parser.match_str("{:else") {
+        let else_index = parser.index;
+        parser.eat("{:else")?;
+        parser.skip_whitespace();
+        parser.eat("}")?;
+        Some(parse_block_fragments(parser, else_index)?)
+    } else {
+        None
+    };
+
+    if parser.match_str("{:") {
+        return Err(unexpected_block_tag(parser, None));
+    }
+    parser.eat("{/each}")?;
+
+    Ok(RustleEachBlock {
+        span: parser.span(start_index, parser.index),
+        expression,
+        context,
+        index,
+        key,
+        fragments,
+        else_fragments,
+    })
+}
+
+/// Parses the `{#await}` block after the `await` keyword, including its
+/// `{:then}` and `{:catch}` branches and the closing `{/await}`.
+///
+/// `{#await promise then value}` and `{#await promise catch error}`
+/// start directly with the `then` or `catch` branch.
+fn parse_await_block(
+    parser: &mut Parser,
+    start_index: usize,
+) -> Result<RustleAwaitBlock, Box<CompileError>> {
+    parser.skip_whitespace();
+    let expression = parse_javascript(parser)?;
+    parser.skip_whitespace();
+
+    let mut block = RustleAwaitBlock {
+        span: Span::default(),
+        expression,
+        value: None,
+        error: None,
+        pending_fragments: Vec::new(),
+        then_fragments: None,
+        catch_fragments: None,
+    };
+
+    // `None` for the pending branch, otherwise `then` or `catch`
+    let mut branch = None;
+    if parser.match_str("then") || parser.match_str("catch") {
+        let keyword = if parser.match_str("then") { "then" } else { "catch" };
+        parser.eat(keyword)?;
+        let pattern = parse_await_pattern(parser)?;
+        branch = Some(keyword);
+        match keyword {
+            "then" => block.value = pattern,
+            _ => block.error = pattern,
+        }
+    }
+    parser.eat("}")?;
+
+    let mut branch_index = start_index;
+    loop {
+        let fragments = parse_block_fragments(parser, branch_index)?;
+        match branch {
+            None => block.pending_fragments = fragments,
+            Some("then") => block.then_fragments = Some(fragments),
+            Some(_) => block.catch_fragments = Some(fragments),
+        }
+
+        branch_index = parser.index;
+        if parser.match_str("{:then") {
+            if let Some(branch) = branch {
+                return Err(parser.error(
+                    Error::invalid_then_placement_unclosed_block(&format!("{{:{}}}", branch)),
+                    None,
+                ));
+            }
+            parser.eat("{:then")?;
+            block.value = parse_await_pattern(parser)?;
+            parser.eat("}")?;
+            branch = Some("then");
+        } else if parser.match_str("{:catch") {
+            if branch == Some("catch") {
+                return Err(parser.error(
+                    Error::invalid_catch_placement_unclosed_block("{:catch}"),
+                    None,
+                ));
+            }
+            parser.eat("{:catch")?;
+            block.error = parse_await_pattern(parser)?;
+            parser.eat("}")?;
+            branch = Some("catch");
+        } else if parser.match_str("{:") {
+            return Err(unexpected_block_tag(parser, None));
+        } else {
+            parser.eat("{/await}")?;
+            block.span = parser.span(start_index, parser.index);
+            return Ok(block);
+        }
+    }
+}
+
+/// Parses the optional value or error pattern of a `then` or `catch`
+/// branch untill the closing `}`, like `{:then value}` or `{:catch}`
+fn parse_await_pattern(parser: &mut Parser) -> Result<Option<Pat>, Box<CompileError>> {
+    parser.skip_whitespace();
+    let pattern_index = parser.index;
+    let pattern_source = read_context(parser);
+    parser.skip_whitespace();
+
+    if pattern_source.is_empty() {
+        return Ok(None);
+    }
+
+    swc_parse_pattern(parser, &pattern_source, pattern_index).map(Some)
+}
+
+/// Reads the context of an `{#each}` block untill the `,`, `(` or `}`
+/// following it, skipping over destructuring brackets like `{ id, name }`.
+fn read_context(parser: &mut Parser) -> String {
+    let mut depth = 0usize;
+    let mut context = String::new();
+
+    while let Some(char) = parser.rest().chars().next() {
+        match char {
+            ',' | '(' | '}' if depth == 0 => break,
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        context.push(char);
+        parser.index += char.len_utf8();
+    }
+
+    context.trim_end().to_owned()
+}
+
+/// Parses the fragments of a block branch untill the next
+/// `{:...}` or `{/...}` tag.
+fn parse_block_fragments(
+    parser: &mut Parser,
+    start_index: usize,
+) -> Result<Vec<Fragment>, Box<CompileError>> {
+    let fragments = parse_fragments(parser, |parser| {
+        parser.index < parser.content.len()
+            && !parser.match_str("{:")
+            && !parser.match_str("{/")
+    })?;
+
+    if parser.index >= parser.content.len() {
+        return Err(parser.error(Error::unclosed_block(), Some(start_index)));
+    }
+
+    Ok(fragments)
+}
+
+/// Creates the error for a `{:...}` or `{/...}` tag found where no
+/// block can continue, optionally inside an unclosed `block` like `<div>`.
+fn unexpected_block_tag(parser: &Parser, block: Option<&str>) -> Box<CompileError> {
+    let error = if parser.match_str("{:else if") {
+        match block {
+            Some(block) => Error::invalid_elseif_placement_unclosed_block(block),
+            None => Error::invalid_elseif_placement_outside_if(),
+        }
+    } else if parser.match_str("{:else") {
+        match block {
+            Some(block) => Error::invalid_else_placement_unclosed_block(block),
+            None => Error::invalid_else_placement_outside_if(),
+        }
+    } else if parser.match_str("{:then") {
+        match block {
+            Some(block) => Error::invalid_then_placement_unclosed_block(block),
+            None => Error::invalid_then_placement_without_await(),
+        }
+    } else if parser.match_str("{:catch") {
+        match block {
+            Some(block) => Error::invalid_catch_placement_unclosed_block(block),
+            None => Error::invalid_catch_placement_without_await(),
+        }
+    } else {
+        Error::unexpected_block_close()
+    };
+
+    parser.error(error, None)
 }
 
 /// Checks if the index is at a curly brace `{` and parses the expression
 /// at the index untill the next closing curly brace `}`.
 ///
 /// Sets the `parser.index` to the closing curly brace `}` index.
-fn parse_expression(parser: &mut Parser) -> Option<Expr> {
+fn parse_expression(parser: &mut Parser) -> Result<Option<Expr>, Box<CompileError>> {
     if parser.match_str("{") {
-        parser.eat("{");
-        let expr = parse_javascript(parser);
-        parser.eat("}");
+        parser.eat("{")?;
+        let expr = parse_javascript(parser)?;
+        parser.eat("}")?;
 
-        return Some(expr);
+        return Ok(Some(expr));
     }
 
-    None
+    Ok(None)
+}
+
+/// Parses a `{@html expression}`, `{@debug a, b}`
+/// or `{@const pattern = init}` tag
+fn parse_tag(parser: &mut Parser) -> Result<Option<Fragment>, Box<CompileError>> {
+    if !parser.match_str("{@") {
+        return Ok(None);
+    }
+
+    let start_index = parser.index;
+    parser.eat("{@")?;
+    let name = parser.read_while_matching(&IDENTIFIER);
+    parser.skip_whitespace();
+
+    let tag = match name.as_str() {
+        "html" => {
+            let expression = parse_javascript(parser)?;
+            parser.skip_whitespace();
+            parser.eat("}")?;
+            Fragment::RawHtml(RustleRawHtml {
+                span: parser.span(start_index, parser.index),
+                expression,
+            })
+        }
+        "debug" => {
+            let identifiers = match parser.match_str("}") {
+                true => Vec::new(),
+                false => {
+                    let expression_index = parser.index;
+                    let expressions = match parse_javascript(parser)? {
+                        Expr::Seq(seq) => seq.exprs.into_iter().map(|expr| *expr).collect(),
+                        expr => vec![expr],
+                    };
+                    expressions
+                        .into_iter()
+                        .map(|expr| match expr {
+                            Expr::Ident(ident) => Ok(ident),
+                            _ => Err(parser.error(Error::invalid_debug_args(), Some(expression_index))),
+                        })
+                        .collect::<Result<Vec<Ident>, Box<CompileError>>>()?
+                }
+            };
+            parser.skip_whitespace();
+            parser.eat("}")?;
+            Fragment::DebugTag(RustleDebugTag {
+                span: parser.span(start_index, parser.index),
+                identifiers,
+            })
+        }
+        "const" => {
+            let expression_index = parser.index;
+            let (pattern, init) = match parse_javascript(parser)? {
+                Expr::Assign(assign) if assign.op == AssignOp::Assign => {
+                    let pattern = match assign.left {
+                        PatOrExpr::Pat(pat) => match *pat {
+                            Pat::Expr(expr) => match *expr {
+                                Expr::Ident(ident) => Some(Pat::Ident(ident.into())),
+                                _ => None,
+                            },
+                            pat => Some(pat),
+                        },
+                        PatOrExpr::Expr(expr) => match *expr {
+                            Expr::Ident(ident) => Some(Pat::Ident(ident.into())),
+                            _ => None,
+                        },
+                    };
+                    (pattern, *assign.right)
+                }
+                expr => (None, expr),
+            };
+            let pattern = pattern
+                .ok_or_else(|| parser.error(Error::invalid_const_args(), Some(expression_index)))?;
+            parser.skip_whitespace();
+            parser.eat("}")?;
+            Fragment::ConstTag(RustleConstTag {
+                span: parser.span(start_index, parser.index),
+                pattern,
+                init,
+            })
+        }
+        _ => return Err(parser.error(Error::expected_tag_type(), Some(start_index + 2))),
+    };
+
+    Ok(Some(tag))
 }
 
 /// Parses text between tags for example `<div>some text</div>`
 fn parse_text(parser: &mut Parser) -> Option<Text> {
+    let start_index = parser.index;
     let text = parser.read_while_matching(&READ_TEXT);
 
     if text.trim() != "" {
         return Some(Text {
-            span: Span::default(),
+            span: parser.span(start_index, parser.index),
             data: text.into(),
             raw: None,
         });
@@ -189,66 +862,364 @@ fn parse_text(parser: &mut Parser) -> Option<Text> {
     None
 }
 
-/// Parses all the attributes inside a tag untill the closing `>`
-/// for example `on:click={action}`
-fn parse_attribute_list(parser: &mut Parser) -> Vec<RustleAttribute> {
+/// The attributes of a tag, the directives that are not attributes apart
+struct AttributeList {
+    attributes: Vec<RustleAttribute>,
+    handlers: Vec<RustleEventHandler>,
+    actions: Vec<RustleAction>,
+    transitions: Vec<RustleTransition>,
+    animation: Option<RustleAction>,
+}
+
+/// Parses all the attributes inside a tag untill the closing `>`, the
+/// `on:`, `use:`, transition and `animate:` directives are returned apart
+fn parse_attribute_list(
+    parser: &mut Parser,
+    is_component: bool,
+) -> Result<AttributeList, Box<CompileError>> {
     let mut attributes = Vec::new();
+    let mut handlers = Vec::new();
+    let mut actions = Vec::new();
+    let mut transitions: Vec<RustleTransition> = Vec::new();
+    let mut animation = None;
     parser.skip_whitespace();
 
     while !parser.match_str(">") && !parser.match_str("/>") {
-        attributes.push(parse_attribute(parser));
+        if parser.index >= parser.content.len() {
+            return Err(parser.error(Error::unexpected_eof(), None));
+        }
+        if parser.match_str("on:") {
+            handlers.push(parse_event_handler(parser, is_component)?);
+        } else if parser.match_str("use:") {
+            if is_component {
+                return Err(parser.error(Error::invalid_action(), None));
+            }
+            actions.push(parse_action(parser)?);
+        } else if ["transition:", "in:", "out:"].iter().any(|prefix| parser.match_str(prefix)) {
+            if is_component {
+                return Err(parser.error(Error::invalid_transition(), None));
+            }
+            let start_index = parser.index;
+            let transition = parse_transition(parser)?;
+            // an element plays a single intro and a single outro
+            if let Some(existing) = transitions
+                .iter()
+                .find(|existing| (existing.intro && transition.intro) || (existing.outro && transition.outro))
+            {
+                return Err(parser.error(
+                    Error::duplicate_transition(transition_directive(existing), transition_directive(&transition)),
+                    Some(start_index),
+                ));
+            }
+            transitions.push(transition);
+        } else if parser.match_str("animate:") {
+            if is_component {
+                return Err(parser.error(Error::invalid_animation(), None));
+            }
+            if animation.is_some() {
+                return Err(parser.error(Error::duplicate_animation(), None));
+            }
+            animation = Some(parse_animation(parser)?);
+        } else {
+            attributes.push(parse_attribute(parser)?);
+        }
         parser.skip_whitespace();
     }
 
-    attributes
+    Ok(AttributeList {
+        attributes,
+        handlers,
+        actions,
+        transitions,
+        animation,
+    })
 }
 
-/// Gets the attribute name and the expression between curly braces
-/// `on:click={action}` -> `on:click`, `action`
-fn parse_attribute(parser: &mut Parser) -> RustleAttribute {
+/// Parses a `transition:name|modifier={params}`, `in:name` or
+/// `out:name` directive, the parameters are optional
+fn parse_transition(parser: &mut Parser) -> Result<RustleTransition, Box<CompileError>> {
+    let start_index = parser.index;
+    let (intro, outro) = if parser.match_str("in:") {
+        parser.eat("in:")?;
+        (true, false)
+    } else if parser.match_str("out:") {
+        parser.eat("out:")?;
+        (false, true)
+    } else {
+        parser.eat("transition:")?;
+        (true, true)
+    };
+    let directive = parser.read_while_matching(&ATTRIBUTE_NAME);
+
+    let mut parts = directive.split('|');
+    let name = parts.next().unwrap_or_default().to_owned();
+    let modifiers = parts.collect::<Vec<&str>>();
+    if name.is_empty() {
+        return Err(parser.error(Error::empty_directive_name("Transition"), Some(start_index)));
+    }
+    if modifiers.iter().any(|modifier| *modifier != "local" && *modifier != "global") {
+        return Err(parser.error(Error::invalid_transition_modifier(), Some(start_index)));
+    }
+    if modifiers.contains(&"local") && modifiers.contains(&"global") {
+        return Err(parser.error(
+            Error::invalid_transition_modifier_combination(),
+            Some(start_index),
+        ));
+    }
+
+    let expression = parse_directive_expression(parser)?;
+
+    Ok(RustleTransition {
+        span: parser.span(start_index, parser.index),
+        name,
+        expression,
+        intro,
+        outro,
+        local: !modifiers.contains(&"global"),
+    })
+}
+
+/// The directive of a transition as written, `in`, `out` or `transition`
+fn transition_directive(transition: &RustleTransition) -> &'static str {
+    match (transition.intro, transition.outro) {
+        (true, false) => "in",
+        (false, true) => "out",
+        _ => "transition",
+    }
+}
+
+/// Parses an `animate:name={params}` directive,
+/// the parameters are optional
+fn parse_animation(parser: &mut Parser) -> Result<RustleAction, Box<CompileError>> {
+    let start_index = parser.index;
+    parser.eat("animate:")?;
+    let name = parser.read_while_matching(&ATTRIBUTE_NAME);
+    if name.is_empty() {
+        return Err(parser.error(Error::empty_directive_name("Animation"), Some(start_index)));
+    }
+
+    let expression = parse_directive_expression(parser)?;
+
+    Ok(RustleAction {
+        span: parser.span(start_index, parser.index),
+        name,
+        expression,
+    })
+}
+
+/// Parses a `use:action={parameters}` directive,
+/// the parameters are optional
+fn parse_action(parser: &mut Parser) -> Result<RustleAction, Box<CompileError>> {
+    let start_index = parser.index;
+    parser.eat("use:")?;
+    let name = parser.read_while_matching(&ATTRIBUTE_NAME);
+    if name.is_empty() {
+        return Err(parser.error(Error::empty_directive_name("Action"), Some(start_index)));
+    }
+
+    let expression = parse_directive_expression(parser)?;
+
+    Ok(RustleAction {
+        span: parser.span(start_index, parser.index),
+        name,
+        expression,
+    })
+}
+
+/// Parses an `on:name|modifier={expression}` directive,
+/// `on:name` alone forwards the event
+///
+/// Components only accept the `once` modifier.
+fn parse_event_handler(
+    parser: &mut Parser,
+    is_component: bool,
+) -> Result<RustleEventHandler, Box<CompileError>> {
+    let start_index = parser.index;
+    parser.eat("on:")?;
+    let directive = parser.read_while_matching(&ATTRIBUTE_NAME);
+
+    let mut parts = directive.split('|');
+    let name = parts.next().unwrap_or_default().to_owned();
+    let modifiers = parts.map(str::to_owned).collect::<Vec<String>>();
+    if name.is_empty() {
+        return Err(parser.error(Error::empty_directive_name("Event"), Some(start_index)));
+    }
+
+    if modifiers.iter().any(|modifier| !EVENT_MODIFIERS.contains(&modifier.as_str())) {
+        return Err(parser.error(Error::invalid_event_modifier(&EVENT_MODIFIERS), Some(start_index)));
+    }
+    for (first, second) in [("passive", "preventDefault"), ("passive", "nonpassive")] {
+        if modifiers.iter().any(|m| m == first) && modifiers.iter().any(|m| m == second) {
+            return Err(parser.error(
+                Error::invalid_event_modifier_combination(first, second),
+                Some(start_index),
+            ));
+        }
+    }
+    if is_component && modifiers.iter().any(|modifier| modifier != "once") {
+        return Err(parser.error(Error::invalid_event_modifier_component(), Some(start_index)));
+    }
+
+    let expression = parse_directive_expression(parser)?;
+
+    Ok(RustleEventHandler {
+        span: parser.span(start_index, parser.index),
+        name,
+        modifiers,
+        expression,
+    })
+}
 
+/// Gets the attribute name and the expression between curly braces
+/// `value={name}` -> `value`, `name`
+fn parse_attribute(parser: &mut Parser) -> Result<RustleAttribute, Box<CompileError>> {
     // parse for attribute self case
     if parser.match_str("{") {
-        parser.eat("{");
-        let value = parse_javascript(parser);
-        parser.eat("}");
+        let start_index = parser.index;
+        parser.eat("{")?;
+        parser.skip_whitespace();
+        if parser.match_str("...") {
+            parser.eat("...")?;
+            let value = parse_javascript(parser)?;
+            parser.skip_whitespace();
+            parser.eat("}")?;
+            return Ok(RustleAttribute {
+                name: "...".into(),
+                value,
+            });
+        }
+        let value = parse_javascript(parser)?;
+        parser.eat("}")?;
         match value {
             Expr::Ident(ref ident) => {
-                return RustleAttribute { name: ident.sym.to_string(), value }
-            },
-            _ => panic!()
+                return Ok(RustleAttribute {
+                    name: ident.sym.to_string(),
+                    value,
+                })
+            }
+            _ => {
+                return Err(parser.error(Error::empty_attribute_shorthand(), Some(start_index)))
+            }
         }
     }
 
+    let start_index = parser.index;
     let name = parser.read_while_matching(&ATTRIBUTE_NAME);
 
+    if let Some(binding) = name.strip_prefix("bind:") {
+        return parse_binding(parser, start_index, name.clone(), binding);
+    }
+
+    for (prefix, directive_type) in [("class:", "Class"), ("style:", "Style"), ("let:", "Let")] {
+        if let Some(directive) = name.strip_prefix(prefix) {
+            let mut parts = directive.split('|');
+            let property = parts.next().unwrap_or_default();
+            if property.is_empty() {
+                return Err(parser.error(Error::empty_directive_name(directive_type), Some(start_index)));
+            }
+            let modifiers = parts.collect::<Vec<&str>>();
+            if prefix == "style:" && modifiers.iter().any(|modifier| *modifier != "important") {
+                return Err(parser.error(Error::invalid_style_directive_modifier(), Some(start_index)));
+            }
+
+            // `class:name` is a shorthand for `class:name={name}`, and so on
+            if !parser.match_str("=") {
+                let name_index = start_index + prefix.len();
+                let value = Expr::Ident(Ident::new(
+                    property.into(),
+                    parser.span(name_index, name_index + property.len()),
+                ));
+                return Ok(RustleAttribute { name, value });
+            }
+        }
+    }
+
     if parser.match_str("=\"") {
-        parser.eat("=\"");
-        parser.content.insert(parser.index, '`');
-        let mut indent = 1usize;
-        while parser.content.chars().nth(parser.index + indent).unwrap().to_string().as_str() != "\"" {
-            indent += 1;
+        parser.eat("=\"")?;
+        let start_index = parser.index;
+        let raw = parser.read_while_matching(&ATTRIBUTE_STRING_VALUE);
+        if !parser.match_str("\"") {
+            return Err(parser.error(Error::unclosed_attribute_value("\""), None));
         }
-        parser.content.insert(parser.index + indent, '`');
 
-        let value = parse_javascript(parser);
+        // the value is parsed as a template string, so `"{name} dancing"`
+        // becomes `` `{name} dancing` ``
+        let mut template = Parser::new(&format!("`{}`", raw));
+        let value = parse_javascript(&mut template).map_err(|error| {
+            parser.error(
+                Error {
+                    code: error.code,
+                    message: error.message,
+                },
+                Some(start_index + error.pos.saturating_sub(1)),
+            )
+        })?;
 
-        parser.eat("\"");
+        parser.eat("\"")?;
 
-        return RustleAttribute { name, value };
+        Ok(RustleAttribute { name, value })
     } else {
-        parser.eat("={");
+        parser.eat("={")?;
+
+        let value = parse_javascript(parser)?;
+
+        parser.eat("}")?;
+        Ok(RustleAttribute { name, value })
+    }
+}
 
-        let value = parse_javascript(parser);
+/// Parses a `bind:name={expression}` directive, `bind:name` is
+/// a shorthand for `bind:name={name}`
+fn parse_binding(
+    parser: &mut Parser,
+    start_index: usize,
+    name: String,
+    binding: &str,
+) -> Result<RustleAttribute, Box<CompileError>> {
+    if binding.is_empty() {
+        return Err(parser.error(Error::empty_directive_name("Binding"), Some(start_index)));
+    }
 
-        parser.eat("}");
-        return RustleAttribute { name, value };
+    if !parser.match_str("=") {
+        let name_index = start_index + "bind:".len();
+        let value = Expr::Ident(Ident::new(
+            binding.into(),
+            parser.span(name_index, parser.index),
+        ));
+        return Ok(RustleAttribute { name, value });
+    }
+
+    if !parser.match_str("={") {
+        return Err(parser.error(Error::invalid_directive_value(), Some(parser.index + 1)));
+    }
+    parser.eat("={")?;
+    let value_index = parser.index;
+    let value = parse_javascript(parser)?;
+    parser.eat("}")?;
+
+    match value {
+        Expr::Ident(_) | Expr::Member(_) => Ok(RustleAttribute { name, value }),
+        _ => Err(parser.error(Error::invalid_binding_value(), Some(value_index))),
+    }
+}
+
+/// Parses the optional `={expression}` of a directive
+fn parse_directive_expression(parser: &mut Parser) -> Result<Option<Expr>, Box<CompileError>> {
+    if !parser.match_str("=") {
+        return Ok(None);
+    }
+    if !parser.match_str("={") {
+        return Err(parser.error(Error::invalid_directive_value(), Some(parser.index + 1)));
     }
 
+    parser.eat("={")?;
+    let expression = parse_javascript(parser)?;
+    parser.eat("}")?;
+    Ok(Some(expression))
 }
 
 /// Parses javascript using SWC at the current index.
 /// Probably redundant.
-fn parse_javascript(parser: &mut Parser) -> Expr {
+fn parse_javascript(parser: &mut Parser) -> Result<Expr, Box<CompileError>> {
     parse_expression_at(parser)
 }
@@ -1,6 +1,6 @@
 //pub mod acorn;
 
-//pub mod errors;
+pub mod errors;
 //pub mod index;
 pub mod parser;
 // pub mod read;
@@ -1,18 +1,30 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use swc_common::{BytePos, Span, DUMMY_SP};
+use swc_ecma_ast::Module;
 
-use crate::compiler::{Fragment, RustleAst};
+use crate::compiler::utils::{CompileError, NewErrorProps};
+use crate::compiler::analyse::static_value;
+use crate::compiler::{Fragment, RustleAst, RustleElement, RustleOptions};
 
+use super::errors::Error;
 use super::fragments::parse_fragments;
 
 lazy_static! {
     static ref WHITESPACE: Regex = Regex::new("[\\s\n]").unwrap();
+    static ref CUSTOM_ELEMENT_NAME: Regex = Regex::new("^[a-zA-Z][a-zA-Z0-9]*-[a-zA-Z0-9-]+$").unwrap();
 }
 
 #[derive(Debug, Clone)]
 pub struct Parser {
+    /// The byte offset of the parser in the content
     pub index: usize,
     pub content: String,
+    pub filename: Option<String>,
+    /// The names of the open elements and blocks, like `div` or `if`
+    pub stack: Vec<String>,
+    /// The `<svelte:head>`, `<svelte:window>` and `<svelte:body>` tags seen so far
+    pub meta_tags: Vec<String>,
 }
 
 impl Parser {
@@ -36,40 +48,151 @@ impl Parser {
         Self {
             index: 0,
             content: content.into(),
+            filename: None,
+            stack: Vec::new(),
+            meta_tags: Vec::new(),
         }
     }
 
-    /// Parses the content to an AST and returns it
-    pub fn parse(&mut self) -> RustleAst {
-        let mut fragments = parse_fragments(self, |parser| parser.index < parser.content.len());
+    /// Parses the content to an AST and returns it.
+    ///
+    /// Returns a `CompileError` pointing at the offending
+    /// position if the content is not a valid component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustle::compiler::parse::Parser;
+    ///
+    /// let error = Parser::new("<div>").parse().err().unwrap();
+    /// assert_eq!(error.code, "unclosed-element");
+    /// ```
+    pub fn parse(&mut self) -> Result<RustleAst, Box<CompileError>> {
+        let mut fragments =
+            parse_fragments(self, |parser| parser.index < parser.content.len())?;
+
+        let import = match fragments.iter().position(|f| matches!(f, Fragment::Import(_))) {
+            Some(index) => match fragments.remove(index) {
+                Fragment::Import(import) => import,
+                _ => unreachable!(),
+            },
+            None => "".to_owned(),
+        };
+
+        let script = match fragments.iter().position(|f| matches!(f, Fragment::Script(_))) {
+            Some(index) => match fragments.remove(index) {
+                Fragment::Script(script) => script,
+                _ => unreachable!(),
+            },
+            None => Module {
+                span: DUMMY_SP,
+                body: vec![],
+                shebang: None,
+            },
+        };
 
-        let script_index = fragments
+        let options = match fragments
             .iter()
-            .position(|f| match f {
-                Fragment::Import(_) => true,
-                Fragment::Script(_) => true,
-                _ => false,
+            .position(|f| matches!(f, Fragment::Element(element) if element.name == "svelte:options"))
+        {
+            Some(index) => match fragments.remove(index) {
+                Fragment::Element(element) => Some(self.options(element)?),
+                _ => unreachable!(),
+            },
+            None => None,
+        };
+
+        let (styles, fragments): (Vec<Fragment>, Vec<Fragment>) = fragments
+            .into_iter()
+            .partition(|f| matches!(f, Fragment::Style(_)));
+        let mut styles = styles
+            .into_iter()
+            .filter_map(|f| match f {
+                Fragment::Style(style) => Some(style),
+                _ => None,
             })
-            .unwrap();
+            .collect::<Vec<_>>();
+        if let Some(duplicate) = styles.get(1) {
+            let tag_index = duplicate.start - "<style>".len();
+            return Err(self.error(Error::duplicate_style(), Some(tag_index)));
+        }
 
-        let f = fragments.remove(script_index);
-        match f {
-            Fragment::Import(s) => {
-                let script = if let Fragment::Script(s) = fragments.remove(script_index) {
-                    Some(s)
-                } else {
-                    None
-                }.unwrap();
-                return RustleAst { import: s, script, fragments }
-            }
-            Fragment::Script(script) => {
-                return RustleAst { import: "".to_owned(), script, fragments }
-            },
-            _ => {
-                todo!()
+        Ok(RustleAst {
+            import,
+            script,
+            style: styles.pop(),
+            options,
+            fragments,
+        })
+    }
+
+    /// Reads the component options of the `<svelte:options>` tag
+    fn options(&self, element: RustleElement) -> Result<RustleOptions, Box<CompileError>> {
+        let index = element.span.lo.0 as usize - 1;
+        let mut options = RustleOptions {
+            span: element.span,
+            custom_element: None,
+        };
+
+        if let Some(handler) = element.handlers.first() {
+            let name = format!("on:{}", handler.name);
+            return Err(self.error(Error::invalid_options_attribute_unknown(&name), Some(index)));
+        }
+        for attr in element.attributes {
+            match attr.name.as_str() {
+                "customElement" => {
+                    let name = static_value(&attr.value)
+                        .ok_or_else(|| self.error(Error::invalid_custom_element_attribute(), Some(index)))?;
+                    if !CUSTOM_ELEMENT_NAME.is_match(&name) {
+                        return Err(self.error(Error::invalid_tag_property(), Some(index)));
+                    }
+                    options.custom_element = Some(name);
+                }
+                name => {
+                    return Err(self.error(Error::invalid_options_attribute_unknown(name), Some(index)))
+                }
             }
         }
 
+        Ok(options)
+    }
+
+    /// Creates a `CompileError` from the provided `Error`
+    /// located at `index`, or at the current index if `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error code and message
+    /// * `index` - The position in the content the error points to
+    pub fn error(&self, error: Error, index: Option<usize>) -> Box<CompileError> {
+        Box::new(CompileError::new(
+            &error.message,
+            NewErrorProps {
+                name: "ParseError",
+                code: &error.code,
+                source: &self.content,
+                filename: self.filename.as_deref().unwrap_or_default(),
+                start: index.unwrap_or(self.index),
+                end: None,
+            },
+        ))
+    }
+
+    /// Creates a `Span` from the `start` index to the `end` index.
+    ///
+    /// Like the spans of SWC nodes, its positions are
+    /// byte offsets in the content shifted by 1.
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(
+            BytePos(start as u32 + 1),
+            BytePos(end as u32 + 1),
+            Default::default(),
+        )
+    }
+
+    /// Returns the content from the index to the end
+    pub fn rest(&self) -> &str {
+        self.content.get(self.index..).unwrap_or_default()
     }
 
     /// Checks if the string at the current index
@@ -90,19 +213,13 @@ impl Parser {
     /// parser.index = 10;
     /// assert!(parser.match_str("awesome"));
     /// ```
-    pub fn match_str(&mut self, str: &str) -> bool {
-        self.content
-            .chars()
-            .skip(self.index)
-            .take(str.len())
-            .collect::<String>()
-            .as_str()
-            == str
+    pub fn match_str(&self, str: &str) -> bool {
+        self.rest().starts_with(str)
     }
 
     /// Eats the provided string at the index
     /// if it matches and advances the index.
-    /// If not, then it panics.
+    /// If not, then it returns an `unexpected-token` error.
     ///
     /// # Arguments
     ///
@@ -115,21 +232,29 @@ impl Parser {
     ///
     /// let mut parser = Parser::new("rustle is awesome");
     ///
-    /// parser.eat("rustle");
+    /// parser.eat("rustle").unwrap();
     /// assert_eq!(parser.index, 6);
     ///
-    /// parser.eat(" ");
+    /// parser.eat(" ").unwrap();
     /// assert_eq!(parser.index, 7);
     ///
-    /// parser.eat("is");
+    /// parser.eat("is").unwrap();
     /// assert_eq!(parser.index, 9);
+    ///
+    /// assert!(parser.eat("not").is_err());
     /// ```
-    pub fn eat(&mut self, str: &str) {
+    pub fn eat(&mut self, str: &str) -> Result<(), Box<CompileError>> {
         if self.match_str(str) {
             self.index += str.len();
-        } else {
-            panic!("Parse error: expecting {}", str);
+            return Ok(());
         }
+
+        let error = if self.index >= self.content.len() {
+            Error::unexpected_eof_token(str)
+        } else {
+            Error::unexpected_token(str)
+        };
+        Err(self.error(error, None))
     }
 
     /// Reads the content at the index untill
@@ -143,7 +268,7 @@ impl Parser {
     ///
     /// ```
     /// use rustle::compiler::parse::Parser;
-    ///	use regex::Regex;
+    /// use regex::Regex;
     ///
     /// let mut parser = Parser::new("rustle is awesome");
     /// // Regex that matches the letters a-z
@@ -155,27 +280,15 @@ impl Parser {
     /// assert_eq!(parser.read_while_matching(&regex), "awesome".to_string());
     /// ```
     pub fn read_while_matching(&mut self, regex: &Regex) -> String {
-        let start_index = self.index;
-
-        while regex.is_match(
-            self.content
-                .chars()
-                .nth(self.index)
-                .unwrap()
-                .to_string()
-                .as_str(),
-        ) {
-            self.index += 1;
-
-            if self.index >= self.content.len() {
-                break;
-            }
-        }
+        let rest = self.rest();
+        let length = rest
+            .char_indices()
+            .find(|(_, char)| !regex.is_match(char.encode_utf8(&mut [0; 4])))
+            .map_or(rest.len(), |(offset, _)| offset);
+        let matched = rest[..length].to_string();
 
-        self.content
-            .get(start_index..self.index)
-            .unwrap()
-            .to_string()
+        self.index += length;
+        matched
     }
 
     /// Advances the index untill the next non-whitespace character.
@@ -189,11 +302,13 @@ impl Parser {
 #[cfg(test)]
 mod tests {
     use crate::compiler::parse::Parser;
+    use crate::compiler::Fragment;
     use regex::Regex;
+    use swc_ecma_ast::Expr;
 
     #[test]
     fn test_match_str() {
-        let mut parser = Parser::new("rustle is awesome");
+        let parser = Parser::new("rustle is awesome");
         assert!(parser.match_str("rustle"));
     }
 
@@ -201,22 +316,36 @@ mod tests {
     fn test_eat() {
         let mut parser = Parser::new("rustle is awesome");
 
-        parser.eat("rustle");
+        parser.eat("rustle").unwrap();
         assert_eq!(parser.index, 6);
 
-        parser.eat(" ");
+        parser.eat(" ").unwrap();
         assert_eq!(parser.index, 7);
 
-        parser.eat("is");
+        parser.eat("is").unwrap();
         assert_eq!(parser.index, 9);
 
-        parser.eat(" ");
+        parser.eat(" ").unwrap();
         assert_eq!(parser.index, 10);
 
-        parser.eat("awesome");
+        parser.eat("awesome").unwrap();
         assert_eq!(parser.index, 17);
     }
 
+    #[test]
+    fn test_eat_error() {
+        let mut parser = Parser::new("rustle is awesome");
+
+        let error = parser.eat("svelte").unwrap_err();
+        assert_eq!(error.code, "unexpected-token");
+        assert_eq!(error.message, "Expected svelte");
+        assert_eq!(parser.index, 0);
+
+        parser.index = 17;
+        let error = parser.eat("!").unwrap_err();
+        assert_eq!(error.code, "unexpected-eof");
+    }
+
     #[test]
     fn test_read_while_matching() {
         let mut parser = Parser::new("rustle is awesome");
@@ -226,14 +355,14 @@ mod tests {
         assert_eq!(parser.index, 6);
         assert_eq!(matched, "rustle".to_string());
 
-        parser.eat(" ");
+        parser.eat(" ").unwrap();
         assert_eq!(parser.index, 7);
 
         matched = parser.read_while_matching(&regex);
         assert_eq!(parser.index, 9);
         assert_eq!(matched, "is".to_string());
 
-        parser.eat(" ");
+        parser.eat(" ").unwrap();
         assert_eq!(parser.index, 10);
 
         matched = parser.read_while_matching(&regex);
@@ -247,4 +376,430 @@ mod tests {
         parser.skip_whitespace();
         assert_eq!(parser.index, 6);
     }
+
+    #[test]
+    fn test_parse_error_location() {
+        let source = "<script>\n\tlet a = 1;\n</script>\n\n<div>\n\t<p>{a +}</p>\n</div>";
+        let error = Parser::new(source).parse().err().unwrap();
+
+        assert_eq!(error.code, "parse-error");
+        assert_eq!(error.start.line, 6);
+        assert!(error.frame.contains("6: "));
+    }
+
+    #[test]
+    fn test_parse_unclosed_element() {
+        let error = Parser::new("<div><p>text</p>").parse().err().unwrap();
+
+        assert_eq!(error.code, "unclosed-element");
+        assert_eq!(error.message, "<div> was left open");
+        assert_eq!(error.start.line, 1);
+        assert_eq!(error.start.column, 0);
+    }
+
+    #[test]
+    fn test_parse_non_ascii() {
+        for source in ["é", "<p>é</p>", "<p>日本語</p>", "<p title=\"été\">{a}ü</p>"] {
+            let ast = Parser::new(source).parse().unwrap();
+            assert_eq!(ast.fragments.len(), 1);
+        }
+
+        let source = "<script>let a = \"é\";</script><p>{a}</p>\n<style>p::after { content: \"日本\"; }</style>";
+        let ast = Parser::new(source).parse().unwrap();
+        assert_eq!(ast.script.body.len(), 1);
+        match &ast.fragments[0] {
+            Fragment::Element(element) => {
+                assert_eq!(element.span.lo.0 as usize - 1, source.find("<p>").unwrap());
+                assert!(matches!(element.fragments[0], Fragment::Expression(Expr::Ident(_))));
+            }
+            _ => panic!(),
+        }
+        let style = ast.style.unwrap();
+        assert_eq!(&source[style.start..style.start + style.content.len()], style.content);
+
+        let error = Parser::new("<p>é</p>\n<p>ü {a +}</p>").parse().err().unwrap();
+        assert_eq!(error.code, "parse-error");
+        assert_eq!((error.start.line, error.start.column), (2, 9));
+
+        let error = Parser::new("<script>let a = 'é' +;</script>").parse().err().unwrap();
+        assert_eq!(error.start.column, 21);
+    }
+
+    #[test]
+    fn test_parse_if_block() {
+        let source = "{#if a}<p>a</p>{:else if b}<p>b</p>{:else}<p>c</p>{/if}";
+        let ast = Parser::new(source).parse().unwrap();
+
+        let block = match &ast.fragments[0] {
+            Fragment::IfBlock(block) => block,
+            _ => panic!("expected an if block"),
+        };
+        assert_eq!(block.fragments.len(), 1);
+
+        let else_if = match block.else_fragments.as_deref() {
+            Some([Fragment::IfBlock(else_if)]) => else_if,
+            _ => panic!("expected an else if block"),
+        };
+        assert_eq!(else_if.else_fragments.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_if_block_errors() {
+        let error = Parser::new("<p>{:else}</p>").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-else-placement");
+        assert_eq!(error.message, "Expected to close <p> before seeing {:else} block");
+
+        let error = Parser::new("{:else if a}").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-elseif-placement");
+
+        let error = Parser::new("{#if a}<p>a</p>").parse().err().unwrap();
+        assert_eq!(error.code, "unclosed-block");
+
+        let error = Parser::new("{/if}").parse().err().unwrap();
+        assert_eq!(error.code, "unexpected-block-close");
+    }
+
+    #[test]
+    fn test_parse_each_block() {
+        let source = "{#each items as { id, name }, i (id)}<p>{name}</p>{:else}<p>empty</p>{/each}";
+        let ast = Parser::new(source).parse().unwrap();
+
+        let block = match &ast.fragments[0] {
+            Fragment::EachBlock(block) => block,
+            _ => panic!("expected an each block"),
+        };
+        assert!(block.context.is_object());
+        assert_eq!(block.index, Some("i".to_owned()));
+        assert!(block.key.is_some());
+        assert_eq!(block.fragments.len(), 1);
+        assert_eq!(block.else_fragments.as_ref().unwrap().len(), 1);
+
+        let ast = Parser::new("{#each items as item}{item}{/each}").parse().unwrap();
+        match &ast.fragments[0] {
+            Fragment::EachBlock(block) => {
+                assert!(block.context.is_ident());
+                assert!(block.index.is_none());
+                assert!(block.key.is_none());
+            }
+            _ => panic!("expected an each block"),
+        }
+    }
+
+    #[test]
+    fn test_parse_each_block_errors() {
+        let error = Parser::new("{#each items}{/each}").parse().err().unwrap();
+        assert_eq!(error.code, "unexpected-token");
+        assert_eq!(error.message, "Expected as");
+
+        let error = Parser::new("{#each items as item}{/if}").parse().err().unwrap();
+        assert_eq!(error.message, "Expected {/each}");
+    }
+
+    #[test]
+    fn test_parse_await_block() {
+        let source = "{#await promise}<p>...</p>{:then value}<p>{value}</p>{:catch error}<p>{error}</p>{/await}";
+        let ast = Parser::new(source).parse().unwrap();
+
+        let block = match &ast.fragments[0] {
+            Fragment::AwaitBlock(block) => block,
+            _ => panic!("expected an await block"),
+        };
+        assert_eq!(block.pending_fragments.len(), 1);
+        assert!(block.value.is_some());
+        assert!(block.error.is_some());
+        assert_eq!(block.then_fragments.as_ref().unwrap().len(), 1);
+        assert_eq!(block.catch_fragments.as_ref().unwrap().len(), 1);
+
+        let ast = Parser::new("{#await promise then { name }}{name}{/await}").parse().unwrap();
+        match &ast.fragments[0] {
+            Fragment::AwaitBlock(block) => {
+                assert!(block.pending_fragments.is_empty());
+                assert!(block.value.as_ref().unwrap().is_object());
+                assert!(block.catch_fragments.is_none());
+            }
+            _ => panic!("expected an await block"),
+        }
+    }
+
+    #[test]
+    fn test_parse_await_block_errors() {
+        let error = Parser::new("{:then value}").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-then-placement");
+        assert_eq!(error.message, "Cannot have an {:then} block outside an {#await ...} block");
+
+        let error = Parser::new("<p>{:catch error}</p>").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-catch-placement");
+
+        let error = Parser::new("{#await p}{:catch e}{:then v}{/await}").parse().err().unwrap();
+        assert_eq!(error.message, "Expected to close {:catch} before seeing {:then} block");
+    }
+
+    #[test]
+    fn test_parse_style() {
+        let ast = Parser::new("<p>a</p>\n<style>p { color: red; }</style>").parse().unwrap();
+        let style = ast.style.unwrap();
+        assert_eq!(style.content, "p { color: red; }");
+        assert_eq!(style.start, 16);
+        assert_eq!(style.stylesheet.rules.len(), 1);
+        assert_eq!(ast.fragments.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_style_errors() {
+        let error = Parser::new("<style>p { color: red; }").parse().err().unwrap();
+        assert_eq!(error.code, "unclosed-style");
+
+        let error = Parser::new("<style></style>\n<style></style>").parse().err().unwrap();
+        assert_eq!(error.code, "duplicate-style");
+        assert_eq!(error.start.line, 2);
+
+        let error = Parser::new("<style>p { color: \"red; }</style>").parse().err().unwrap();
+        assert_eq!(error.code, "css-syntax-error");
+
+        let error = Parser::new("<style>p, :global() { color: red; }</style>").parse().err().unwrap();
+        assert_eq!(error.message, ":global() must contain a selector");
+        assert_eq!(error.start.column, 10);
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        let ast = Parser::new("<input bind:value /><input bind:checked={form.agreed} />")
+            .parse()
+            .unwrap();
+        let attributes = ast
+            .fragments
+            .iter()
+            .map(|fragment| match fragment {
+                Fragment::Element(element) => &element.attributes[0],
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(attributes[0].name, "bind:value");
+        assert!(matches!(&attributes[0].value, Expr::Ident(ident) if &*ident.sym == "value"));
+        assert_eq!(attributes[1].name, "bind:checked");
+        assert!(matches!(attributes[1].value, Expr::Member(_)));
+
+        let error = Parser::new("<input bind:={a} />").parse().err().unwrap();
+        assert_eq!(error.code, "empty-directive-name");
+
+        let error = Parser::new("<input bind:value={a + 1} />").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-directive-value");
+    }
+
+    #[test]
+    fn test_parse_event_handlers() {
+        let ast = Parser::new("<form on:submit|preventDefault|once={() => a += 1} on:click />")
+            .parse()
+            .unwrap();
+        let handlers = match &ast.fragments[0] {
+            Fragment::Element(element) => &element.handlers,
+            _ => panic!(),
+        };
+
+        assert_eq!(handlers[0].name, "submit");
+        assert_eq!(handlers[0].modifiers, vec!["preventDefault", "once"]);
+        assert!(matches!(handlers[0].expression, Some(Expr::Arrow(_))));
+        assert_eq!(handlers[1].name, "click");
+        assert!(handlers[1].expression.is_none());
+
+        let error = Parser::new("<div on:click|prevent={a} />").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-event-modifier");
+
+        let error = Parser::new("<div on:wheel|passive|preventDefault={a} />").parse().err().unwrap();
+        assert_eq!(
+            error.message,
+            "The 'passive' and 'preventDefault' modifiers cannot be used together"
+        );
+
+        let error = Parser::new("<Nested on:click|preventDefault={a} />").parse().err().unwrap();
+        assert_eq!(
+            error.message,
+            "Event modifiers other than 'once' can only be used on DOM elements"
+        );
+    }
+
+    #[test]
+    fn test_parse_class_and_style_directives() {
+        let ast = Parser::new("<div class:active style:color|important={c} />")
+            .parse()
+            .unwrap();
+        let attributes = match &ast.fragments[0] {
+            Fragment::Element(element) => &element.attributes,
+            _ => panic!(),
+        };
+
+        assert_eq!(attributes[0].name, "class:active");
+        assert!(matches!(&attributes[0].value, Expr::Ident(ident) if &*ident.sym == "active"));
+        assert_eq!(attributes[1].name, "style:color|important");
+        assert!(matches!(&attributes[1].value, Expr::Ident(ident) if &*ident.sym == "c"));
+
+        let error = Parser::new("<div class:={a} />").parse().err().unwrap();
+        assert_eq!(error.message, "Class name cannot be empty");
+
+        let error = Parser::new("<div style:color|bold={a} />").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-style-directive-modifier");
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        let ast = Parser::new("<div use:focus use:tooltip={{ text }} />").parse().unwrap();
+        let actions = match &ast.fragments[0] {
+            Fragment::Element(element) => &element.actions,
+            _ => panic!(),
+        };
+
+        assert_eq!(actions[0].name, "focus");
+        assert!(actions[0].expression.is_none());
+        assert_eq!(actions[1].name, "tooltip");
+        assert!(matches!(actions[1].expression, Some(Expr::Object(_))));
+
+        let error = Parser::new("<Nested use:focus />").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-action");
+    }
+
+    #[test]
+    fn test_parse_spread() {
+        let ast = Parser::new("<div {...attrs} title=\"a\" />").parse().unwrap();
+        let attributes = match &ast.fragments[0] {
+            Fragment::Element(element) => &element.attributes,
+            _ => panic!(),
+        };
+
+        assert_eq!(attributes[0].name, "...");
+        assert!(matches!(&attributes[0].value, Expr::Ident(ident) if &*ident.sym == "attrs"));
+        assert_eq!(attributes[1].name, "title");
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let ast = Parser::new("{@html markup}{@debug a, b}{#if a}{@const { x } = b}{/if}")
+            .parse()
+            .unwrap();
+
+        assert!(matches!(&ast.fragments[0], Fragment::RawHtml(tag) if matches!(tag.expression, Expr::Ident(_))));
+        match &ast.fragments[1] {
+            Fragment::DebugTag(tag) => {
+                let names = tag.identifiers.iter().map(|ident| &*ident.sym).collect::<Vec<&str>>();
+                assert_eq!(names, vec!["a", "b"]);
+            }
+            _ => panic!(),
+        }
+        match &ast.fragments[2] {
+            Fragment::IfBlock(block) => {
+                assert!(matches!(&block.fragments[0], Fragment::ConstTag(tag) if matches!(tag.init, Expr::Ident(_))));
+            }
+            _ => panic!(),
+        }
+
+        let error = Parser::new("{@debug a.b}").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-debug-args");
+        let error = Parser::new("{@const a}").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-const-args");
+        let error = Parser::new("{@foo a}").parse().err().unwrap();
+        assert_eq!(error.code, "expected-tag-type");
+    }
+
+    #[test]
+    fn test_parse_key_block() {
+        let ast = Parser::new("{#key round}<p>{round}</p>{/key}").parse().unwrap();
+        match &ast.fragments[0] {
+            Fragment::KeyBlock(block) => {
+                assert!(matches!(&block.expression, Expr::Ident(ident) if &*ident.sym == "round"));
+                assert!(matches!(block.fragments[0], Fragment::Element(_)));
+            }
+            _ => panic!(),
+        }
+
+        let error = Parser::new("{#key round}{:else}{/key}").parse().err().unwrap();
+        assert_eq!(error.code, "invalid-else-placement");
+        let error = Parser::new("{#key round}<p>").parse().err().unwrap();
+        assert_eq!(error.code, "unclosed-element");
+    }
+
+    #[test]
+    fn test_parse_transitions() {
+        let source = "<p in:fly={{ y: 20 }} out:fade|global animate:flip={{ duration }} />";
+        let ast = Parser::new(source).parse().unwrap();
+        match &ast.fragments[0] {
+            Fragment::Element(element) => {
+                let transitions = &element.transitions;
+                assert_eq!(transitions.len(), 2);
+                assert_eq!(transitions[0].name, "fly");
+                assert!(transitions[0].intro && !transitions[0].outro && transitions[0].local);
+                assert!(transitions[0].expression.is_some());
+                assert_eq!(transitions[1].name, "fade");
+                assert!(!transitions[1].intro && transitions[1].outro && !transitions[1].local);
+                assert!(transitions[1].expression.is_none());
+                assert_eq!(element.animation.as_ref().unwrap().name, "flip");
+            }
+            _ => panic!(),
+        }
+
+        let cases = [
+            ("<p transition:fade in:fly />", "duplicate-transition"),
+            ("<p transition:fade|once />", "invalid-transition-modifier"),
+            ("<p animate:flip animate:flip />", "duplicate-animation"),
+            ("<Nested transition:fade />", "invalid-transition"),
+            ("<Nested animate:flip />", "invalid-animation"),
+        ];
+        for (source, code) in cases {
+            let error = Parser::new(source).parse().err().unwrap();
+            assert_eq!(error.code, code);
+        }
+    }
+
+    #[test]
+    fn test_parse_svelte_elements() {
+        let source = "<svelte:window on:resize={resize} /><svelte:head><title>a</title></svelte:head>
+            <svelte:element this={tag} class=\"a\" /><svelte:component this={Page} title=\"b\" />";
+        let ast = Parser::new(source).parse().unwrap();
+        let elements = ast
+            .fragments
+            .iter()
+            .filter_map(|fragment| match fragment {
+                Fragment::Element(element) => Some(element),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let names = elements.iter().map(|element| element.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            vec!["svelte:window", "svelte:head", "svelte:element", "svelte:component"]
+        );
+        assert!(!elements[2].is_component);
+        assert!(matches!(&elements[2].this, Some(Expr::Ident(ident)) if &*ident.sym == "tag"));
+        assert_eq!(elements[2].attributes.len(), 1);
+        assert!(elements[3].is_component);
+        assert!(matches!(&elements[3].this, Some(Expr::Ident(ident)) if &*ident.sym == "Page"));
+
+        let error_code = |source: &str| Parser::new(source).parse().err().unwrap().code;
+        assert_eq!(error_code("<svelte:widow />"), "invalid-tag-name");
+        assert_eq!(error_code("<div><svelte:window /></div>"), "invalid-window-placement");
+        assert_eq!(error_code("<svelte:body /><svelte:body />"), "duplicate-body");
+        assert_eq!(error_code("<svelte:window><p /></svelte:window>"), "invalid-window-content");
+        assert_eq!(error_code("<svelte:self />"), "invalid-self-placement");
+        assert_eq!(error_code("<svelte:element />"), "missing-element-definition");
+        assert_eq!(error_code("<svelte:component />"), "missing-component-definition");
+        assert_eq!(error_code("<svelte:component this=\"Page\" />"), "invalid-component-definition");
+        assert!(Parser::new("{#if a}<svelte:self />{/if}").parse().is_ok());
+    }
+
+    #[test]
+    fn test_parse_svelte_options() {
+        let source = "<svelte:options customElement=\"my-counter\" /><p>a</p>";
+        let ast = Parser::new(source).parse().unwrap();
+        assert_eq!(ast.fragments.len(), 1);
+        let options = ast.options.unwrap();
+        assert_eq!(options.custom_element.as_deref(), Some("my-counter"));
+        assert!(Parser::new("<p>a</p>").parse().unwrap().options.is_none());
+
+        let error_code = |source: &str| Parser::new(source).parse().err().unwrap().code;
+        assert_eq!(error_code("<svelte:options tag=\"my-a\" />"), "invalid-options-attribute");
+        assert_eq!(error_code("<svelte:options customElement={name} />"), "invalid-customElement-attribute");
+        assert_eq!(error_code("<svelte:options customElement=\"counter\" />"), "invalid-tag-property");
+        assert_eq!(error_code("<div><svelte:options /></div>"), "invalid-options-placement");
+        assert_eq!(error_code("<svelte:options /><svelte:options />"), "duplicate-options");
+    }
 }
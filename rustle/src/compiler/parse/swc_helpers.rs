@@ -1,35 +1,124 @@
-use swc_common::errors::{ColorConfig, Handler};
 use swc_common::sync::Lrc;
-use swc_common::{FileName, SourceMap};
-use swc_ecma_ast::{EsVersion, Expr, Script};
-use swc_ecma_parser::parse_file_as_script;
+use swc_common::{BytePos, FileName, SourceMap, Span, Spanned};
+use swc_css::ast::{PseudoClassSelector, Stylesheet};
+use swc_css::parser::{error::Error as SwcCssError, parse_str, parser::ParserConfig};
+use swc_css::visit::{Visit as CssVisit, VisitWith as CssVisitWith};
+use swc_ecma_ast::{Decl, EsVersion, Expr, Module, ModuleItem, Pat, Stmt};
+use swc_ecma_parser::error::Error as SwcError;
+use swc_ecma_parser::parse_file_as_module;
 use swc_ecma_parser::{lexer::Lexer, Parser as SwcParser, StringInput, Syntax};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
 
+use crate::compiler::utils::CompileError;
+
+use super::errors::Error;
 use super::parser::Parser;
 
 /// Parser the provided string using `SWC` and returns
-/// a `swc_ecma_ast::Script`
+/// a `swc_ecma_ast::Module`
 ///
 /// # Arguments
 ///
+/// * `parser` - The `Parser` struct, used to locate errors
 /// * `source` - The Javascript string to parse
+/// * `offset` - The byte offset of `source` in the parser content
 ///
-pub fn swc_parse(source: &str) -> Script {
+pub fn swc_parse(parser: &Parser, source: &str, offset: usize) -> Result<Module, Box<CompileError>> {
     let cm: Lrc<SourceMap> = Default::default();
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-
     let fm = cm.new_source_file(FileName::Anon, source.into());
 
-    let result = parse_file_as_script(
+    let mut errors = Vec::new();
+    let result = parse_file_as_module(
         &fm,
         Syntax::Es(Default::default()),
         EsVersion::latest(),
         None,
-        &mut Vec::new(),
-    )
-    .unwrap();
+        &mut errors,
+    );
+
+    match (result, errors.into_iter().next()) {
+        (Err(e), _) | (Ok(_), Some(e)) => Err(swc_error(parser, e, offset)),
+        (Ok(mut script), None) => {
+            script.visit_mut_with(&mut RebaseSpan::new(offset));
+            Ok(script)
+        }
+    }
+}
+
+/// Parses the content of a `<style>` tag using `SWC`
+/// and returns a `swc_css::ast::Stylesheet`
+///
+/// The spans of the stylesheet are byte positions in `source`, shifted by 1.
+///
+/// # Arguments
+///
+/// * `parser` - The `Parser` struct, used to locate errors
+/// * `source` - The CSS string to parse
+/// * `offset` - The byte offset of `source` in the parser content
+pub fn swc_css_parse(
+    parser: &Parser,
+    source: &str,
+    offset: usize,
+) -> Result<Stylesheet, Box<CompileError>> {
+    let mut errors = Vec::new();
+    let result = parse_str::<Stylesheet>(
+        source,
+        BytePos(1),
+        BytePos(source.len() as u32 + 1),
+        ParserConfig::default(),
+        &mut errors,
+    );
+
+    match (result, errors.into_iter().next()) {
+        (Err(e), _) | (Ok(_), Some(e)) => Err(swc_css_error(parser, e, offset)),
+        (Ok(stylesheet), None) => {
+            let mut visitor = EmptyGlobalVisitor { span: None };
+            stylesheet.visit_with(&mut visitor);
+            match visitor.span {
+                Some(span) => {
+                    let position = span.lo.0 as usize - 1;
+                    Err(parser.error(Error::empty_global_selector(), Some(offset + position)))
+                }
+                None => Ok(stylesheet),
+            }
+        }
+    }
+}
+
+/// Finds the first `:global()` without a selector
+struct EmptyGlobalVisitor {
+    span: Option<Span>,
+}
+
+impl CssVisit for EmptyGlobalVisitor {
+    fn visit_pseudo_class_selector(&mut self, selector: &PseudoClassSelector) {
+        let empty = selector.children.as_deref().unwrap_or_default().is_empty();
+        if &*selector.name.value == "global" && empty && self.span.is_none() {
+            self.span = Some(selector.span);
+        }
+        selector.visit_children_with(self);
+    }
+}
+
+/// Parses a binding pattern like `item` or `{ id, name }`
+/// using `SWC` and returns a `swc_ecma_ast::Pat`
+///
+/// # Arguments
+///
+/// * `parser` - The `Parser` struct, used to locate errors
+/// * `source` - The pattern string to parse
+/// * `offset` - The byte offset of `source` in the parser content
+pub fn swc_parse_pattern(parser: &Parser, source: &str, offset: usize) -> Result<Pat, Box<CompileError>> {
+    // the pattern is parsed as a declaration, `let ` shifts the errors by 4
+    let declaration = format!("let {} = 0;", source);
+    let script = swc_parse(parser, &declaration, offset.saturating_sub(4))?;
 
-    result
+    match script.body.into_iter().next() {
+        Some(ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl)))) if var_decl.decls.len() == 1 => {
+            Ok(var_decl.decls.into_iter().next().unwrap().name)
+        }
+        _ => Err(parser.error(Error::unexpected_token_destructure(), Some(offset))),
+    }
 }
 
 /// Parses an expression at the given index
@@ -40,18 +129,11 @@ pub fn swc_parse(source: &str) -> Script {
 /// # Arguments
 ///
 /// * `parser` - The `Parser` struct with the content and index set to the start of the expression
-pub fn parse_expression_at(parser: &mut Parser) -> Expr {
-    let source = parser
-        .content
-        .chars()
-        .skip(parser.index)
-        .collect::<String>()
-        .replace("\n", " ");
+pub fn parse_expression_at(parser: &mut Parser) -> Result<Expr, Box<CompileError>> {
+    let source = parser.rest().replace('\n', " ");
 
     let cm: Lrc<SourceMap> = Default::default();
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-
-    let fm = cm.new_source_file(FileName::Anon, source);
+    let fm = cm.new_source_file(FileName::Anon, source.clone());
 
     let lexer = Lexer::new(
         Syntax::Es(Default::default()),
@@ -62,20 +144,69 @@ pub fn parse_expression_at(parser: &mut Parser) -> Expr {
 
     let mut swc_parser = SwcParser::new_from(lexer);
 
-    for e in swc_parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
-    }
-
     let expr = swc_parser
         .parse_expr()
-        .map_err(|e| e.into_diagnostic(&handler).emit())
-        .expect("failed to parse script")
-        .unwrap_parens()
-        .clone();
+        .map_err(|e| swc_error(parser, e, parser.index))?;
+
+    if let Some(e) = swc_parser.take_errors().into_iter().next() {
+        return Err(swc_error(parser, e, parser.index));
+    }
+
+    let mut expr = expr.unwrap_parens().clone();
+    let end_position = get_end_position(&expr);
+    expr.visit_mut_with(&mut RebaseSpan::new(parser.index));
+
+    parser.index += end_position - 1;
+
+    Ok(expr)
+}
+
+/// Shifts the spans of a node parsed from a slice of the parser content
+/// starting at the byte `offset`, so they point into the whole content.
+struct RebaseSpan {
+    offset: u32,
+}
+
+impl RebaseSpan {
+    fn new(offset: usize) -> Self {
+        RebaseSpan {
+            offset: offset as u32,
+        }
+    }
+}
+
+impl VisitMut for RebaseSpan {
+    fn visit_mut_span(&mut self, span: &mut Span) {
+        if !span.is_dummy() {
+            span.lo.0 += self.offset;
+            span.hi.0 += self.offset;
+        }
+    }
+}
+
+/// Converts an `SWC` error into a `parse-error` located
+/// relative to `offset` in the parser content.
+fn swc_error(parser: &Parser, error: SwcError, offset: usize) -> Box<CompileError> {
+    let position = (error.span().lo.0 as usize).saturating_sub(1);
+    let message = error.kind().msg();
+
+    parser.error(
+        Error::parse_error(&message),
+        Some(offset + position),
+    )
+}
 
-    parser.index += get_end_position(&expr) - 1;
+/// Converts an `SWC` CSS error into a `css-syntax-error` located
+/// relative to `offset` in the parser content.
+fn swc_css_error(parser: &Parser, error: SwcCssError, offset: usize) -> Box<CompileError> {
+    let message = error.message();
+    let (span, _) = *error.into_inner();
+    let position = (span.lo.0 as usize).saturating_sub(1);
 
-    expr
+    parser.error(
+        Error::css_syntax_error(&message),
+        Some(offset + position),
+    )
 }
 
 fn get_end_position(expr: &Expr) -> usize {
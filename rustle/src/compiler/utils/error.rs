@@ -1,75 +1,81 @@
+use std::fmt;
+
 use crate::compiler::utils::get_code_frame;
 use rustle_locate_character::{locate, Options, Search};
+use serde::Serialize;
 
-#[derive(Default, PartialEq, Debug, Copy, Clone)]
+#[derive(Default, PartialEq, Debug, Copy, Clone, Serialize)]
 pub struct Location {
-    line: usize,
-    column: usize,
+    pub line: usize,
+    pub column: usize,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Serialize)]
 pub struct CompileError {
-    code: String,
-    start: Location,
-    end: Location,
-    pos: usize,
-    filename: String,
-    frame: String,
-    message: String,
+    pub code: String,
+    pub start: Location,
+    pub end: Location,
+    pub pos: usize,
+    pub filename: String,
+    pub frame: String,
+    pub message: String,
 }
 
-impl ToString for CompileError {
-    fn to_string(&self) -> String {
-        format!(
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
             "{} ({}:{})\n{}",
             self.message, self.start.line, self.start.column, self.frame
         )
     }
 }
 
+impl std::error::Error for CompileError {}
+
 #[derive(Copy, Clone)]
 pub struct NewErrorProps<'a> {
     pub name: &'a str,
     pub code: &'a str,
     pub source: &'a str,
     pub filename: &'a str,
+    /// The byte offset in `source` the error points to
     pub start: usize,
     pub end: Option<usize>,
 }
 
 impl CompileError {
     pub fn new(message: &str, props: NewErrorProps) -> Self {
-        let start = locate(
-            props.source,
-            Search::Index(props.start),
-            Some(Options {
-                offset_line: Some(1),
-                ..Default::default()
-            }),
-        )
-        .map(|start| Location {
-            line: start.line,
-            column: start.column,
-        });
-        let end = locate(
-            props.source,
-            Search::Index(props.end.unwrap_or(props.start)),
-            Some(Options {
-                offset_line: Some(1),
-                ..Default::default()
-            }),
-        )
-        .map(|start| Location {
-            line: start.line,
-            column: start.column,
-        });
+        let locate = |index: usize| {
+            locate(
+                props.source,
+                Search::Index(index),
+                Some(Options {
+                    offset_line: Some(1),
+                    ..Default::default()
+                }),
+            )
+        };
+        // the located column is a byte offset in the line,
+        // the reported one counts its characters
+        let location = |(line, column): (usize, usize)| Location {
+            line,
+            column: props
+                .source
+                .split('\n')
+                .nth(line - 1)
+                .and_then(|text| text.get(..column))
+                .map_or(column, |text| text.chars().count()),
+        };
+        let start = locate(props.start).map(|start| (start.line, start.column));
+        let end = locate(props.end.unwrap_or(props.start)).map(|end| (end.line, end.column));
         let frame = start
-            .map(|start| get_code_frame(props.source.to_string(), start.line - 1, start.column));
+            .map(|(line, column)| get_code_frame(props.source.to_string(), line - 1, column));
 
         Self {
             code: props.code.to_string(),
-            start: start.unwrap_or_default(),
-            end: end.unwrap_or_default(),
+            start: start.map(location).unwrap_or_default(),
+            end: end.map(location).unwrap_or_default(),
             pos: props.start,
             filename: props.filename.to_string(),
             frame: frame.unwrap_or_default(),
@@ -12,7 +12,7 @@ fn tabs_to_spaces(str: &str) -> String {
 
 pub fn get_code_frame(source: String, line: usize, column: usize) -> String {
     let lines = source.split('\n').collect::<Vec<&str>>();
-    let line_start = if line <= 2 { 0 } else { line - 2 };
+    let line_start = line.saturating_sub(2);
     let line_end = line + 3;
     let (frame_start, frame_end) = (max(0, line_start), min(line_end, lines.len()));
     let digits = format!("{}", frame_end + 1).len();
@@ -24,12 +24,12 @@ pub fn get_code_frame(source: String, line: usize, column: usize) -> String {
 
             if frame_start + t.0 == line {
                 let indicator = " "
-                    .repeat(digits + 2 + tabs_to_spaces(&t.1[0..column.try_into().unwrap()]).len())
+                    .repeat(digits + 2 + tabs_to_spaces(&t.1[0..column]).chars().count())
                     + "^";
-                return format!("{line_num}: {}\n{indicator}", tabs_to_spaces(*t.1));
+                return format!("{line_num}: {}\n{indicator}", tabs_to_spaces(t.1));
             }
 
-            return format!("{line_num}: {}", tabs_to_spaces(*t.1));
+            format!("{line_num}: {}", tabs_to_spaces(t.1))
         })
         .collect::<Vec<String>>()
         .join("\n");
@@ -0,0 +1,34 @@
+/// Hashes a string to a short base 36 string,
+/// matching the `hash` helper of the javascript compiler.
+pub fn hash(str: &str) -> String {
+    let str = str.replace('\r', "");
+    let mut hash: i32 = 5381;
+    for code in str.encode_utf16().collect::<Vec<u16>>().into_iter().rev() {
+        hash = hash.wrapping_shl(5).wrapping_sub(hash) ^ code as i32;
+    }
+
+    let mut value = hash as u32;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit(value % 36, 36).unwrap());
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+
+    digits.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(""), "45h");
+        assert_eq!(hash("\n\th1 { color: red; }\n"), "169zj74");
+        assert_eq!(hash("\r\n\th1 { color: red; }\r\n"), "169zj74");
+        assert_eq!(hash("é€"), "387k0");
+    }
+}
@@ -2,15 +2,18 @@ mod error;
 mod full_char_at;
 mod full_char_code_at;
 mod get_code_frame;
+mod hash;
 mod list;
-mod names;
-mod namespaces;
+pub mod names;
+pub mod namespaces;
 mod patterns;
-mod push_vec;
+mod warning;
 
 //re-exports
 pub use error::{CompileError, Location, NewErrorProps};
 pub use full_char_at::full_char_at;
 pub use full_char_code_at::full_char_code_at;
 pub use get_code_frame::get_code_frame;
+pub use hash::hash;
 pub use patterns::{DIMENSIONS, END_WHITESPACE, START_NEWLINE, START_WHITESPACE, WHITESPACE};
+pub use warning::Warning;
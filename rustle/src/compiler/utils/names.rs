@@ -144,9 +144,7 @@ pub fn sanitize(name: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{
-        ENDS_WITH_UNDERSCORE, LETTERS_AND_NUMBERS, STARTS_WITH_NUMBER, STARTS_WITH_UNDERSCORE,
-    };
+    use super::LETTERS_AND_NUMBERS;
 
     #[test]
     fn test_leters_and_numbers_regex() {
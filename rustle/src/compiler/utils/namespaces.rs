@@ -14,11 +14,11 @@ pub const VALID_NAMESPACES: [&str; 14] = [
 ];
 
 pub struct Namespaces {
-    foreign: String,
-    html: String,
-    mathml: String,
-    svg: String,
-    xlink: String,
-    xml: String,
-    xmlns: String,
+    pub foreign: String,
+    pub html: String,
+    pub mathml: String,
+    pub svg: String,
+    pub xlink: String,
+    pub xml: String,
+    pub xmlns: String,
 }
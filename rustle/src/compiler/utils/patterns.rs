@@ -11,7 +11,7 @@ lazy_static! {
 
 #[cfg(test)]
 mod tests {
-    use super::{DIMENSIONS, END_WHITESPACE, START_NEWLINE, START_WHITESPACE, WHITESPACE};
+    use super::{DIMENSIONS, END_WHITESPACE, START_WHITESPACE, WHITESPACE};
 
     #[test]
     fn test_whitespace_regex() {
@@ -0,0 +1,40 @@
+use serde::Serialize;
+
+use crate::compiler::utils::{CompileError, Location, NewErrorProps};
+
+/// A problem found while compiling that doesn't
+/// stop the component from being generated.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Warning {
+    pub code: String,
+    pub start: Location,
+    pub end: Location,
+    pub pos: usize,
+    pub filename: String,
+    pub frame: String,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(message: &str, props: NewErrorProps) -> Self {
+        let CompileError {
+            code,
+            start,
+            end,
+            pos,
+            filename,
+            frame,
+            message,
+        } = CompileError::new(message, props);
+
+        Self {
+            code,
+            start,
+            end,
+            pos,
+            filename,
+            frame,
+            message,
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod compiler;
 pub mod runtime;
 pub mod shared;
+
+pub use compiler::compile::{compile, CompileOptions, CompileResult, Generate};
@@ -0,0 +1,28 @@
+// The built-in animations, imported from 'rustle/animate'.
+//
+// An animation is called as `fn(node, { from, to }, params)` with the
+// bounding rects of the node before and after the keyed `{#each}` block
+// reordered it, and returns a transition running `t` from 0 to 1.
+
+import { cubicOut } from 'rustle/transition';
+
+export function flip(node, { from, to }, params = {}) {
+	const style = getComputedStyle(node);
+	const transform = style.transform === 'none' ? '' : style.transform;
+	const [ox, oy] = style.transformOrigin.split(' ').map(parseFloat);
+	const dx = from.left + (from.width * ox) / to.width - (to.left + ox);
+	const dy = from.top + (from.height * oy) / to.height - (to.top + oy);
+	const { delay = 0, duration = d => Math.sqrt(d) * 120, easing = cubicOut } = params;
+	return {
+		delay,
+		duration: typeof duration === 'function' ? duration(Math.sqrt(dx * dx + dy * dy)) : duration,
+		easing,
+		css: (t, u) => {
+			const x = u * dx;
+			const y = u * dy;
+			const sx = t + (u * from.width) / to.width;
+			const sy = t + (u * from.height) / to.height;
+			return `transform: ${transform} translate(${x}px, ${y}px) scale(${sx}, ${sy});`;
+		},
+	};
+}
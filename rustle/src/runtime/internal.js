@@ -0,0 +1,500 @@
+// The helpers the compiled components import from 'rustle/internal'.
+//
+// Every component shares this module, so it is emitted once next to them
+// rather than inlined in each one.
+
+export const VERSION = '0.1.0';
+
+// dom
+
+export function element(name) {
+	return document.createElement(name);
+}
+
+export function text(data) {
+	return document.createTextNode(data);
+}
+
+// the anchor the nodes of a block are inserted before
+export function comment() {
+	return document.createComment('');
+}
+
+export function attr(node, name, value) {
+	if (value == null) node.removeAttribute(name);
+	else if (node.getAttribute(name) !== value) node.setAttribute(name, value);
+}
+
+// sets the attributes of a spread, removing the ones no longer set
+export function set_attributes(node, previous, attributes) {
+	for (const key in previous) {
+		if (!(key in attributes)) node.removeAttribute(key);
+	}
+	for (const key in attributes) {
+		const value = attributes[key];
+		if (value == null) node.removeAttribute(key);
+		else if (key === 'class') node.className = value;
+		else if (key in node) node[key] = value;
+		else node.setAttribute(key, value);
+	}
+	return attributes;
+}
+
+// adds the listener and returns the function removing it
+export function listen(node, event, handler, options) {
+	node.addEventListener(event, handler, options);
+	return () => node.removeEventListener(event, handler, options);
+}
+
+export function append(target, node) {
+	if (hydration) insert_hydration(target, node, null);
+	else target.appendChild(node);
+}
+
+export function insert(target, node, anchor) {
+	if (hydration) insert_hydration(target, node, anchor);
+	else target.insertBefore(node, anchor || null);
+}
+
+export function detach(node) {
+	if (node.parentNode) node.parentNode.removeChild(node);
+}
+
+export function safe_not_equal(a, b) {
+	return a != a ? b == b : a !== b || (a && typeof a === 'object') || typeof a === 'function';
+}
+
+// hydration
+
+// The nodes claimed or created while hydrating, along with the cursors of the
+// parents they are claimed from: the next node to claim, and the end markers
+// of the blocks being claimed.
+let hydration = null;
+const claimed = new WeakSet();
+const created = new WeakSet();
+
+// starts claiming the nodes of `target`, unless the component is created
+// by another one, which claims its nodes along with it
+export function start_hydrating(target, nested, dev) {
+	if (hydration || nested || !target.firstChild) return false;
+	hydration = { cursors: new Map(), regions: new Map(), dev };
+	hydration.cursors.set(target, target.firstChild);
+	return true;
+}
+
+// removes the nodes of the server markup left unclaimed
+export function end_hydrating(started) {
+	if (!started) return;
+	for (const parent of hydration.cursors.keys()) {
+		for (const node of Array.from(parent.childNodes)) {
+			if (!claimed.has(node)) detach(node);
+		}
+	}
+	hydration = null;
+}
+
+// the next node to claim in `parent`, `anchor` when the nodes up to it are
+// claimed. The nodes of the blocks ended before `anchor` are skipped.
+function cursor(parent, anchor) {
+	const regions = hydration.regions.get(parent) || [];
+	while (regions.length && regions[regions.length - 1] !== anchor) {
+		hydration.cursors.set(parent, regions.pop().nextSibling);
+	}
+	const node = hydration.cursors.has(parent) ? hydration.cursors.get(parent) : parent.firstChild;
+	return node === undefined ? null : node;
+}
+
+// claims the first node of `parent` before `anchor` matching, looking
+// further than the next node with `search`
+function claim(parent, anchor, matches, search) {
+	// the anchor of a block missing from the markup
+	if (anchor && created.has(anchor)) return null;
+	for (let node = cursor(parent, anchor || null); node && node !== anchor; node = node.nextSibling) {
+		if (claimed.has(node)) continue;
+		if (matches(node)) {
+			claimed.add(node);
+			hydration.cursors.set(parent, node.nextSibling);
+			return node;
+		}
+		if (!search) break;
+	}
+	return null;
+}
+
+// warns about the nodes missing from the markup rendered on the server
+function mismatch(expected, parent, anchor) {
+	if (!hydration.dev || !hydration.cursors.has(parent) || (anchor && created.has(anchor))) return;
+	console.warn(`Hydration mismatch: expected ${expected} in`, parent);
+}
+
+function create(node) {
+	claimed.add(node);
+	created.add(node);
+	return node;
+}
+
+// the claimed nodes stay in place, the created ones are inserted before the next node to claim
+function insert_hydration(target, node, anchor) {
+	if (claimed.has(node) && node.parentNode === target) return;
+	claimed.add(node);
+	const next = hydration.cursors.has(target) && !(anchor && created.has(anchor)) ? cursor(target, anchor || null) : anchor;
+	target.insertBefore(node, next || null);
+}
+
+export function claim_element(parent, anchor, name) {
+	if (!hydration) return element(name);
+	const node = claim(parent, anchor, (node) => node.nodeType === 1 && node.nodeName.toLowerCase() === name.toLowerCase(), true);
+	if (!node) {
+		mismatch(`<${name}>`, parent, anchor);
+		return create(element(name));
+	}
+	hydration.cursors.set(node, node.firstChild);
+	return node;
+}
+
+// claims a text node, splitting the texts the html parser merged
+export function claim_text(parent, anchor, data) {
+	if (!hydration) return text(data);
+	const value = `${data}`;
+	// the empty texts are not in the markup
+	const node = value && claim(parent, anchor, (node) => node.nodeType === 3, false);
+	if (!node) {
+		if (value) mismatch(`"${value}"`, parent, anchor);
+		return create(text(data));
+	}
+	if (node.data.startsWith(value) && node.data !== value) {
+		node.splitText(value.length);
+		hydration.cursors.set(parent, node.nextSibling);
+	} else if (node.data !== value) {
+		mismatch(`"${value}"`, parent, anchor);
+		node.data = value;
+	}
+	return node;
+}
+
+// claims the end marker of a block rendered on the server as its anchor,
+// the nodes of the block are claimed next
+export function claim_comment(parent, anchor) {
+	if (!hydration) return comment();
+	const start = claim(parent, anchor, (node) => node.nodeType === 8 && node.data === '[', false);
+	let end = start && start.nextSibling;
+	for (let depth = 0; end; end = end.nextSibling) {
+		if (end.nodeType !== 8) continue;
+		if (end.data === '[') depth++;
+		else if (end.data === ']' && depth-- === 0) break;
+	}
+	if (!end) {
+		mismatch('a block', parent, anchor);
+		return create(comment());
+	}
+
+	detach(start);
+	claimed.add(end);
+	end.data = '';
+	if (!hydration.regions.has(parent)) hydration.regions.set(parent, []);
+	hydration.regions.get(parent).push(end);
+	return end;
+}
+
+// keyed each blocks
+
+// the blocks of a keyed each left in place when it is reordered, the longest
+// run of blocks still in the order of their `previous` indexes
+export function unmoved_blocks(blocks, previous) {
+	const kept = blocks.filter((block) => previous.has(block));
+	// the last block of the longest run of each length, and the block before each block
+	const tails = [];
+	const links = [];
+	kept.forEach((block, index) => {
+		let low = 0;
+		let high = tails.length;
+		while (low < high) {
+			const middle = (low + high) >> 1;
+			if (previous.get(kept[tails[middle]]) < previous.get(block)) low = middle + 1;
+			else high = middle;
+		}
+		links[index] = low > 0 ? tails[low - 1] : -1;
+		tails[low] = index;
+	});
+
+	const unmoved = new Set();
+	for (let index = tails.length ? tails[tails.length - 1] : -1; index !== -1; index = links[index]) {
+		unmoved.add(kept[index]);
+	}
+	return unmoved;
+}
+
+// scheduler
+
+const resolved_promise = Promise.resolve();
+const dirty_components = [];
+
+// updates the component in the next flush, along with the other components
+// changed until then, in the order they changed
+export function schedule_update(component) {
+	if (!dirty_components.length) resolved_promise.then(flush);
+	dirty_components.push(component);
+}
+
+export function flush() {
+	while (dirty_components.length) {
+		dirty_components.shift().$$flush();
+	}
+}
+
+// resolves once the pending changes are applied
+export function tick() {
+	return resolved_promise.then(flush);
+}
+
+// component
+
+// The base of the component lifecycles, the compiled component assigns
+// its `create`, `insert`, `update`, `$set` and `destroy` methods to it
+export class Component {
+	constructor() {
+		// the variables assigned since the last flush
+		this.$$dirty = [];
+		this.$$created = false;
+		// the callbacks the parent component registered with `$on`
+		this.$$callbacks = {};
+	}
+
+	$$invalidate(name, value) {
+		if (!this.$$created) return value;
+		if (!this.$$dirty.length) schedule_update(this);
+		if (!this.$$dirty.includes(name)) this.$$dirty.push(name);
+		return value;
+	}
+
+	$$flush() {
+		const changed = this.$$dirty;
+		this.$$dirty = [];
+		if (this.$$created) this.update(changed);
+	}
+
+	$$bubble(type, event) {
+		(this.$$callbacks[type] || []).slice().forEach(callback => callback(event));
+	}
+
+	$on(type, callback) {
+		const callbacks = this.$$callbacks[type] || (this.$$callbacks[type] = []);
+		callbacks.push(callback);
+		return () => {
+			const index = callbacks.indexOf(callback);
+			if (index !== -1) callbacks.splice(index, 1);
+		};
+	}
+}
+
+// transitions
+
+function hash(value) {
+	let hash = 5381;
+	for (let i = value.length; i--; ) hash = ((hash << 5) - hash) ^ value.charCodeAt(i);
+	return hash >>> 0;
+}
+
+function insert_keyframes(name, keyframes) {
+	let style = document.getElementById('rustle-keyframes');
+	if (!style) {
+		style = element('style');
+		style.id = 'rustle-keyframes';
+		append(document.head, style);
+	}
+	const rules = style.sheet.cssRules;
+	if (!Array.from(rules).some(rule => rule.name === name)) {
+		style.sheet.insertRule(`@keyframes ${name} ${keyframes}`, rules.length);
+	}
+}
+
+// runs the keyframes of `css(t, u)` on the node from `t = a` to `t = b`, calling
+// `tick(t, u)` every frame, the final style is kept when `keep` is set
+function run_transition(node, a, b, options, keep, done) {
+	const { delay = 0, duration = 300, easing = t => t, css, tick } = options || {};
+	let animation = '';
+	if (css && duration > 0) {
+		let keyframes = '{\n';
+		for (let p = 0; p < 1; p += 16.666 / duration) {
+			const t = a + (b - a) * easing(p);
+			keyframes += `${p * 100}%{${css(t, 1 - t)}}\n`;
+		}
+		keyframes += `100%{${css(b, 1 - b)}}\n}`;
+		const name = `__rustle_${hash(keyframes)}`;
+		insert_keyframes(name, keyframes);
+		animation = `${name} ${duration}ms linear ${delay}ms 1 both`;
+		node.style.animation = node.style.animation ? `${node.style.animation}, ${animation}` : animation;
+	}
+	const clear = () => {
+		if (!animation) return;
+		node.style.animation = node.style.animation.split(', ').filter(a => a !== animation).join(', ');
+	};
+
+	const start = performance.now() + delay;
+	let running = true;
+	if (tick) tick(a, 1 - a);
+	function loop(now) {
+		if (!running) return;
+		if (now >= start + duration) {
+			running = false;
+			if (tick) tick(b, 1 - b);
+			if (!keep) clear();
+			if (done) done();
+			return;
+		}
+		if (tick && now >= start) {
+			const t = a + (b - a) * easing((now - start) / duration);
+			tick(t, 1 - t);
+		}
+		requestAnimationFrame(loop);
+	}
+	requestAnimationFrame(loop);
+
+	return {
+		abort() {
+			if (!running) return;
+			running = false;
+			clear();
+		},
+	};
+}
+
+// plays the transition `fn` of the node, in or out, aborting the `previous` one
+export function transition(node, fn, params, intro, previous, done) {
+	if (previous) previous.abort();
+	const options = fn(node, params, { direction: intro ? 'in' : 'out' });
+	return intro
+		? run_transition(node, 0, 1, options, false, done)
+		: run_transition(node, 1, 0, options, true, done);
+}
+
+// animates the node from the rect it had before it moved
+export function animate(node, from, fn, params, previous) {
+	const to = node.getBoundingClientRect();
+	if (from.left === to.left && from.top === to.top && from.width === to.width && from.height === to.height) {
+		return previous;
+	}
+	if (previous) previous.abort();
+	return run_transition(node, 0, 1, fn(node, { from, to }, params), false);
+}
+
+// server side rendering
+
+const ATTRIBUTE_ESCAPES = { '"': '&quot;', '&': '&amp;', '<': '&lt;' };
+const CONTENT_ESCAPES = { '&': '&amp;', '<': '&lt;' };
+
+// escapes a value for the html content, or a double quoted attribute
+export function escape(value, is_attr = false) {
+	const escapes = is_attr ? ATTRIBUTE_ESCAPES : CONTENT_ESCAPES;
+	return String(value ?? '').replace(is_attr ? /["&<]/g : /[&<]/g, (char) => escapes[char]);
+}
+
+// the attribute as written in the html, `null` and `false` leave it out
+export function add_attribute(name, value) {
+	if (value == null || value === false) return '';
+	if (value === true) return ` ${name}`;
+	return ` ${name}="${escape(value, true)}"`;
+}
+
+// the attributes of an element with spreads, `classes` and `styles` are
+// the ones of its `class:` and `style:` directives
+export function spread(attributes, classes, styles) {
+	const attrs = Object.assign({}, attributes);
+	const names = classes.filter(Boolean);
+	if (names.length) attrs.class = [attrs.class, ...names].filter((name) => name != null).join(' ');
+	const declarations = Object.keys(styles)
+		.filter((name) => styles[name] != null)
+		.map((name) => `${name}: ${styles[name]};`);
+	if (declarations.length) attrs.style = [attrs.style, ...declarations].filter((style) => style != null).join(' ');
+
+	return Object.keys(attrs)
+		.map((name) => add_attribute(name, attrs[name]))
+		.join('');
+}
+
+// wraps the `$$render(result, props, slots)` of a server side component,
+// `render(props)` collects the css and the head of the components it renders
+export function create_ssr_component(fn) {
+	function $$render(result, props, slots) {
+		return fn(result, Object.assign({}, props, { $$slots: slots }), slots);
+	}
+
+	return {
+		render(props = {}) {
+			const result = { head: '', css: new Set() };
+			const html = $$render(result, props, {});
+			return {
+				html,
+				css: { code: Array.from(result.css).join('\n'), map: null },
+				head: result.head,
+			};
+		},
+		$$render,
+	};
+}
+
+// custom elements
+
+// wraps the component in a custom element class, the props are set from the
+// attributes and properties of the element, and the component is created
+// along with its css in the open shadow root of the element
+export function create_custom_element(component, props, css) {
+	class CustomElement extends HTMLElement {
+		static get observedAttributes() {
+			return props.map((name) => name.toLowerCase());
+		}
+
+		constructor() {
+			super();
+			this.attachShadow({ mode: 'open' });
+			this.$$props = {};
+			this.$$component = null;
+			// the properties set before the element was defined
+			for (const name of props) {
+				if (Object.prototype.hasOwnProperty.call(this, name)) {
+					this.$$props[name] = this[name];
+					delete this[name];
+				}
+			}
+		}
+
+		connectedCallback() {
+			if (this.$$component) return;
+			if (css) {
+				const style = element('style');
+				style.textContent = css;
+				append(this.shadowRoot, style);
+			}
+			this.$$component = component(Object.assign({}, this.$$props));
+			this.$$component.create(this.shadowRoot);
+		}
+
+		disconnectedCallback() {
+			// moving the element disconnects and connects it again right away
+			Promise.resolve().then(() => {
+				if (this.isConnected || !this.$$component) return;
+				this.$$component.destroy();
+				this.$$component = null;
+				this.shadowRoot.textContent = '';
+			});
+		}
+
+		attributeChangedCallback(attribute, previous, value) {
+			this[props.find((name) => name.toLowerCase() === attribute)] = value;
+		}
+	}
+
+	for (const name of props) {
+		Object.defineProperty(CustomElement.prototype, name, {
+			get() {
+				return this.$$props[name];
+			},
+			set(value) {
+				this.$$props[name] = value;
+				if (this.$$component) this.$$component.$set({ [name]: value });
+			},
+		});
+	}
+
+	return CustomElement;
+}
@@ -1 +1,25 @@
+//! The javascript modules the generated components import, to be
+//! served or bundled under their module name.
+//!
+//! ```
+//! use rustle::runtime::{ANIMATE, INTERNAL, TRANSITION, VERSION};
+//!
+//! assert!(INTERNAL.contains(&format!("export const VERSION = '{}';", VERSION)));
+//! assert!(TRANSITION.contains("export function fade"));
+//! assert!(ANIMATE.contains("export function flip"));
+//! ```
 
+/// The version of the runtime, the compiled components
+/// expect the runtime of the same version as the compiler
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// `rustle/internal`, the dom helpers, the update scheduler and the
+/// `Component` base of the lifecycles every compiled component imports
+pub const INTERNAL: &str = include_str!("internal.js");
+
+/// `rustle/transition`, the `fade`, `fly`, `slide` and `scale`
+/// transitions along with the `linear`, `cubicOut` and `cubicInOut` easings
+pub const TRANSITION: &str = include_str!("transition.js");
+
+/// `rustle/animate`, the `flip` animation of keyed `{#each}` items
+pub const ANIMATE: &str = include_str!("animate.js");
@@ -1,4 +1,5 @@
 mod boolean_attributes;
 mod utils;
 
+pub use boolean_attributes::BOOLEAN_ATTRIBUTES;
 pub use utils::is_void;
@@ -1,20 +1,36 @@
 use std::fs;
 
-use rustle::compiler::analyse::analyse;
-use rustle::compiler::generate::generate;
-use rustle::compiler::parse::Parser;
+use rustle::{compile, CompileOptions, Generate};
+use swc_common::{sync::Lrc, FileName, SourceMap};
+use swc_ecma_ast::EsVersion;
+use swc_ecma_parser::{parse_file_as_module, Syntax};
 
 fn test_parsing(path: String) {
     let source = fs::read_to_string(format!("tests/{}/app.rustle", path)).unwrap();
-    let ast = Parser::new(&source).parse();
-    let analysis = analyse(&ast);
-    let generated = generate(ast, analysis);
+    let result = compile(&source, CompileOptions::default()).unwrap();
+    if let Some(css) = result.css {
+        fs::write(format!("tests/{}/app.css", path), css.code).unwrap();
+    }
 
-    fs::write(format!("tests/{}/app.js", path), generated).unwrap();
-
-    assert!(true)
+    assert_module(&result.js.code);
+    fs::write(format!("tests/{}/app.js", path), result.js.code).unwrap();
 }
 
+/// Checks that the generated code is a valid javascript module
+fn assert_module(code: &str) {
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(FileName::Anon, code.into());
+
+    let mut errors = Vec::new();
+    let result = parse_file_as_module(
+        &fm,
+        Syntax::Es(Default::default()),
+        EsVersion::latest(),
+        None,
+        &mut errors,
+    );
+    assert!(result.is_ok() && errors.is_empty(), "invalid module:\n{}", code);
+}
 
 // in browser passed
 #[test]
@@ -54,16 +70,29 @@ fn test_parsing_hello() { test_parsing("hello".to_owned()) }
 #[test]
 fn test_parsing_reactive_assignments() { test_parsing("reactive-assignments".to_owned()) }
 
+// in browser passed
+#[test]
+fn test_parsing_reactive_declarations() {
+    test_parsing("reactive-declarations".to_owned());
+
+    let code = fs::read_to_string("tests/reactive-declarations/app.js").unwrap();
+    // `doubled` is computed before `quadrupled` reads it
+    assert!(code.contains("let quadrupled, doubled;"));
+    assert!(code.contains("$$reactive_0();\n$$reactive_1();\n$$reactive_2();"));
+    assert!(code.contains("function $$reactive_0() {\n    doubled = count * 2;\n}"));
+    assert!(code.contains(
+        "if (changed.includes('doubled')) {\n\t\t\t\t\t$$reactive_1();\n\t\t\t\t\tchanged = changed.concat([\"quadrupled\"]);"
+    ));
+}
+
 // in browser passed
 #[test]
 fn test_parsing_nested() {
     test_parsing("nested".to_owned());
     let source = fs::read_to_string("tests/nested/Nested.rustle").unwrap();
-    let ast = Parser::new(&source).parse();
-    let analysis = analyse(&ast);
-    let generated = generate(ast, analysis);
+    let result = compile(&source, CompileOptions::default()).unwrap();
 
-    fs::write("tests/nested/Nested.js", generated).unwrap();
+    fs::write("tests/nested/Nested.js", result.js.code).unwrap();
 }
 
 // in browser passed
@@ -75,10 +104,425 @@ fn test_tag_update() { test_parsing("tag_update".to_owned()) }
 fn test_component_update() {
     test_parsing("component_update".to_owned());
 
+    let code = fs::read_to_string("tests/component_update/app.js").unwrap();
+    // the attributes are the initial props, only the changed ones are set again
+    assert!(code.contains("Nested_3 = new Nested({ \"q\": out_q });"));
+    assert!(code.contains(
+        "const changes = {};\n\t\t\t\tif (changed.includes('out_q')) changes[\"q\"] = out_q;\n\t\t\t\tNested_3.$set(changes);"
+    ));
+
     let source = fs::read_to_string("tests/component_update/Nested.rustle").unwrap();
-    let ast = Parser::new(&source).parse();
-    let analysis = analyse(&ast);
-    let generated = generate(ast, analysis);
+    let result = compile(&source, CompileOptions::default()).unwrap();
+
+    assert_module(&result.js.code);
+    // `export let q = 0` defaults to 0 when the parent doesn't set it
+    assert!(result.js.code.contains("let { q =0  } = $$props;"));
+    assert!(result.js.code.contains(
+        "if ('q' in props && $$safe_not_equal(q, props.q)) $$invalidate('q', q = props.q);"
+    ));
+    fs::write("tests/component_update/Nested.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_if_block() {
+    test_parsing("if_block".to_owned());
+
+    let code = fs::read_to_string("tests/if_block/app.js").unwrap();
+    // the branches are tested in order, the else branch last
+    assert!(code.contains(
+        "if (count > 10) return create_if_block_4_0;\nif (count > 5) return create_if_block_4_1;\nreturn create_if_block_4_2;"
+    ));
+    assert!(code.contains("(changed.includes('count')) && select_if_block_4() !== if_block_4_current"));
+}
+
+// in browser passed
+#[test]
+fn test_each_block() {
+    test_parsing("each_block".to_owned());
+
+    let code = fs::read_to_string("tests/each_block/app.js").unwrap();
+    // the blocks are keyed by `todo.id` and the else block shows for an empty list
+    assert!(code.contains("(todo = context_value);\ni = context_index;"));
+    assert!(code.contains("function key_each_block_4(todo, i) {\n\t\t\treturn todo.id;"));
+    assert!(code.contains("const each_items = Array.from(todos.slice(done));"));
+    assert!(code.contains("if (each_items.length === 0 && !each_block_4_else) {"));
+    // only the blocks out of order are moved, before an empty text marking the next block
+    assert!(code.contains("each_block_4_first = $$text('');"));
+    assert!(code.contains("const each_unmoved = $$unmoved_blocks(each_blocks, each_previous);"));
+    assert!(code.contains("} else if (!each_unmoved.has(block)) {\n\t\t\t\t\tblock.insert(each_target, each_anchors[index]);"));
+    assert!(!code.contains("Cannot have duplicate keys"));
+
+    let source = fs::read_to_string("tests/each_block/app.rustle").unwrap();
+    let options = CompileOptions {
+        dev: true,
+        ..Default::default()
+    };
+    let result = compile(&source, options).unwrap();
+    assert_module(&result.js.code);
+    // duplicate keys throw in dev mode, before the blocks are updated
+    assert!(result.js.code.contains("if (each_indexes.has(key)) {\n\t\t\t\t\tthrow new Error(`Cannot have duplicate keys in a keyed each"));
+}
+
+// in browser passed
+#[test]
+fn test_await_block() {
+    test_parsing("await_block".to_owned());
+
+    let code = fs::read_to_string("tests/await_block/app.js").unwrap();
+    // a stale promise doesn't replace the block of the latest one
+    assert!(code.contains("const await_value = fetch_user(id);"));
+    assert!(code.contains(
+        "if (await_token === await_block_3_token) switch_await_block_3(create_await_block_3_then, value, true);"
+    ));
+    assert!(code.contains(
+        "if (await_token === await_block_3_token) switch_await_block_3(create_await_block_3_catch, error, true);"
+    ));
+    assert!(code.contains("if (changed.includes('id')) {\n\t\t\tupdate_await_block_3(true);"));
+    // a rejection without a catch branch isn't swallowed
+    assert!(!code.contains("switch_await_block_3(create_await_block_3_catch, error, true);\n\t\t\t\t\t\tthrow error;"));
+    assert!(code.contains(
+        "if (await_token === await_block_12_token) switch_await_block_12(null, error, true);\n\t\t\t\t\t\tthrow error;"
+    ));
+}
+
+// in browser passed
+#[test]
+fn test_style() {
+    test_parsing("style".to_owned());
+
+    let css = fs::read_to_string("tests/style/app.css").unwrap();
+    assert!(css.contains("p.intro.svelte-7bvhbe::first-line { color: red; }"));
+    assert!(css.contains(".box.svelte-7bvhbe, .box.svelte-7bvhbe > .inner.svelte-7bvhbe {"));
+    assert!(css.contains("body .active.svelte-7bvhbe { background: yellow; }"));
+    assert!(css.contains("button.svelte-7bvhbe:hover { color: blue; }"));
+
+    let code = fs::read_to_string("tests/style/app.js").unwrap();
+    // only the elements a selector can match get the scoping class
+    assert!(code.contains("h1_1.className = 'svelte-7bvhbe';"));
+    assert!(code.contains("div_7.className = (`${active ? 'box active' : 'box'}`) + ' svelte-7bvhbe';"));
+    assert!(!code.contains("span_9.className"));
+}
+
+// in browser passed
+#[test]
+fn test_bindings() {
+    test_parsing("bindings".to_owned());
+
+    let code = fs::read_to_string("tests/bindings/app.js").unwrap();
+    assert!(code.contains("$$invalidate('heading', heading = h1_1);"));
+    assert!(code.contains(
+        "$$listen(input_4, 'input', () => $$invalidate('name', name = input_4.value));"
+    ));
+    assert!(code.contains("age = input_5.value === '' ? null : +input_5.value"));
+    assert!(code.contains("$$listen(input_6, 'change', () => $$invalidate('agreed', agreed = input_6.checked));"));
+    assert!(code.contains("input_7.checked = input_7.value === flavour;"));
+    assert!(code.contains("toppings = input_9.checked ? [...toppings, input_9.value] : toppings.filter(item => item !== input_9.value)"));
+    assert!(code.contains("$$listen(select_11, 'change', () => $$invalidate('size', size = select_11.value));"));
+    assert!(code.contains(
+        "$$bound: { \"count\": $$value => { if (total !== $$value) $$invalidate('total', total = $$value); } }"
+    ));
+    // a binding to an item changes the list it belongs to
+    assert!(code.contains("() => $$invalidate('todos', todo.done = input_28.checked));"));
+    assert!(code.contains("() => $$invalidate('todos', todo.text = input_29.value));"));
+    assert!(code.contains(
+        "() => $$invalidate('names', names[each_block_30_index] = input_32.value));"
+    ));
+
+    let source = fs::read_to_string("tests/bindings/Counter.rustle").unwrap();
+    let result = compile(&source, CompileOptions::default()).unwrap();
+
+    assert_module(&result.js.code);
+    // the child reports its changes to the bound parent variable
+    assert!(result.js.code.contains(
+        "if (changed.includes('count') && $$props.$$bound && $$props.$$bound.count) $$props.$$bound.count(count);"
+    ));
+    fs::write("tests/bindings/Counter.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_events() {
+    test_parsing("events".to_owned());
+
+    let code = fs::read_to_string("tests/events/app.js").unwrap();
+    // the modifiers wrap the handler
+    assert!(code.contains(
+        "$$listen(form_4, 'submit', function (event) { event.preventDefault(); return log.call(this, event); });"
+    ));
+    assert!(code.contains(
+        "function (event) { event.stopPropagation(); return log.call(this, event); }, { once: true });"
+    ));
+    assert!(code.contains("function (event) { if (event.target !== this) return; return log.call(this, event); });"));
+    assert!(code.contains("Inner_8_click = Inner_8.$on('click', function (event) { return log.call(this, event); });"));
+    assert!(code.contains("Inner_8.$on('click', function (event) { Inner_8_click_9(); return"));
+
+    let source = fs::read_to_string("tests/events/Inner.rustle").unwrap();
+    let result = compile(&source, CompileOptions::default()).unwrap();
+
+    assert_module(&result.js.code);
+    // `on:click` without a handler forwards the event to the parent
+    assert!(result.js.code.contains(
+        "$$listen(button_1, 'click', function (event) { $$bubble('click', event); });"
+    ));
+    fs::write("tests/events/Inner.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_class_style() {
+    test_parsing("class_style".to_owned());
+
+    let code = fs::read_to_string("tests/class_style/app.js").unwrap();
+    assert!(code.contains("div_3.className = (`${kind}`) + ' svelte-wx8mc6';\ndiv_3.classList.toggle('active', !!(active));"));
+    assert!(code.contains("div_3.classList.toggle('big', !!(big));"));
+    assert!(code.contains("div_3.style.setProperty('width', `${width}px`);"));
+    assert!(code.contains(
+        "if (changed.includes('color')) {\n\t\t\t\tdiv_3.style.setProperty('color', color);"
+    ));
+    assert!(code.contains("div_3.style.setProperty('font-weight', active ? 'bold' : 'normal', 'important');"));
+}
+
+// in browser passed
+#[test]
+fn test_actions() {
+    test_parsing("actions".to_owned());
+
+    let code = fs::read_to_string("tests/actions/app.js").unwrap();
+    assert!(code.contains("input_1_focus_action = focus(input_1);"));
+    assert!(code.contains("p_3_tooltip_action = tooltip(p_3, text);"));
+    assert!(code.contains(
+        "if ((changed.includes('text')) && p_3_tooltip_action && typeof p_3_tooltip_action.update === 'function') {"
+    ));
+    assert!(code.contains(
+        "if (p_3_tooltip_action && typeof p_3_tooltip_action.destroy === 'function') p_3_tooltip_action.destroy();"
+    ));
+}
+
+// in browser passed
+#[test]
+fn test_slots() {
+    test_parsing("slots".to_owned());
+
+    let code = fs::read_to_string("tests/slots/app.js").unwrap();
+    // `let:active={selected}` renames the slot prop
+    assert!(code.contains("({ item: item, active: selected } = context_value);"));
+    assert!(code.contains(
+        "$$slots: { \"default\": create_Layout_1_slot_default, \"header\": create_Layout_1_slot_header }"
+    ));
+
+    let source = fs::read_to_string("tests/slots/Layout.rustle").unwrap();
+    let result = compile(&source, CompileOptions::default()).unwrap();
+
+    assert_module(&result.js.code);
+    // a slot the parent doesn't fill renders its fallback
+    assert!(result.js.code.contains(
+        "$$props.$$slots[\"header\"]({  }) : create_slot_2_fallback();"
+    ));
+    assert!(result.js.code.contains(
+        "$$props.$$slots[\"default\"]({ \"item\": item, \"active\": index === hovered }) : null;"
+    ));
+    fs::write("tests/slots/Layout.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_spread() {
+    test_parsing("spread".to_owned());
+
+    let code = fs::read_to_string("tests/spread/app.js").unwrap();
+    // the attributes after the spread win, the scoping class survives it
+    assert!(code.contains(
+        "div_1_attributes = $$set_attributes(div_1, div_1_attributes, { \"class\": `box`, ...attrs, \"title\": `Fixed` });\ndiv_1.classList.add('svelte-wx745y');"
+    ));
+    assert!(code.contains("Field_3_props = { ...config, \"required\": true };"));
+    // the props missing from the new spread are unset
+    assert!(code.contains("for (const key in Field_3_props) if (!(key in props)) changes[key] = undefined;"));
+
+    let source = fs::read_to_string("tests/spread/Field.rustle").unwrap();
+    let result = compile(&source, CompileOptions::default()).unwrap();
+
+    assert_module(&result.js.code);
+    assert!(result.js.code.contains("const declared = [\"label\"];"));
+    assert!(result.js.code.contains("{ \"type\": `text`, ...$$restProps }"));
+    fs::write("tests/spread/Field.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_tags() {
+    test_parsing("tags".to_owned());
+
+    let code = fs::read_to_string("tests/tags/app.js").unwrap();
+    assert!(code.contains("template.innerHTML = value == null ? '' : value;"));
+    assert!(code.contains("(area = box.width * box.height * scale);"));
+    assert!(code.contains("({ width  } = boxes[0]);"));
+    assert!(code.contains("if (changed.includes('box') || changed.includes('scale')) {"));
+    // `{@debug}` only logs in dev mode
+    assert!(!code.contains("debugger;"));
+
+    let source = fs::read_to_string("tests/tags/app.rustle").unwrap();
+    let options = CompileOptions {
+        dev: true,
+        ..Default::default()
+    };
+    let result = compile(&source, options).unwrap();
+    assert_module(&result.js.code);
+    assert!(result.js.code.contains("console.log({ scale });\ndebugger;"));
+}
+
+// in browser passed
+#[test]
+fn test_key_block() {
+    test_parsing("key_block".to_owned());
+
+    let code = fs::read_to_string("tests/key_block/app.js").unwrap();
+    // the block is recreated when `round` changes, updated otherwise
+    assert!(code.contains(
+        "if ((changed.includes('round')) && $$safe_not_equal(key_block_5_value, key_block_5_value = round)) {"
+    ));
+    assert!(code.contains("outro_block.outro(true, () => outro_block.destroy()); }\n\t\t\tkey_block_5 = create_key_block_5();"));
+    assert!(code.contains("if (key_block_5) key_block_5.update(changed);"));
+
+    let source = fs::read_to_string("tests/key_block/Counter.rustle").unwrap();
+    let result = compile(&source, CompileOptions::default()).unwrap();
+
+    assert_module(&result.js.code);
+    fs::write("tests/key_block/Counter.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_svelte_elements() {
+    test_parsing("svelte_elements".to_owned());
+
+    let code = fs::read_to_string("tests/svelte_elements/app.js").unwrap();
+    assert!(code.contains(
+        "window_1_innerWidth_binding_resize = $$listen(window_1, 'resize', window_1_innerWidth_binding);"
+    ));
+    assert!(code.contains("window_1.scrollTo(window_1.scrollX, y);"));
+    assert!(code.contains("body_2 = document.body;"));
+    assert!(code.contains("$$append(document.head, title_3);"));
+    assert!(code.contains("svelte_element_7 = $$element(svelte_element_6_value);"));
+    assert!(code.contains("svelte_component_12 = new svelte_component_11_value({ \"name\": `dynamic` });"));
+
+    for name in ["Red", "Blue", "Tree"] {
+        let source = fs::read_to_string(format!("tests/svelte_elements/{}.rustle", name)).unwrap();
+        let result = compile(&source, CompileOptions::default()).unwrap();
+
+        assert_module(&result.js.code);
+        fs::write(format!("tests/svelte_elements/{}.js", name), result.js.code).unwrap();
+    }
+
+    let code = fs::read_to_string("tests/svelte_elements/Tree.js").unwrap();
+    assert!(code.contains("svelte_self_4 = new $$self({ \"depth\": depth - 1 });"));
+}
+
+// in browser passed
+#[test]
+fn test_transitions() {
+    test_parsing("transitions".to_owned());
+
+    let code = fs::read_to_string("tests/transitions/app.js").unwrap();
+    // the generated parameters don't shadow the `done` variable
+    assert!(code.contains("outro($$local, $$done)"));
+    assert!(code.contains("duration: done\n}, false, p_8_transition, $$finish)"));
+    // local transitions only run with their own block, `|global` ones always
+    assert!(code.contains("if ($$local) { p_10_transition = $$transition(p_10, fly, {"));
+    assert!(code.contains(
+        "{ $$pending++; p_10_transition = $$transition(p_10, slide, {}, false, p_10_transition, $$finish); }"
+    ));
+    // the removed items play their outro before being destroyed
+    assert!(code.contains("each_lookup.forEach(block => block.outro(true, () => block.destroy()));"));
+    assert!(code.contains("li_14_animation = $$animate(li_14, from, flip, {"));
+    assert!(code.contains("const rect = each_rects.get(block);\n\t\t\t\tif (rect) block.animate(rect);"));
+}
+
+#[test]
+fn test_text() {
+    test_parsing("text".to_owned());
+
+    let source = fs::read_to_string("tests/text/app.rustle").unwrap();
+    let code = compile(&source, CompileOptions::default()).unwrap().js.code;
+    assert!(code.contains(r#"$$text("Press `Ctrl\\C`, not \\u00e9")"#));
+    assert!(code.contains(r#"$$text("It costs $")"#));
+}
+
+// in browser passed
+#[test]
+fn test_ssr() {
+    let options = CompileOptions {
+        generate: Generate::Ssr,
+        ..Default::default()
+    };
+    for name in ["app", "Card"] {
+        let source = fs::read_to_string(format!("tests/ssr/{}.rustle", name)).unwrap();
+        let result = compile(&source, options.clone()).unwrap();
+
+        assert_module(&result.js.code);
+        fs::write(format!("tests/ssr/{}.js", name), result.js.code).unwrap();
+    }
+
+    let code = fs::read_to_string("tests/ssr/app.js").unwrap();
+    assert!(code.contains("$$result.css.add(\"h1.svelte-bt9zrl {\\n\\t\\tcolor: red;\\n\\t}\\n\");"));
+    assert!(code.contains("${($$result.head += `<title>Hello ${$$escape(name)}</title>`, '')}"));
+    assert!(code.contains("<h1 class=\"title svelte-bt9zrl\">Hello ${$$escape(name)}!</h1>"));
+    assert!(code.contains("(count > 3) ? `<p>many</p>` : (count > 0) ? `<p>some</p>` : `<p>none</p>`"));
+    // the raw html is not escaped, the other expressions are
+    assert!(code.contains("${(html) ?? ''}"));
+    assert!(code.contains("<input${$$add_attribute(\"value\", name)}>"));
+    assert!(code.contains("Card.$$render($$result, { \"title\": name }, {"));
+
+    let code = fs::read_to_string("tests/ssr/Card.js").unwrap();
+    assert!(code.contains("${$$slots[\"footer\"] ? $$slots[\"footer\"]({}) : `Default footer`}"));
+}
+
+// in browser passed
+#[test]
+fn test_hydration() {
+    let source = fs::read_to_string("tests/hydration/app.rustle").unwrap();
+    let options = CompileOptions {
+        generate: Generate::Ssr,
+        ..Default::default()
+    };
+    let result = compile(&source, options).unwrap();
+    assert_module(&result.js.code);
+    // the blocks are wrapped in the comments their anchors claim
+    assert_eq!(result.js.code.matches("<!--[-->").count(), 2);
+    assert_eq!(result.js.code.matches("<!--]-->").count(), 2);
+    fs::write("tests/hydration/app.ssr.js", result.js.code).unwrap();
+
+    let options = CompileOptions {
+        hydratable: true,
+        dev: true,
+        ..Default::default()
+    };
+    let result = compile(&source, options).unwrap();
+    assert_module(&result.js.code);
+    assert!(result.js.code.contains("const $$hydrating = $$start_hydrating(target, $$props.$$nested, true);"));
+    assert!(result.js.code.contains("h1_1 = $$claim_element(target, anchor, 'h1');"));
+    assert!(result.js.code.contains("txt_3 = $$claim_text(h1_1, null, name);"));
+    assert!(result.js.code.contains("if_block_9_anchor = $$claim_comment(target, anchor);"));
+    assert!(result.js.code.contains("each_block_14_anchor = $$claim_comment(ul_13, null);"));
+    assert!(result.js.code.contains("$$end_hydrating($$hydrating);"));
+    fs::write("tests/hydration/app.js", result.js.code).unwrap();
+}
+
+// in browser passed
+#[test]
+fn test_custom_element() {
+    let source = fs::read_to_string("tests/custom_element/app.rustle").unwrap();
+    let options = CompileOptions {
+        custom_element: true,
+        ..Default::default()
+    };
+    let result = compile(&source, options).unwrap();
 
-    fs::write("tests/component_update/Nested.js", generated).unwrap();
+    assert_module(&result.js.code);
+    // the scoped css goes in the shadow root, the props become attributes
+    assert!(result.js.code.contains(
+        "$$create_custom_element($$self, [\"label\", \"step\"], \"button.svelte-6lxvbz {\\n\\t\\tfont-weight: bold;\\n\\t}\\n\");"
+    ));
+    assert!(result.js.code.contains("customElements.define(\"my-counter\", $$Element);\n\texport default $$Element;"));
+    fs::write("tests/custom_element/app.js", result.js.code).unwrap();
 }
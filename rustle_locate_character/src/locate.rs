@@ -12,7 +12,7 @@ pub fn locate<'a>(
 mod tests {
     use super::*;
 
-    const SAMPLE: &'static str = r#"A flea and a fly in a flue
+    const SAMPLE: &str = r#"A flea and a fly in a flue
 Were imprisoned, so what could they do?
 Said the fly, "let us flee!"
 "Let us fly!" said the flea.
@@ -87,8 +87,8 @@ fn locate_from_number(
     options: Option<Options>,
     current_index: &mut usize,
 ) -> Option<Location> {
-    let offset = options.map(|o| o.get_offset()).unwrap_or(Offset::default());
-    let line_ranges = get_line_ranges(&source);
+    let offset = options.map(|o| o.get_offset()).unwrap_or_default();
+    let line_ranges = get_line_ranges(source);
     let mut range = line_ranges.get(*current_index);
     let end = range.map(|r| r.end).unwrap_or(0);
 
@@ -96,14 +96,14 @@ fn locate_from_number(
 
     while let Some(r) = range {
         if range_contains(r, search) {
-            return Some(get_location(&r, search, offset));
+            return Some(get_location(r, search, offset));
         }
 
         if d == -1 {
             return None;
         }
 
-        *current_index = *current_index + d as usize;
+        *current_index += d as usize;
         range = line_ranges.get(*current_index);
     }
 
@@ -118,7 +118,7 @@ fn locate_from_string<'a>(
     current_index: &mut usize,
 ) -> Option<Location> {
     let start_index = start_index.unwrap_or(0);
-    let search = source[start_index..].find(&search).map(|i| i + start_index);
+    let search = source[start_index..].find(search).map(|i| i + start_index);
 
     if let Some(search) = search {
         return locate_from_number(source, search, options, current_index);
@@ -145,7 +145,7 @@ pub fn get_locator<'a>(
 mod tests {
     use super::*;
 
-    const SAMPLE: &'static str = r#"A flea and a fly in a flue
+    const SAMPLE: &str = r#"A flea and a fly in a flue
 Were imprisoned, so what could they do?
 Said the fly, "let us flee!"
 "Let us fly!" said the flea.